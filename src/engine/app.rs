@@ -21,6 +21,7 @@ pub struct App {
 
     // User input state
     pub keys: [bool; 256],
+    prev_keys: [bool; 256],
     pub mouse_x: i32,
     pub mouse_y: i32,
     pub mouse_rel_x: i32,
@@ -34,9 +35,16 @@ pub struct App {
     pub mouse_wheel: f32,
 }
 
+/// How the game's window is set up.
+pub struct WindowOptions {
+    pub size: nalgebra_glm::I32Vec2,
+    pub title: &'static str,
+    pub fullscreen: bool, //< Takes up the whole desktop, instead of being `size` big
+    pub vsync: bool,
+}
+
 pub fn run(
-    window_size: nalgebra_glm::I32Vec2,
-    window_title: &'static str,
+    window_options: WindowOptions,
     init: &dyn Fn(&App) -> RefCell<Box<dyn Scene>>,
 ) -> Result<(), String> {
     let sdl_context = sdl2::init()?;
@@ -48,12 +56,18 @@ pub fn run(
     gl_attr.set_context_version(3, 3);
     gl_attr.set_double_buffer(true);
 
-    let window = video_subsystem
-        .window(window_title, window_size.x as u32, window_size.y as u32)
-        .resizable()
-        .opengl()
-        .build()
-        .unwrap();
+    let mut window_builder = video_subsystem.window(
+        window_options.title,
+        window_options.size.x as u32,
+        window_options.size.y as u32,
+    );
+    window_builder.resizable().opengl();
+    if window_options.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().unwrap();
+    let (window_width, window_height) = window.size();
+    let window_size = nalgebra_glm::I32Vec2::new(window_width as i32, window_height as i32);
 
     let _gl_context = window.gl_create_context().unwrap();
 
@@ -62,13 +76,20 @@ pub fn run(
 
     window
         .subsystem()
-        .gl_set_swap_interval(SwapInterval::VSync)
+        .gl_set_swap_interval(if window_options.vsync {
+            SwapInterval::VSync
+        } else {
+            SwapInterval::Immediate
+        })
         .unwrap();
 
     unsafe {
         gl::Enable(gl::DEPTH_TEST);
         gl::DepthFunc(gl::LESS);
         gl::Enable(gl::CULL_FACE);
+        // Flat attributes come from a triangle's last vertex, which terrain meshes rely on to share vertices. Other
+        // meshes have a texture coordinate z of 0, so that the shader ignores their flat attributes
+        gl::ProvokingVertex(gl::LAST_VERTEX_CONVENTION);
         gl::Enable(gl::MULTISAMPLE);
         gl::Enable(gl::BLEND);
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
@@ -81,6 +102,7 @@ pub fn run(
         // sdl_context,
         running: true,
         keys: [false; 256],
+        prev_keys: [false; 256],
         mouse_x: 0,
         mouse_y: 0,
         mouse_rel_x: 0,
@@ -127,9 +149,22 @@ pub fn run(
             // );
             // sdl_context.mouse().set_relative_mouse_mode(true);
 
-            if let Some(scene_ref) = scene_stack.last() {
-                scene_ref.borrow_mut().update(&app);
+            let command = if let Some(scene_ref) = scene_stack.last() {
+                let command = scene_ref.borrow_mut().update(&app);
                 app.ticks += 1;
+                command
+            } else {
+                SceneCommand::None
+            };
+            match command {
+                SceneCommand::None => {}
+                SceneCommand::Push(scene) => scene_stack.push(RefCell::new(scene)),
+                SceneCommand::Pop => {
+                    scene_stack.pop();
+                    if scene_stack.is_empty() {
+                        app.running = false;
+                    }
+                }
             }
 
             if !scene_stale {
@@ -142,10 +177,11 @@ pub fn run(
 
         if !scene_stale {
             app.renderer.int_screen_resolution = app.window_size;
-            if let Some(scene_ref) = scene_stack.last() {
+            // Scenes are drawn bottom to top, so that menus can be drawn over the game
+            for scene_ref in &scene_stack {
                 scene_ref.borrow_mut().render(&app);
-                frames += 1;
             }
+            frames += 1;
             window.gl_swap_window();
         }
 
@@ -167,6 +203,7 @@ impl App {
         self.mouse_rel_x = 0;
         self.mouse_rel_y = 0;
         self.mouse_wheel = 0.0;
+        self.prev_keys = self.keys;
         self.prev_mouse_left_down = self.mouse_left_down;
         self.prev_mouse_right_down = self.mouse_right_down;
     }
@@ -232,10 +269,22 @@ impl App {
         self.mouse_left_clicked = !self.prev_mouse_left_down && self.mouse_left_down;
         self.mouse_right_clicked = !self.prev_mouse_right_down && self.mouse_right_down;
     }
+
+    /// Whether a key went down this tick, like `mouse_left_clicked` but for the keyboard.
+    pub fn key_pressed(&self, scancode: Scancode) -> bool {
+        self.keys[scancode as usize] && !self.prev_keys[scancode as usize]
+    }
 }
 
 pub trait Scene {
-    // TODO: Return a "command" enum so that scene's can affect App state
-    fn update(&mut self, app: &App);
+    /// Only the scene on the top of the stack is updated.
+    fn update(&mut self, app: &App) -> SceneCommand;
     fn render(&mut self, app: &App);
 }
+
+/// Returned by a scene's update to change the scene stack.
+pub enum SceneCommand {
+    None,
+    Push(Box<dyn Scene>), //< Put a new scene on top of this one, like a menu
+    Pop,                  //< Remove this scene, and go back to the one under it
+}
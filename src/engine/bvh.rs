@@ -16,11 +16,17 @@ pub type BVHNodeId = u32;
 
 pub const INVALID_BVH_NODE_ID: BVHNodeId = !0u32;
 
+/// Component for entities that own a leaf in a BVH<Entity>, so the leaf can be moved or removed later on.
+pub struct BVHNodeComponent {
+    pub node_id: BVHNodeId,
+}
+
 // TODO: Implement ray tracing query
 // TODO: Implement removing elements
 // TODO: Implement proxy volumes and movement
 struct BVHNode<Object: Copy + Clone> {
-    volume: AABB, // TODO: Could this be generalized?
+    volume: AABB,        // TODO: Could this be generalized?
+    object_volume: AABB, //< The leaf object's own volume, before it was fattened
     left: BVHNodeId,
     right: BVHNodeId,
     parent_id: BVHNodeId,
@@ -47,20 +53,28 @@ pub struct BVHRayIterator<'a, Object: Copy + Clone> {
     stack: Vec<BVHNodeId>,
 }
 
+pub struct BVHAABBIterator<'a, Object: Copy + Clone> {
+    bvh: &'a BVH<Object>, // Reference to the tree
+    aabb: &'a AABB,
+    stack: Vec<BVHNodeId>,
+}
+
 impl<Object: Copy + Clone> BVH<Object> {
     const AABB_EXTENSION: f32 = 0.1;
     const AABB_MULTIPLIER: f32 = 2.0;
+    const RNG_SEED: u64 = 0; //< The tree is shaped the same way every run, which makes bugs easier to reproduce
 
     pub fn new() -> Self {
         Self {
             nodes: vec![],
             root_id: INVALID_BVH_NODE_ID,
-            rng: rand::rngs::StdRng::from_entropy(),
+            rng: rand::rngs::StdRng::seed_from_u64(Self::RNG_SEED),
         }
     }
 
     pub fn insert(&mut self, object: Object, aabb: AABB) -> BVHNodeId {
         let proxy_id = self.allocate_node(object, aabb);
+        self.node_at_mut(proxy_id).object_volume = aabb;
 
         // Fatten the aabb.
         let r = nalgebra_glm::vec3(
@@ -95,6 +109,7 @@ impl<Object: Copy + Clone> BVH<Object> {
         assert!((proxy_id as usize) < self.nodes.len());
         assert!(self.node_at(proxy_id).is_leaf());
 
+        self.node_at_mut(proxy_id).object_volume = *aabb;
         if self.get_volume(proxy_id).intersects(aabb) {
             return false;
         }
@@ -246,7 +261,7 @@ impl<Object: Copy + Clone> BVH<Object> {
             self.adjust_bounds(grand_parent);
         } else {
             self.root_id = sibling;
-            self.set_parent(leaf, INVALID_BVH_NODE_ID);
+            self.set_parent(sibling, INVALID_BVH_NODE_ID);
             // TODO: Implement free-list, add parent to free-list
         }
     }
@@ -298,6 +313,21 @@ impl<Object: Copy + Clone> BVH<Object> {
         }
     }
 
+    /// Iterates over the objects whose own volumes overlap an AABB.
+    pub fn iter_aabb<'a>(&'a self, aabb: &'a AABB) -> BVHAABBIterator<'a, Object> {
+        let mut stack = Vec::new();
+
+        if self.root_id != INVALID_BVH_NODE_ID {
+            stack.push(self.root_id);
+        }
+
+        BVHAABBIterator {
+            bvh: self,
+            aabb,
+            stack,
+        }
+    }
+
     pub fn walk_tree(&self) {
         let mut stack = vec![];
         stack.push(self.root_id);
@@ -323,6 +353,7 @@ impl<Object: Copy + Clone> BVH<Object> {
         let node_index = self.nodes.len() as u32;
         let new_node = BVHNode::<Object> {
             volume: aabb,
+            object_volume: aabb,
             left: INVALID_BVH_NODE_ID,
             right: INVALID_BVH_NODE_ID,
             parent_id: INVALID_BVH_NODE_ID,
@@ -347,6 +378,7 @@ impl<Object: Copy + Clone> BVH<Object> {
         let x = self.rng.gen_range(0.0..1.0);
         let new_node = BVHNode::<Object> {
             volume: AABB::new(),
+            object_volume: AABB::new(),
             left: if x < 0.5 { left_id } else { right_id },
             right: if x < 0.5 { right_id } else { left_id },
             parent_id: parent,
@@ -502,3 +534,79 @@ impl<'a, Object: Copy + Clone> Iterator for BVHRayIterator<'a, Object> {
         None
     }
 }
+
+impl<'a, Object: Copy + Clone> Iterator for BVHAABBIterator<'a, Object> {
+    type Item = Object;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(current_id) = self.stack.pop() {
+            let current_node = self.bvh.node_at(current_id);
+            if !current_node.volume.intersects(self.aabb) {
+                continue;
+            }
+
+            if current_node.left != INVALID_BVH_NODE_ID {
+                self.stack.push(current_node.left);
+            }
+            if current_node.right != INVALID_BVH_NODE_ID {
+                self.stack.push(current_node.right);
+            }
+            // The fattened volume can overlap when the object itself doesn't
+            if let Some(object) = current_node.object {
+                if current_node.object_volume.intersects(self.aabb) {
+                    return Some(object);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aabb_queries_only_hit_objects_that_really_overlap() {
+        let mut bvh = BVH::new();
+        let cube = |x: f32| {
+            AABB::from_min_max(
+                nalgebra_glm::vec3(x, 0.0, 0.0),
+                nalgebra_glm::vec3(x + 1.0, 1.0, 1.0),
+            )
+        };
+        bvh.insert(1, cube(0.0));
+        let moved = bvh.insert(2, cube(3.0));
+        bvh.move_obj(moved, &cube(3.05), &nalgebra_glm::vec3(0.05, 0.0, 0.0));
+
+        let hits = |query: AABB| {
+            let mut hits: Vec<i32> = bvh.iter_aabb(&query).collect();
+            hits.sort();
+            hits
+        };
+        assert_eq!(hits(cube(0.5)), vec![1]);
+        assert_eq!(
+            hits(AABB::from_min_max(
+                nalgebra_glm::vec3(0.5, 0.0, 0.0),
+                nalgebra_glm::vec3(3.5, 1.0, 1.0),
+            )),
+            vec![1, 2]
+        );
+        // Within the fattened margins of both cubes, but touching neither
+        assert_eq!(
+            hits(AABB::from_min_max(
+                nalgebra_glm::vec3(1.05, 0.0, 0.0),
+                nalgebra_glm::vec3(2.98, 1.0, 1.0),
+            )),
+            Vec::<i32>::new()
+        );
+        // Where the second cube moved to, even though its leaf didn't need to move
+        assert_eq!(
+            hits(AABB::from_min_max(
+                nalgebra_glm::vec3(4.02, 0.0, 0.0),
+                nalgebra_glm::vec3(4.5, 1.0, 1.0),
+            )),
+            vec![2]
+        );
+    }
+}
@@ -95,6 +95,26 @@ impl Camera {
         Frustrum::from_inv_proj_view(self.inv_proj_view(), false)
     }
 
+    /// Projects a world-space point to screen-space pixel coordinates, with the origin at the top left. Returns None
+    /// if the point is behind the camera.
+    pub fn world_to_screen(
+        &self,
+        point: nalgebra_glm::Vec3,
+        screen_size: nalgebra_glm::Vec2,
+    ) -> Option<nalgebra_glm::Vec2> {
+        let clip = self.proj_matrix
+            * self.view_matrix
+            * nalgebra_glm::vec4(point.x, point.y, point.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.xy() / clip.w;
+        Some(nalgebra_glm::vec2(
+            (ndc.x + 1.0) * 0.5 * screen_size.x,
+            (1.0 - ndc.y) * 0.5 * screen_size.y,
+        ))
+    }
+
     pub fn set_position(&mut self, position: nalgebra_glm::Vec3) {
         self.position = position;
         self.regen_view_proj_matrices()
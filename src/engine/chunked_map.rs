@@ -1,205 +1,663 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
 use hecs::{Entity, World};
 use rand::{Rng, SeedableRng};
 
-use crate::scenes::gameplay::Rock;
+use crate::game::{
+    biomes::Biome,
+    farming::Crop,
+    items::{Item, ItemKind},
+    plants::BerryBush,
+    props::{all_props, spawn_prop, take_props, Prop},
+    trees::Tree,
+};
 
 use super::{
-    bvh::BVH,
-    perlin::{HeightMap, PerlinMap},
+    aabb::AABB,
+    bvh::{BVHNodeComponent, BVH},
+    perlin::{HeightMap, PerlinMap, EROSION_PADDING},
+    ray::Ray,
     render_core::{ModelComponent, RenderContext},
     sphere::Sphere,
+    worker_pool::WorkerPool,
 };
 
-#[derive(Default)]
+/// Height of the sea. Terrain below this is underwater.
+pub const WATER_LEVEL: f32 = 0.5;
+
+/// Version of the terrain generator. It goes up whenever a seed starts generating different terrain, so that saves of
+/// the old terrain can tell.
+pub const TERRAIN_VERSION: u32 = 2;
+/// Chunks at most this many chunks away from the player are generated and drawn, unless the map is told otherwise.
+pub const DEFAULT_RENDER_DISTANCE: usize = 8;
+/// Chunks further than this many chunks away from the player are unloaded, unless the map is told otherwise.
+pub const DEFAULT_UNLOAD_RADIUS: usize = 10;
+/// Chunks at most this many chunks away from the player have their props and animals, unless the map is told
+/// otherwise. The chunks past them are only terrain.
+pub const DEFAULT_PROP_DISTANCE: usize = 3;
+/// Cells that at least this much rain ran through during erosion have a river or lake in them.
+pub const RIVER_FLOW: f32 = 3.5;
+/// How far above the river bed the surface of a river or lake is.
+const RIVER_DEPTH: f32 = 0.04;
+/// Rivers and lakes are a little see-through, so that their beds show.
+const RIVER_TINT: nalgebra_glm::Vec4 = nalgebra_glm::Vec4::new(1.0, 1.0, 1.0, 0.8);
+/// How long each frame can spend putting generated chunks and rebuilt meshes into the world, and spawning props.
+const CHUNK_TIME_BUDGET: Duration = Duration::from_millis(4);
+/// Temperature changes slower than the terrain does, so that biomes span a few chunks.
+const TEMPERATURE_DETAIL: f32 = 0.25;
+/// Mixed into the world seed for the temperature, so that it doesn't follow the terrain.
+const TEMPERATURE_SEED: i32 = 7919;
+/// Chunks at least this many chunk widths away from the camera get meshes with half as many samples along each side,
+/// for each of these distances they're past.
+const LOD_DISTANCES: [f32; 3] = [2.0, 4.0, 8.0];
+/// A chunk's mesh only gets finer again once the camera is this many chunk widths back inside the LOD distance that
+/// made it coarser, so that a camera going back and forth across the distance doesn't rebuild the mesh every time.
+const LOD_HYSTERESIS: f32 = 0.5;
+/// How far below the lowest point of a chunk's edge its skirts hang down to.
+const SKIRT_MARGIN: f32 = 0.05;
+/// Whether terrain triangles are lit with normals that blend across the terrain, or with a normal of their own,
+/// unless a chunk is told otherwise.
+const TERRAIN_SHADING: Shading = Shading::Smooth;
+/// Texture coordinates with this z are the same all over a triangle, and come from its last vertex.
+const FLAT_UV: f32 = 1.0;
+/// Like `FLAT_UV`, but the triangle's normal comes from its last vertex too.
+const FLAT_UV_AND_NORMAL: f32 = 2.0;
+
+/// Farming state of a single terrain cell.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Soil {
+    pub tilled: bool,
+    pub watered: bool,
+    pub planted: bool,
+}
+
+/// Chunk coordinates, the position of a chunk divided by the width of a chunk.
+pub type ChunkCoord = nalgebra_glm::I32Vec2;
+
+#[derive(Clone)]
 pub struct Chunk {
     map: PerlinMap,
     hydration: PerlinMap,
+    temperature: PerlinMap,
+    normals: Vec<nalgebra_glm::Vec3>, //< One per cell corner, worked out with the neighboring chunks' heights
+    soil: Vec<Soil>,                  //< One per cell, allocated when the chunk is generated
     chunk_width: usize,
     pos: nalgebra_glm::Vec2,
-    generated: bool,
+    prop_coord: ChunkCoord, //< Coordinates the props are seeded from, which wrap around in a wrapping world
+    populated: bool, //< Whether the chunk's props and soil are in the world, only near the player
+    entity: Option<Entity>, //< The entity with the chunk's terrain mesh
+    water: Option<Entity>, //< The entity with the surface of the chunk's rivers and lakes, if it has any
+    mesh_step: usize, //< How many cells apart the mesh's samples are, it's coarser further from the camera
+    shading: Shading,
 
     level_of_detail: f32,
     seed: i32,
     amplitude: f32,
+    wrap: Option<usize>, //< Width the terrain repeats at, in a wrapping world
+}
+
+/// What's past the edges of the map.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MapEdges {
+    Bounded,   //< Nothing but sea
+    Wrapping,  //< The other side of the map, like walking around a planet
+    Unbounded, //< More terrain, for as far as anyone cares to walk
 }
 
+/// Everything needed to generate the same terrain again.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MapParams {
+    pub map_width: usize,
+    pub chunk_width: usize,
+    pub level_of_detail: f32,
+    pub seed: i32,
+    pub amplitude: f32,
+    pub edges: MapEdges,
+}
+
+/// What the player changed about a chunk, kept once it's been unloaded. Everything else about the chunk can be
+/// generated again from the seed.
 #[derive(Default)]
-pub struct ChunkedPerlinMap {
-    chunks: Vec<Chunk>,
-    map_width: usize,
-    chunk_width: usize,
+struct StoredChunk {
+    removed: Vec<usize>, //< Generated props that are gone or were changed, by their index in `place_props`
+    added: Vec<Prop>,    //< Props that weren't generated, including the changed ones
+    soil: Vec<(usize, Soil)>, //< Cells that were tilled or watered, by index
+}
 
-    level_of_detail: f32,
-    seed: i32,
-    amplitude: f32,
+/// What the player changed about the whole map, the way a save keeps it. Props and cells are where they'd be on the
+/// map, in case their chunk wrapped around.
+#[derive(Default)]
+pub struct MapChanges {
+    pub removed: Vec<(ChunkCoord, Vec<usize>)>, //< Generated props that are gone or were changed, by chunk and index
+    pub added: Vec<Prop>, //< Props that weren't generated, including the changed ones
+    pub soil: Vec<(nalgebra_glm::Vec2, Soil)>, //< Cells that were tilled, by their world-space origin
+}
+
+/// Indices, vertices, normals and UVs of a chunk's terrain mesh.
+pub type ChunkMesh = (Vec<u32>, Vec<f32>, Vec<f32>, Vec<f32>);
+
+/// How terrain triangles are lit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Shading {
+    Smooth,
+    Flat,
+}
+
+/// What a terrain triangle looks like. Every vertex of a terrain mesh says this about the triangles that end on it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Face {
+    uv: (f32, f32),
+    normal: Option<nalgebra_glm::Vec3>, //< Only when the terrain is flat shaded
+}
+
+/// A terrain mesh that's being built.
+#[derive(Default)]
+struct MeshBuilder {
+    indices: Vec<u32>,
+    vertices: Vec<f32>,
+    normals: Vec<f32>,
+    uv: Vec<f32>,
+}
+
+/// A chunk that was generated on a worker thread, but hasn't been put into the world yet.
+struct PreparedChunk {
+    coord: ChunkCoord,
+    chunk: Chunk,
+    mesh: ChunkMesh,
+    water: Option<ChunkMesh>,
 }
 
-struct Tree {}
+/// Something for the map's worker threads to do.
+enum ChunkJob {
+    Generate(ChunkCoord, Chunk),
+    Remesh(ChunkCoord, Chunk, usize), //< A copy of the chunk with its new mesh step, and the job's number
+}
+
+/// What the map's worker threads did.
+enum ChunkWork {
+    Generated(Box<PreparedChunk>),
+    Remeshed {
+        coord: ChunkCoord,
+        job: usize,
+        step: usize,
+        mesh: ChunkMesh,
+    },
+}
+
+/// Terrain made of chunks that are generated around the player as they walk, and unloaded once they're far enough
+/// away. Only the generated chunks are kept, so the map can be as big as it likes, or have no edges at all.
+pub struct ChunkedPerlinMap {
+    chunks: HashMap<ChunkCoord, Chunk>,       //< The generated chunks
+    stored: HashMap<ChunkCoord, StoredChunk>, //< By wrapped coordinates, with their props where they'd be on the map
+    workers: WorkerPool<ChunkJob, ChunkWork>,
+    pending: HashSet<ChunkCoord>, //< Chunks the workers are generating
+    remeshing: HashMap<ChunkCoord, (usize, usize)>, //< The number and step of the newest mesh job of each chunk
+    remesh_jobs: usize,                             //< How many mesh jobs there have been
+    render_distance: usize,
+    unload_radius: usize,
+    prop_distance: usize,
+    params: MapParams,
+}
 
 impl Chunk {
-    pub fn new(
-        chunk_width: usize,
-        pos: nalgebra_glm::Vec2,
-        level_of_detail: f32,
-        seed: i32,
-        amplitude: f32,
-    ) -> Self {
+    /// A chunk that hasn't been generated yet. In a wrapping world, it comes out the same as the chunk on the map
+    /// that it wraps around to.
+    pub fn new(params: &MapParams, coord: ChunkCoord) -> Self {
+        let chunk_width = params.chunk_width;
         Self {
             map: PerlinMap::new(chunk_width + 1),
             hydration: PerlinMap::new(chunk_width + 1),
+            temperature: PerlinMap::new(chunk_width + 1),
+            normals: vec![],
+            soil: vec![],
             chunk_width,
-            pos,
-            generated: false,
-            level_of_detail,
-            seed,
-            amplitude,
+            pos: params.chunk_pos(coord),
+            prop_coord: params.wrapped(coord),
+            populated: false,
+            entity: None,
+            water: None,
+            mesh_step: 1,
+            shading: TERRAIN_SHADING,
+            level_of_detail: params.level_of_detail,
+            seed: params.seed,
+            amplitude: params.amplitude,
+            wrap: params.wrap(),
         }
     }
 
-    pub fn generate(&mut self, renderer: &RenderContext, world: &mut World, bvh: &mut BVH<Entity>) {
-        if !self.generated {
-            self.map.generate(
-                self.level_of_detail,
-                10,
-                self.seed,
-                self.amplitude,
-                self.pos,
-            );
-            self.hydration
-                .generate(self.level_of_detail, 2, self.seed, self.amplitude, self.pos);
-
-            self.map.create_bulge();
-            self.map.create_shelf(0.6, 0.4);
-
-            let mut rng = rand::rngs::StdRng::from_entropy();
-            // self.map.erode(64, rand::Rng::gen(&mut rng));
-
-            let grass_texture = renderer.get_texture_id_from_name("grass").unwrap();
-            let tree_texture = renderer.get_texture_id_from_name("tree").unwrap();
-            let rock_texture = renderer.get_texture_id_from_name("rock").unwrap();
-            let tree_mesh = renderer.get_mesh_id_from_name("tree").unwrap();
-            let bush_mesh = renderer.get_mesh_id_from_name("bush").unwrap();
-            let cube_mesh = renderer.get_mesh_id_from_name("cube").unwrap();
-
-            let pos_with_z = nalgebra_glm::vec3(self.pos.x, self.pos.y, 0.0);
-            let (i, v, n, u) = self.create_mesh();
-            let grass_mesh = renderer.add_mesh_from_verts(i, vec![&v, &n, &u], None);
-            let chunk_entity = world.spawn((ModelComponent::new(
-                grass_mesh,
-                grass_texture,
+    /// Puts a chunk that was prepared by `prepare_chunk` into the world. Uploads its meshes, and spawns its terrain
+    /// and water entities. Its props are spawned separately, once the player is close enough.
+    fn add_to_world(
+        &mut self,
+        mesh: ChunkMesh,
+        water: Option<ChunkMesh>,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        let grass_texture = renderer.get_texture_id_from_name("grass").unwrap();
+        let pos_with_z = nalgebra_glm::vec3(self.pos.x, self.pos.y, 0.0);
+        let (i, v, n, u) = mesh;
+        let grass_mesh = renderer.add_mesh_from_verts(i, vec![&v, &n, &u], None);
+        let chunk_entity = world.spawn((ModelComponent::new(
+            grass_mesh,
+            grass_texture,
+            pos_with_z,
+            nalgebra_glm::vec3(1.0, 1.0, 1.0),
+        ),));
+        let node_id = bvh.insert(chunk_entity, self.aabb());
+        world
+            .insert_one(chunk_entity, BVHNodeComponent { node_id })
+            .unwrap();
+        self.entity = Some(chunk_entity);
+
+        if let Some((i, v, n, u)) = water {
+            let water_texture = renderer.get_texture_id_from_name("water").unwrap();
+            let (lowest, highest) = v
+                .chunks(3)
+                .fold((f32::MAX, f32::MIN), |(lowest, highest), vertex| {
+                    (lowest.min(vertex[2]), highest.max(vertex[2]))
+                });
+            let water_mesh = renderer.add_mesh_from_verts(i, vec![&v, &n, &u], None);
+            let mut model = ModelComponent::new(
+                water_mesh,
+                water_texture,
                 pos_with_z,
                 nalgebra_glm::vec3(1.0, 1.0, 1.0),
-            ),));
-            bvh.insert(
-                chunk_entity,
-                renderer.get_mesh_aabb(grass_mesh).translate(pos_with_z),
             );
+            model.tint = RIVER_TINT;
+            let water_entity = world.spawn((model,));
+            let width = self.chunk_width as f32;
+            let aabb = AABB::from_min_max(
+                nalgebra_glm::vec3(self.pos.x, self.pos.y, lowest),
+                nalgebra_glm::vec3(self.pos.x + width, self.pos.y + width, highest),
+            );
+            let node_id = bvh.insert(water_entity, aabb);
+            world
+                .insert_one(water_entity, BVHNodeComponent { node_id })
+                .unwrap();
+            self.water = Some(water_entity);
+        }
+    }
+
+    /// Spawns the chunk's props, and gives it its soil. Both come from the store if the player changed the chunk.
+    fn populate(
+        &mut self,
+        props: Vec<Prop>,
+        soil: &[(usize, Soil)],
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        self.populated = true;
+        for (index, soil) in soil {
+            self.soil[*index] = *soil;
+        }
+        if !soil.is_empty() {
+            self.rebuild_mesh(renderer, world);
+        }
+        for mut prop in props {
+            if let Prop::Crop { crop, position } = &mut prop {
+                if let Some(index) = self.soil_index(crop.cell - self.pos) {
+                    self.soil[index].planted = true;
+                }
+                // Saves only keep the cell a crop is in, so it's put on the terrain in the middle of the cell here
+                let center = crop.cell + nalgebra_glm::vec2(0.5, 0.5);
+                let height = self.height_interpolated(center - self.pos);
+                *position = nalgebra_glm::vec3(center.x, center.y, height);
+            }
+            spawn_prop(prop, renderer, world, bvh);
+        }
+    }
+
+    /// Takes the chunk's props out of the world, and clears its soil, leaving only the terrain. If the player changed
+    /// anything about the chunk, what's needed to put it back is returned. The mesh isn't rebuilt, so if the soil was
+    /// changed it's up to the caller to rebuild it.
+    fn depopulate(&mut self, world: &mut World, bvh: &mut BVH<Entity>) -> Option<StoredChunk> {
+        let chunk_size = nalgebra_glm::vec2(self.chunk_width as f32, self.chunk_width as f32);
+        let props = take_props(world, bvh, self.pos, self.pos + chunk_size);
+        if !self.populated {
+            // Only animals wander into chunks without props
+            return None;
+        }
+        let changes = self.changes(props);
+        self.populated = false;
+        self.soil.fill(Soil::default());
+        changes
+    }
+
+    /// Takes the chunk's terrain and props out of the world, and frees its meshes. If the player changed anything
+    /// about the chunk, what's needed to put it back is returned.
+    fn unload(
+        mut self,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) -> Option<StoredChunk> {
+        let changes = self.depopulate(world, bvh);
+
+        for entity in [self.entity.take(), self.water.take()]
+            .into_iter()
+            .flatten()
+        {
+            let mesh_id = world.get::<&ModelComponent>(entity).unwrap().mesh_id;
+            renderer.remove_mesh(mesh_id);
+            if let Ok(bvh_node) = world.get::<&BVHNodeComponent>(entity) {
+                bvh.remove(bvh_node.node_id);
+            }
+            world.despawn(entity).unwrap();
+        }
+
+        changes
+    }
+
+    /// What the player changed about the chunk, given the props that are on it now, or None if it's still the way it
+    /// was generated. Animals wander around, so they don't count.
+    fn changes(&self, props: Vec<Prop>) -> Option<StoredChunk> {
+        let soil: Vec<(usize, Soil)> = self
+            .soil
+            .iter()
+            .enumerate()
+            .filter(|(_, soil)| soil.tilled || soil.watered)
+            .map(|(index, soil)| (index, *soil))
+            .collect();
+        let mut unmatched: Vec<(usize, Prop)> = self
+            .place_props()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, prop)| !prop.is_animal())
+            .collect();
+        let mut added = vec![];
+        for prop in props {
+            match unmatched
+                .iter()
+                .position(|(_, generated)| prop.unchanged_from(generated))
+            {
+                Some(index) => {
+                    unmatched.swap_remove(index);
+                }
+                None => added.push(prop),
+            }
+        }
+        let mut removed: Vec<usize> = unmatched.into_iter().map(|(index, _)| index).collect();
+        removed.sort_unstable();
+
+        let modified = !soil.is_empty() || !removed.is_empty() || !added.is_empty();
+        modified.then_some(StoredChunk {
+            removed,
+            added,
+            soil,
+        })
+    }
+
+    /// Generates the chunk's heightmaps, normals, hydration and temperature, and clears its soil.
+    fn generate_terrain(&mut self) {
+        // The terrain is generated with some more around it to erode, so that the rivers line up across chunk borders.
+        // One more cell is kept around the chunk after eroding, so that the normals on the border come out the same as
+        // the neighboring chunk's
+        let padding = EROSION_PADDING + 1;
+        let mut map = PerlinMap::new(self.chunk_width + 1 + 2 * padding);
+        map.generate(
+            self.level_of_detail,
+            10,
+            self.seed,
+            self.amplitude,
+            self.pos - nalgebra_glm::vec2(padding as f32, padding as f32),
+            self.wrap,
+        );
+        map.create_bulge();
+        map.create_shelf(0.6, 0.4);
+        map.erode(
+            nalgebra_glm::I32Vec2::new(
+                self.pos.x as i32 - padding as i32,
+                self.pos.y as i32 - padding as i32,
+            ),
+            self.seed,
+            self.wrap,
+        );
+        let apron = map.cropped(EROSION_PADDING, EROSION_PADDING, self.chunk_width + 3);
+        self.normals = (0..=self.chunk_width)
+            .flat_map(|y| (0..=self.chunk_width).map(move |x| (x, y)))
+            .map(|(x, y)| apron.vertex_normal(x + 1, y + 1))
+            .collect();
+        self.map = apron.cropped(1, 1, self.chunk_width + 1);
+
+        self.hydration.generate(
+            self.level_of_detail,
+            2,
+            self.seed,
+            self.amplitude,
+            self.pos,
+            self.wrap,
+        );
+        self.temperature.generate(
+            self.level_of_detail * TEMPERATURE_DETAIL,
+            2,
+            self.seed.wrapping_add(TEMPERATURE_SEED),
+            self.amplitude,
+            self.pos,
+            self.wrap,
+        );
+
+        self.soil = vec![Soil::default(); self.chunk_width * self.chunk_width];
+    }
+
+    /// Decides where the rocks, trees, bushes, crops and animals of the chunk go. How many of each there are depends on
+    /// the biome they'd be in. The layout only depends on the world seed and the chunk's position, so a chunk always
+    /// comes out the same in the same world.
+    fn place_props(&self) -> Vec<Prop> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(chunk_seed(
+            self.seed,
+            self.prop_coord.x,
+            self.prop_coord.y,
+        ));
+        let mut props = vec![];
+
+        for _ in 0..8 {
+            // Add all the rocks
+            let mut position = nalgebra_glm::vec3(
+                rng.gen_range(0..self.chunk_width) as f32,
+                rng.gen_range(0..self.chunk_width) as f32,
+                0.0,
+            );
+            let chance: f32 = rng.gen();
+            position.z = self.map.get_z_interpolated(position.xy());
+            if position.z < WATER_LEVEL || chance >= self.biome(position.xy()).rock_density() {
+                continue;
+            }
+            position.x += self.pos.x;
+            position.y += self.pos.y;
+            props.push(Prop::Rock { position });
+        }
 
-            // TODO: This should be OUT!
+        // Only trees in this chunk are kept apart, so that the layout doesn't depend on which chunks were generated
+        // first
+        let mut trees: Vec<(nalgebra_glm::Vec3, f32)> = vec![];
+        for _ in 0..10 {
+            // Add all the trees
+            let pos = nalgebra_glm::vec2(
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+            );
+            let height = self.map.get_z_interpolated(pos);
+            let variation: f32 = rng.gen_range(0.0..1.0);
+            let chance: f32 = rng.gen();
+            let scale = 1.4 + 1.0 * variation;
+            let scale_vec = nalgebra_glm::vec3(scale, scale, scale * 0.8);
+            let position = nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height);
+            if height >= 1.0
+                && !self.has_fresh_water(pos)
+                && chance < self.biome(pos).tree_density()
+                && !overlaps_tree(&trees, position, scale)
+            {
+                trees.push((position, scale_vec.x));
+                props.push(Prop::Tree {
+                    position,
+                    scale: scale_vec,
+                    health: Tree::new().health,
+                });
 
-            for _ in 0..4 {
-                // Add all the rocks
-                let mut position = nalgebra_glm::vec3(
-                    rng.gen_range(0..self.chunk_width) as f32,
-                    rng.gen_range(0..self.chunk_width) as f32,
+                // Trees drop sticks around them. They're kept on the chunk, so that they're unloaded with it
+                let stick_xy = nalgebra_glm::clamp(
+                    &(pos + nalgebra_glm::vec2(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))),
                     0.0,
+                    self.chunk_width as f32 - 1.0,
                 );
-                let scale = 0.2;
-                let scale_vec = nalgebra_glm::vec3(scale, scale, scale);
-                position.z = self.map.get_z_interpolated(position.xy());
-                if position.z < 1.0 {
-                    continue;
+                let stick_height = self.map.get_z_interpolated(stick_xy);
+                if stick_height >= 1.0 {
+                    props.push(Prop::Item {
+                        item: Item::new(ItemKind::Stick),
+                        position: nalgebra_glm::vec3(
+                            stick_xy.x + self.pos.x,
+                            stick_xy.y + self.pos.y,
+                            stick_height,
+                        ),
+                    });
                 }
-                position.x += self.pos.x;
-                position.y += self.pos.y;
-                let rock_entity = world.spawn((
-                    ModelComponent::new(cube_mesh, rock_texture, position, scale_vec),
-                    Rock {},
-                ));
-                bvh.insert(
-                    rock_entity,
-                    renderer
-                        .get_mesh_aabb(cube_mesh)
-                        .scale(scale_vec * 0.5)
-                        .translate(position),
-                );
             }
+        }
 
-            for _ in 0..4 {
-                // Add all the trees
-                let pos = nalgebra_glm::vec2(
-                    rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
-                    rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
-                );
-                let height = self.map.get_z_interpolated(pos);
-                let hydro_normal = self.hydration.get_normal(pos);
-                let variation: f32 = rng.gen_range(0.0..1.0);
-                let scale = (1.4 + 1.0 * variation);
-                let scale_vec = nalgebra_glm::vec3(scale, scale, scale * 0.8);
-                let position = nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height);
-                if height >= 1.0
-                    && hydro_normal.y > 0.0
-                    && 0.5 < self.hydration.height(pos)
-                    && bvh
-                        .iter_sphere(&Sphere::new(position, scale))
-                        .filter(|entity| world.get::<&Tree>(*entity).is_ok())
-                        .count()
-                        == 0
-                {
-                    let tree_entity = world.spawn((
-                        ModelComponent::new(tree_mesh, tree_texture, position, scale_vec),
-                        Tree {},
-                    ));
-                    bvh.insert(
-                        tree_entity,
-                        renderer
-                            .get_mesh_aabb(cube_mesh)
-                            .scale(scale_vec)
-                            .translate(position),
-                    );
-                }
+        for _ in 0..3 {
+            // Add clay in the mud
+            let pos = nalgebra_glm::vec2(
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+            );
+            let chance: f32 = rng.gen();
+            let height = self.map.get_z_interpolated(pos);
+            if height < WATER_LEVEL || chance >= self.biome(pos).clay_density() {
+                continue;
             }
+            props.push(Prop::Item {
+                item: Item::new(ItemKind::Clay),
+                position: nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height),
+            });
+        }
 
-            for _ in 0..4 {
-                // Add all the bushes
-                let pos = nalgebra_glm::vec2(
-                    rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
-                    rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
-                );
-                let height = self.map.get_z_interpolated(pos);
-                let hydro_normal = self.hydration.get_normal(pos);
-                let variation: f32 = rng.gen_range(0.0..1.0);
-                let scale = (0.4 + 1.0 * variation);
-                let scale_vec = nalgebra_glm::vec3(scale, scale, scale * 0.8);
-                let position = nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height);
-                if height >= 1.0
-                    && variation < (hydro_normal.y + 0.5) * 1.0
-                    && bvh
-                        .iter_sphere(&Sphere::new(position, scale))
-                        .filter(|entity| world.get::<&Tree>(*entity).is_ok())
-                        .count()
-                        == 0
-                {
-                    let tree_entity = world.spawn((ModelComponent::new(
-                        bush_mesh,
-                        tree_texture,
-                        position,
-                        scale_vec,
-                    ),));
-                    bvh.insert(
-                        tree_entity,
-                        renderer
-                            .get_mesh_aabb(bush_mesh)
-                            .scale(scale_vec)
-                            .translate(position),
-                    );
+        for _ in 0..6 {
+            // Add all the bushes
+            let pos = nalgebra_glm::vec2(
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+            );
+            let height = self.map.get_z_interpolated(pos);
+            let variation: f32 = rng.gen_range(0.0..1.0);
+            let chance: f32 = rng.gen();
+            let scale = 0.4 + 1.0 * variation;
+            let scale_vec = nalgebra_glm::vec3(scale, scale, scale * 0.8);
+            let position = nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height);
+            if height >= 1.0
+                && !self.has_fresh_water(pos)
+                && chance < self.biome(pos).bush_density()
+                && !overlaps_tree(&trees, position, scale)
+            {
+                props.push(Prop::BerryBush {
+                    bush: BerryBush::new(rng.gen_range(0..=3)),
+                    position,
+                    scale: scale_vec,
+                });
+            }
+        }
+
+        let mut planted = vec![];
+        for _ in 0..2 {
+            // Add some wild crops, if anything grows wild in the biome
+            let cell = nalgebra_glm::vec2(
+                rng.gen_range(0..self.chunk_width) as f32,
+                rng.gen_range(0..self.chunk_width) as f32,
+            );
+            let center = cell + nalgebra_glm::vec2(0.5, 0.5);
+            let height = self.map.get_z_interpolated(center);
+            let Some(kind) = self.biome(center).wild_crop() else {
+                continue;
+            };
+            if height < 1.0
+                || self.map.get_dot_prod(center) < 0.9
+                || self.has_fresh_water(center)
+                || planted.contains(&cell)
+            {
+                continue;
+            }
+            planted.push(cell);
+            props.push(Prop::Crop {
+                crop: Crop::new(kind, kind.stages() - 1, cell + self.pos),
+                position: nalgebra_glm::vec3(center.x + self.pos.x, center.y + self.pos.y, height),
+            });
+        }
+
+        {
+            // Maybe add an animal, if anything lives in the biome
+            let pos = nalgebra_glm::vec2(
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+                rng.gen::<f32>() * (self.chunk_width as f32 - 1.0),
+            );
+            let height = self.map.get_z_interpolated(pos);
+            if let Some((kind, chance)) = self.biome(pos).animal() {
+                if height >= 1.0 && self.map.get_dot_prod(pos) >= 0.8 && rng.gen_bool(chance) {
+                    let position =
+                        nalgebra_glm::vec3(pos.x + self.pos.x, pos.y + self.pos.y, height);
+                    props.push(Prop::Animal { kind, position });
                 }
             }
+        }
 
-            self.generated = true;
+        props
+    }
+
+    fn soil(&self, p: nalgebra_glm::Vec2) -> Option<Soil> {
+        let index = self.soil_index(p)?;
+        Some(self.soil[index])
+    }
+
+    /// Changes the soil of a cell, and rebuilds the chunk's mesh if that changes how the cell looks.
+    fn set_soil(
+        &mut self,
+        p: nalgebra_glm::Vec2,
+        soil: Soil,
+        renderer: &RenderContext,
+        world: &mut World,
+    ) {
+        let Some(index) = self.soil_index(p) else {
+            return;
+        };
+        let old_soil = self.soil[index];
+        self.soil[index] = soil;
+        if old_soil.tilled != soil.tilled || old_soil.watered != soil.watered {
+            self.rebuild_mesh(renderer, world);
         }
     }
 
+    fn soil_index(&self, p: nalgebra_glm::Vec2) -> Option<usize> {
+        if !self.populated
+            || p.x < 0.0
+            || p.y < 0.0
+            || p.x >= self.chunk_width as f32
+            || p.y >= self.chunk_width as f32
+        {
+            return None;
+        }
+        Some(p.y as usize * self.chunk_width + p.x as usize)
+    }
+
+    fn rebuild_mesh(&self, renderer: &RenderContext, world: &mut World) {
+        self.replace_mesh(self.create_mesh(), renderer, world);
+    }
+
+    /// Uploads a new terrain mesh for the chunk, and frees the old one.
+    fn replace_mesh(&self, mesh: ChunkMesh, renderer: &RenderContext, world: &mut World) {
+        let Some(entity) = self.entity else {
+            return;
+        };
+        let (i, v, n, u) = mesh;
+        let mesh = renderer.add_mesh_from_verts(i, vec![&v, &n, &u], None);
+        let mut model = world.get::<&mut ModelComponent>(entity).unwrap();
+        renderer.remove_mesh(model.mesh_id);
+        model.mesh_id = mesh;
+    }
+
     fn pos(&self) -> nalgebra_glm::Vec2 {
         self.pos
     }
@@ -220,110 +678,436 @@ impl Chunk {
         self.map.flow(p)
     }
 
-    fn create_mesh(&self) -> (Vec<u32>, Vec<f32>, Vec<f32>, Vec<f32>) {
-        let mut indices = Vec::<u32>::new();
-        let mut vertices = Vec::<f32>::new();
-        let mut normals = Vec::<f32>::new();
-        let mut uv = Vec::<f32>::new();
+    /// Smooth normal of the terrain at a cell corner.
+    fn vertex_normal(&self, p: nalgebra_glm::Vec2) -> nalgebra_glm::Vec3 {
+        self.normals[p.y as usize * (self.chunk_width + 1) + p.x as usize]
+    }
 
-        let mut i = 0;
-        for y in 0..self.chunk_width {
-            let y = y;
-            for x in 0..self.chunk_width {
-                let x = x;
-                // Left triangle |\
-                let offsets = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
-                self.add_triangle(
-                    &mut indices,
-                    &mut vertices,
-                    &mut normals,
-                    &mut uv,
-                    x as f32,
-                    y as f32,
-                    &offsets,
-                    &mut i,
-                );
+    /// The biome of the cell at a position.
+    fn biome(&self, p: nalgebra_glm::Vec2) -> Biome {
+        let cell = nalgebra_glm::floor(&p);
+        Biome::classify(
+            self.map.height(cell),
+            self.hydration.height(cell),
+            self.temperature.height(cell),
+        )
+    }
 
-                // Right triangle \|
-                let offsets = vec![(1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
-                self.add_triangle(
-                    &mut indices,
-                    &mut vertices,
-                    &mut normals,
-                    &mut uv,
-                    x as f32,
-                    y as f32,
-                    &offsets,
-                    &mut i,
+    /// Whether a river or lake runs through the cell at a position.
+    fn has_fresh_water(&self, p: nalgebra_glm::Vec2) -> bool {
+        let cell = nalgebra_glm::floor(&p);
+        self.map.flow(cell) >= RIVER_FLOW && self.map.height(cell) >= WATER_LEVEL
+    }
+
+    /// Builds the chunk's terrain mesh, with a sample every `mesh_step` cells. The samples are shared by the triangles
+    /// around them. A triangle's texture band, and its normal if the terrain is flat shaded, come from its last vertex
+    /// only (OpenGL's provoking vertex), so a sample is only copied when two triangles that end on it look different.
+    /// Skirts hang down from the mesh's edges, to hide the cracks between chunks whose meshes have different steps.
+    fn create_mesh(&self) -> ChunkMesh {
+        let mut mesh = MeshBuilder::default();
+        let step = self.mesh_step;
+        let cells = self.chunk_width / step;
+        let samples = cells + 1;
+        let corner = |i: usize, j: usize| nalgebra_glm::vec2((i * step) as f32, (j * step) as f32);
+        let index = |i: usize, j: usize| (j * samples + i) as u32;
+
+        // Each cell is split into a left |\ triangle that ends on the cell's origin, and a right \| triangle that ends
+        // on the corner across from it
+        let left = |i: usize, j: usize| [corner(i + 1, j), corner(i, j + 1), corner(i, j)];
+        let right = |i: usize, j: usize| [corner(i, j + 1), corner(i + 1, j), corner(i + 1, j + 1)];
+        let faces: Vec<(Face, Face)> = (0..cells)
+            .flat_map(|j| (0..cells).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                (
+                    self.face(corner(i, j), left(i, j)),
+                    self.face(corner(i, j), right(i, j)),
+                )
+            })
+            .collect();
+
+        // Every sample looks like the left triangle of the cell it's the origin of, or else like the right triangle of
+        // the cell it's across from. The two corners that don't end any triangles can look like anything
+        let mut sample_faces = vec![];
+        for j in 0..samples {
+            for i in 0..samples {
+                let face = if i < cells && j < cells {
+                    faces[j * cells + i].0
+                } else if i > 0 && j > 0 {
+                    faces[(j - 1) * cells + i - 1].1
+                } else {
+                    faces[0].0
+                };
+                mesh.add_vertex(
+                    self.sample_position(corner(i, j)),
+                    self.vertex_normal(corner(i, j)),
+                    face,
                 );
+                sample_faces.push(face);
+            }
+        }
+
+        for j in 0..cells {
+            for i in 0..cells {
+                let right_face = faces[j * cells + i].1;
+                mesh.indices
+                    .extend([index(i + 1, j), index(i, j + 1), index(i, j)]);
+
+                let far = corner(i + 1, j + 1);
+                let far_index = if sample_faces[index(i + 1, j + 1) as usize] == right_face {
+                    index(i + 1, j + 1)
+                } else {
+                    mesh.add_vertex(
+                        self.sample_position(far),
+                        self.vertex_normal(far),
+                        right_face,
+                    )
+                };
+                mesh.indices
+                    .extend([index(i, j + 1), index(i + 1, j), far_index]);
             }
         }
+        self.add_skirts(&mut mesh);
 
-        (indices, vertices, normals, uv)
+        mesh.finish()
     }
 
-    fn add_triangle(
-        &self,
-        indices: &mut Vec<u32>,
-        vertices: &mut Vec<f32>,
-        normals: &mut Vec<f32>,
-        uv: &mut Vec<f32>,
-        x: f32,
-        y: f32,
-        offsets: &Vec<(f32, f32)>,
-        i: &mut u32,
-    ) {
-        let mut sum_z = 0.0;
-        let tri_verts: Vec<nalgebra_glm::Vec3> = offsets
-            .iter()
-            .map(|(xo, yo)| {
-                let z = self.height_nearest(nalgebra_glm::vec2(x + xo, y + yo));
-                let mapval = nalgebra_glm::vec3(x + xo, y + yo, z);
-                sum_z += self.height_nearest(nalgebra_glm::vec2(x + xo, y + yo));
-                add_vertex(vertices, x + xo, y + yo, z);
-                indices.push(*i);
-                *i += 1;
-                mapval
-            })
-            .collect();
+    /// Builds the surface of the chunk's rivers and lakes, a little above the terrain of every cell that has fresh
+    /// water in it, or nothing if there aren't any. It's always at full detail, since rivers are only a cell or two
+    /// wide.
+    fn create_water_mesh(&self) -> Option<ChunkMesh> {
+        let mut mesh = MeshBuilder::default();
+        for y in 0..self.chunk_width {
+            for x in 0..self.chunk_width {
+                let cell = nalgebra_glm::vec2(x as f32, y as f32);
+                if !self.has_fresh_water(cell) {
+                    continue;
+                }
+                let first = (mesh.vertices.len() / 3) as u32;
+                for corner in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                    let p = cell + nalgebra_glm::vec2(corner.0, corner.1);
+                    mesh.vertices
+                        .extend([p.x, p.y, self.height_nearest(p) + RIVER_DEPTH]);
+                    mesh.normals.extend([0.0, 0.0, 1.0]);
+                    // The water texture is all one color
+                    mesh.uv.extend([0.5, 0.5, 0.0]);
+                }
+                mesh.indices
+                    .extend([first, first + 1, first + 2, first + 2, first + 1, first + 3]);
+            }
+        }
+        (!mesh.indices.is_empty()).then(|| mesh.finish())
+    }
 
-        let edge1 = tri_verts[1] - tri_verts[0];
-        let edge2 = tri_verts[2] - tri_verts[0];
-        let normal = nalgebra_glm::cross(&edge1, &edge2).normalize();
-        for _ in 0..3 {
-            normals.push(normal.x);
-            normals.push(normal.y);
-            normals.push(normal.z);
+    /// Hangs a skirt from each edge of the mesh, down to the lowest point of the full detail terrain along the edge.
+    /// Two neighboring chunks share the same heights along their edge, so whichever of their meshes is lower somewhere,
+    /// the other one's skirt reaches down past it.
+    fn add_skirts(&self, mesh: &mut MeshBuilder) {
+        let width = self.chunk_width;
+        let step = self.mesh_step;
+        let corner = |x: usize, y: usize| nalgebra_glm::vec2(x as f32, y as f32);
+        // Around the chunk counterclockwise, so that the skirts face outwards
+        let samples: Vec<usize> = (0..=width).step_by(step).collect();
+        let edges: [Vec<nalgebra_glm::Vec2>; 4] = [
+            samples.iter().map(|&x| corner(x, 0)).collect(),
+            samples.iter().map(|&y| corner(width, y)).collect(),
+            samples.iter().rev().map(|&x| corner(x, width)).collect(),
+            samples.iter().rev().map(|&y| corner(0, y)).collect(),
+        ];
+
+        for edge in edges {
+            for pair in edge.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let bottom = (0..=step)
+                    .map(|k| self.height_nearest(a + (b - a) * (k as f32 / step as f32)))
+                    .fold(f32::MAX, f32::min)
+                    - SKIRT_MARGIN;
+                let (top_a, top_b) = (self.height_nearest(a), self.height_nearest(b));
+                // Skirts look like the cell they hang from, and are lit like the terrain above them
+                let cell = nalgebra_glm::vec2(a.x.min(b.x), a.y.min(b.y))
+                    .map(|c| c.min((width - step) as f32));
+                let face = Face {
+                    uv: self.cell_uv(cell, 0.5 * (top_a + top_b), 1.0),
+                    normal: None,
+                };
+                let (normal_a, normal_b) = (self.vertex_normal(a), self.vertex_normal(b));
+
+                let a_top = mesh.add_vertex(nalgebra_glm::vec3(a.x, a.y, top_a), normal_a, face);
+                let a_bottom =
+                    mesh.add_vertex(nalgebra_glm::vec3(a.x, a.y, bottom), normal_a, face);
+                let b_top = mesh.add_vertex(nalgebra_glm::vec3(b.x, b.y, top_b), normal_b, face);
+                let b_bottom =
+                    mesh.add_vertex(nalgebra_glm::vec3(b.x, b.y, bottom), normal_b, face);
+                mesh.indices
+                    .extend([a_top, a_bottom, b_top, b_top, a_bottom, b_bottom]);
+            }
         }
+    }
+
+    /// What a terrain triangle looks like, given the cell it's in and its corners.
+    fn face(&self, cell: nalgebra_glm::Vec2, corners: [nalgebra_glm::Vec2; 3]) -> Face {
+        let [a, b, c] = corners.map(|corner| self.sample_position(corner));
+        let normal = nalgebra_glm::cross(&(b - a), &(c - a)).normalize();
         // 0 = steep
         // 1 = flat
         let dot_prod = nalgebra_glm::dot(&normal, &nalgebra_glm::vec3(0.0, 0.0, 1.0));
+        let avg_z = (a.z + b.z + c.z) / 3.0;
+        Face {
+            uv: self.cell_uv(cell, avg_z, dot_prod),
+            normal: (self.shading == Shading::Flat).then_some(normal),
+        }
+    }
+
+    fn sample_position(&self, p: nalgebra_glm::Vec2) -> nalgebra_glm::Vec3 {
+        nalgebra_glm::vec3(p.x, p.y, self.height_nearest(p))
+    }
 
-        let avg_z = sum_z / 3.0;
-        let u_offset: f32 = if avg_z < 0.5 || (avg_z < 0.9 * dot_prod && 0.9 < dot_prod) {
-            3.0 / 9.0
+    /// Where a cell's color is in the terrain texture, given how high up it is on average and how flat it is.
+    fn cell_uv(&self, cell: nalgebra_glm::Vec2, avg_z: f32, dot_prod: f32) -> (f32, f32) {
+        let soil = self.soil(cell).unwrap_or_default();
+        if self.has_fresh_water(cell) {
+            (6.5 / 8.0, 0.5 / 8.0)
+        } else if soil.tilled && soil.watered {
+            (2.5 / 8.0, 2.5 / 8.0)
+        } else if soil.tilled {
+            (0.5 / 8.0, 2.5 / 8.0)
+        } else if avg_z < WATER_LEVEL {
+            Biome::Beach.terrain_uv()
         } else if dot_prod < 0.9 {
-            5.0 / 9.0
+            Biome::RockyHighland.terrain_uv()
         } else {
-            0.0
+            self.biome(cell).terrain_uv()
+        }
+    }
+
+    /// The box the chunk's terrain fits in, skirts and all.
+    fn aabb(&self) -> AABB {
+        let heights: Vec<f32> = (0..=self.chunk_width)
+            .flat_map(|y| (0..=self.chunk_width).map(move |x| (x, y)))
+            .map(|(x, y)| self.height_nearest(nalgebra_glm::vec2(x as f32, y as f32)))
+            .collect();
+        let lowest = heights.iter().copied().fold(f32::MAX, f32::min);
+        let highest = heights.iter().copied().fold(f32::MIN, f32::max);
+        let width = self.chunk_width as f32;
+        AABB::from_min_max(
+            nalgebra_glm::vec3(self.pos.x, self.pos.y, lowest - SKIRT_MARGIN),
+            nalgebra_glm::vec3(self.pos.x + width, self.pos.y + width, highest),
+        )
+    }
+}
+
+impl MeshBuilder {
+    /// Adds a vertex, and returns its index. The face is what the triangles that end on the vertex look like.
+    fn add_vertex(
+        &mut self,
+        position: nalgebra_glm::Vec3,
+        smooth_normal: nalgebra_glm::Vec3,
+        face: Face,
+    ) -> u32 {
+        let index = (self.vertices.len() / 3) as u32;
+        let normal = face.normal.unwrap_or(smooth_normal);
+        let flat = if face.normal.is_some() {
+            FLAT_UV_AND_NORMAL
+        } else {
+            FLAT_UV
         };
-        let v_offset = 0.0;
-        for _ in 0..3 {
-            add_uv(uv, u_offset, v_offset);
+        self.vertices.extend([position.x, position.y, position.z]);
+        self.normals.extend([normal.x, normal.y, normal.z]);
+        self.uv.extend([face.uv.0, face.uv.1, flat]);
+        index
+    }
+
+    fn finish(self) -> ChunkMesh {
+        (self.indices, self.vertices, self.normals, self.uv)
+    }
+}
+
+/// A terrain mesh spread back out to three vertices for every triangle, the way terrain meshes used to be built, with
+/// every vertex taking on what its triangle's last vertex says about the triangle.
+fn unindexed(mesh: &ChunkMesh) -> ChunkMesh {
+    let (indices, vertices, normals, uv) = mesh;
+    let attribute = |data: &[f32], index: u32| {
+        let start = index as usize * 3;
+        [data[start], data[start + 1], data[start + 2]]
+    };
+    let mut spread = (vec![], vec![], vec![], vec![]);
+    for triangle in indices.chunks(3) {
+        let last = triangle[2];
+        let face_uv = attribute(uv, last);
+        for &index in triangle {
+            let normal = if face_uv[2] == FLAT_UV_AND_NORMAL {
+                attribute(normals, last)
+            } else {
+                attribute(normals, index)
+            };
+            spread.0.push(spread.0.len() as u32);
+            spread.1.extend(attribute(vertices, index));
+            spread.2.extend(normal);
+            spread.3.extend(face_uv);
+        }
+    }
+    spread
+}
+
+/// The terrain meshes of some chunks at full detail, both the way the map builds them and spread out to three vertices
+/// for every triangle, for comparing the two.
+pub fn benchmark_meshes(params: &MapParams, coords: &[ChunkCoord]) -> Vec<(ChunkMesh, ChunkMesh)> {
+    coords
+        .iter()
+        .map(|coord| {
+            let mut chunk = Chunk::new(params, *coord);
+            chunk.generate_terrain();
+            let mesh = chunk.create_mesh();
+            let spread = unindexed(&mesh);
+            (mesh, spread)
+        })
+        .collect()
+}
+
+/// Generates a chunk's terrain and meshes. This is the part of generating a chunk that doesn't need the GPU or the
+/// world, so it's done on the map's worker threads.
+fn prepare_chunk(coord: ChunkCoord, mut chunk: Chunk) -> PreparedChunk {
+    chunk.generate_terrain();
+    let mesh = chunk.create_mesh();
+    let water = chunk.create_water_mesh();
+    PreparedChunk {
+        coord,
+        chunk,
+        mesh,
+        water,
+    }
+}
+
+/// Does a job on one of the map's worker threads.
+fn do_chunk_job(job: ChunkJob) -> ChunkWork {
+    match job {
+        ChunkJob::Generate(coord, chunk) => {
+            ChunkWork::Generated(Box::new(prepare_chunk(coord, chunk)))
         }
+        ChunkJob::Remesh(coord, chunk, job) => ChunkWork::Remeshed {
+            coord,
+            job,
+            step: chunk.mesh_step,
+            mesh: chunk.create_mesh(),
+        },
     }
 }
 
-fn add_vertex(vertices: &mut Vec<f32>, x: f32, y: f32, z: f32) {
-    vertices.push(x);
-    vertices.push(y);
-    vertices.push(z);
+/// Seed for the props of a chunk, mixed from the world seed and the chunk's coordinates so that neighbouring chunks
+/// don't look alike.
+fn chunk_seed(seed: i32, chunk_x: i32, chunk_y: i32) -> u64 {
+    let mut hash = seed as u32 as u64;
+    hash = hash.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ chunk_x as u32 as u64;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9) ^ chunk_y as u32 as u64;
+    hash.wrapping_mul(0x94D0_49BB_1331_11EB)
 }
 
-fn add_uv(uv: &mut Vec<f32>, x: f32, y: f32) {
-    uv.push(x);
-    uv.push(y);
-    uv.push(0.0);
+/// Whether something at a position would be inside one of the trees, given as positions and scales.
+fn overlaps_tree(
+    trees: &[(nalgebra_glm::Vec3, f32)],
+    position: nalgebra_glm::Vec3,
+    radius: f32,
+) -> bool {
+    trees.iter().any(|(tree_position, tree_scale)| {
+        nalgebra_glm::distance(&tree_position.xy(), &position.xy()) < radius + tree_scale * 0.5
+    })
+}
+
+impl MapEdges {
+    /// Identifier used to refer to the edges in data files and on the command line.
+    pub fn id(&self) -> &'static str {
+        match self {
+            MapEdges::Bounded => "bounded",
+            MapEdges::Wrapping => "wrapping",
+            MapEdges::Unbounded => "unbounded",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<MapEdges> {
+        [MapEdges::Bounded, MapEdges::Wrapping, MapEdges::Unbounded]
+            .into_iter()
+            .find(|edges| edges.id() == id)
+    }
+}
+
+impl MapParams {
+    /// How many chunks there are along a side of the map.
+    fn side_chunks(&self) -> i32 {
+        (self.map_width / self.chunk_width) as i32
+    }
+
+    /// Whether there's terrain at some chunk coordinates. Only bounded maps run out of it.
+    fn is_on_map(&self, coord: ChunkCoord) -> bool {
+        let side_chunks = self.side_chunks();
+        self.edges != MapEdges::Bounded
+            || (0..side_chunks).contains(&coord.x) && (0..side_chunks).contains(&coord.y)
+    }
+
+    /// The chunk on the map that some chunk coordinates wrap around to. Chunks only wrap around in wrapping worlds,
+    /// everywhere else they're themselves.
+    fn wrapped(&self, coord: ChunkCoord) -> ChunkCoord {
+        if self.edges != MapEdges::Wrapping {
+            return coord;
+        }
+        let side_chunks = self.side_chunks();
+        ChunkCoord::new(
+            coord.x.rem_euclid(side_chunks),
+            coord.y.rem_euclid(side_chunks),
+        )
+    }
+
+    /// Width the terrain repeats at, if it's a wrapping world.
+    fn wrap(&self) -> Option<usize> {
+        (self.edges == MapEdges::Wrapping).then_some(self.map_width)
+    }
+
+    fn chunk_coord(&self, p: nalgebra_glm::Vec2) -> ChunkCoord {
+        let chunk_p = nalgebra_glm::floor(&(p / self.chunk_width as f32));
+        ChunkCoord::new(chunk_p.x as i32, chunk_p.y as i32)
+    }
+
+    /// World-space origin of the chunk at some chunk coordinates.
+    fn chunk_pos(&self, coord: ChunkCoord) -> nalgebra_glm::Vec2 {
+        nalgebra_glm::vec2(coord.x as f32, coord.y as f32) * self.chunk_width as f32
+    }
+
+    /// How many cells apart the mesh samples of a chunk should be, given where the camera is. The step always divides
+    /// the chunk's width, so that the mesh ends right on the chunk's edges.
+    fn mesh_step(&self, coord: ChunkCoord, camera: nalgebra_glm::Vec2) -> usize {
+        self.mesh_step_at(self.camera_distance(coord, camera))
+    }
+
+    /// The step a chunk's mesh should change to from the one it has, given where the camera is. Meshes get coarser as
+    /// soon as the camera is past a LOD distance, but only get finer again once it's `LOD_HYSTERESIS` back inside it.
+    fn next_mesh_step(
+        &self,
+        coord: ChunkCoord,
+        camera: nalgebra_glm::Vec2,
+        current: usize,
+    ) -> usize {
+        let distance = self.camera_distance(coord, camera);
+        let step = self.mesh_step_at(distance);
+        if step < current {
+            self.mesh_step_at(distance + LOD_HYSTERESIS).min(current)
+        } else {
+            step
+        }
+    }
+
+    /// How many chunk widths the camera is from the middle of a chunk.
+    fn camera_distance(&self, coord: ChunkCoord, camera: nalgebra_glm::Vec2) -> f32 {
+        let width = self.chunk_width as f32;
+        let center = self.chunk_pos(coord) + nalgebra_glm::vec2(width, width) * 0.5;
+        nalgebra_glm::distance(&center, &camera) / width
+    }
+
+    /// The mesh step of a chunk that's some number of chunk widths from the camera.
+    fn mesh_step_at(&self, distance: f32) -> usize {
+        let mut step = 1 << LOD_DISTANCES.iter().filter(|d| distance >= **d).count();
+        while !self.chunk_width.is_multiple_of(step) {
+            step /= 2;
+        }
+        step
+    }
 }
 
 impl ChunkedPerlinMap {
@@ -333,100 +1117,967 @@ impl ChunkedPerlinMap {
         level_of_detail: f32,
         seed: i32,
         amplitude: f32,
+        edges: MapEdges,
     ) -> Self {
-        let chunks =
-            Self::generate_chunks(map_width, chunk_width, level_of_detail, seed, amplitude);
-        Self {
-            chunks,
+        Self::from_params(MapParams {
             map_width,
             chunk_width,
             level_of_detail,
             seed,
             amplitude,
+            edges,
+        })
+    }
+
+    /// A map that generates the same terrain as the map the parameters came from.
+    pub fn from_params(params: MapParams) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            stored: HashMap::new(),
+            workers: WorkerPool::with_available_threads(do_chunk_job),
+            pending: HashSet::new(),
+            remeshing: HashMap::new(),
+            remesh_jobs: 0,
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            unload_radius: DEFAULT_UNLOAD_RADIUS,
+            prop_distance: DEFAULT_PROP_DISTANCE,
+            params,
+        }
+    }
+
+    /// How many chunks away from the player chunks are generated and drawn. Far away chunks get coarse meshes and no
+    /// props, so this can be a lot further out than the player can walk to in a while.
+    pub fn set_render_distance(&mut self, distance: usize) {
+        self.render_distance = distance;
+    }
+
+    /// How many chunks away from the player chunks have to be before they're unloaded.
+    pub fn set_unload_radius(&mut self, radius: usize) {
+        self.unload_radius = radius;
+    }
+
+    /// How many chunks away from the player chunks have their props and animals.
+    pub fn set_prop_distance(&mut self, distance: usize) {
+        self.prop_distance = distance;
+    }
+
+    /// How many chunks away from the player chunks are unloaded. It's always further out than the render distance, so
+    /// that chunks at the edge aren't loaded and unloaded over and over. On a wrapping map, it's never so far out that
+    /// a chunk would be loaded on both sides of the player at once.
+    fn unload_distance(&self) -> usize {
+        let distance = self.unload_radius.max(self.render_distance + 1);
+        match self.params.edges {
+            MapEdges::Wrapping => {
+                distance.min(((self.params.side_chunks() - 1) / 2).max(1) as usize)
+            }
+            _ => distance,
         }
     }
 
+    /// How many chunks away from the player chunks are generated and drawn, within the unload distance.
+    fn render_distance(&self) -> usize {
+        self.render_distance.min(self.unload_distance() - 1)
+    }
+
+    /// How many chunks away from the player chunks have their props, within the render distance. Their props are taken
+    /// back out once they're one more chunk away than this, so that chunks at the edge aren't filled and emptied over
+    /// and over.
+    fn prop_distance(&self) -> usize {
+        self.prop_distance.min(self.render_distance())
+    }
+
+    /// Generates the chunks around a position, and unloads the chunks that are too far away from it. The chunk at the
+    /// position is generated right away, the others are generated in the background and put into the world a few at a
+    /// time, so that walking into new chunks doesn't make frames take longer. The chunks' meshes get coarser the
+    /// further they are from the camera, and are rebuilt in the background too. Only the chunks near the position
+    /// have their props.
     pub fn check_chunks(
         &mut self,
         renderer: &RenderContext,
         p: nalgebra_glm::Vec2,
+        camera: nalgebra_glm::Vec3,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        self.generate_chunk_at(renderer, p, world, bvh);
+
+        // Nearest chunks first
+        let center = self.params.chunk_coord(p);
+        let radius = self.render_distance() as i32;
+        let mut offsets: Vec<ChunkCoord> = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| ChunkCoord::new(x, y)))
+            .collect();
+        offsets.sort_by_key(|offset| offset.x.abs().max(offset.y.abs()));
+        for offset in offsets {
+            self.request_chunk(center + offset, camera.xy());
+        }
+
+        let deadline = Instant::now() + CHUNK_TIME_BUDGET;
+        self.add_finished_work(center, deadline, renderer, world, bvh);
+        self.populate_chunks(center, deadline, renderer, world, bvh);
+        self.request_remeshes(camera.xy());
+
+        let prop_reach = self.prop_distance() + 1;
+        let emptied: Vec<ChunkCoord> = self
+            .chunks
+            .iter()
+            .filter(|(coord, chunk)| {
+                chunk.populated && chunk_distance(**coord, center) > prop_reach
+            })
+            .map(|(coord, _)| *coord)
+            .collect();
+        for coord in emptied {
+            self.depopulate_chunk(coord, renderer, world, bvh);
+        }
+
+        let unload_distance = self.unload_distance();
+        let far: Vec<ChunkCoord> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|coord| chunk_distance(*coord, center) > unload_distance)
+            .collect();
+        for coord in far {
+            self.unload_chunk(coord, renderer, world, bvh);
+        }
+    }
+
+    /// Generates the chunk at a position right away, with its props, if it hasn't been already.
+    pub fn generate_chunk_at(
+        &mut self,
+        renderer: &RenderContext,
+        p: nalgebra_glm::Vec2,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        let coord = self.params.chunk_coord(p);
+        if !self.params.is_on_map(coord) {
+            return;
+        }
+        if !self.chunks.contains_key(&coord) {
+            let prepared = prepare_chunk(coord, Chunk::new(&self.params, coord));
+            self.add_prepared_chunk(prepared, renderer, world, bvh);
+        }
+        self.populate_chunk(coord, renderer, world, bvh);
+    }
+
+    /// Has the workers generate the chunk at some chunk coordinates, if it hasn't been already. Its mesh is made as
+    /// coarse as it should be for where the camera is now.
+    fn request_chunk(&mut self, coord: ChunkCoord, camera: nalgebra_glm::Vec2) {
+        if !self.params.is_on_map(coord)
+            || self.chunks.contains_key(&coord)
+            || self.pending.contains(&coord)
+        {
+            return;
+        }
+        self.pending.insert(coord);
+        let mut chunk = Chunk::new(&self.params, coord);
+        chunk.mesh_step = self.params.mesh_step(coord, camera);
+        self.workers.submit(ChunkJob::Generate(coord, chunk));
+    }
+
+    /// Has the workers rebuild the mesh of every chunk whose mesh should be coarser or finer by now. Rebuilds that
+    /// aren't needed anymore, because the camera went back to where it was, are called off.
+    fn request_remeshes(&mut self, camera: nalgebra_glm::Vec2) {
+        for (coord, chunk) in &self.chunks {
+            let step = self.params.next_mesh_step(*coord, camera, chunk.mesh_step);
+            let requested = self.remeshing.get(coord).map(|(_, step)| *step);
+            if step == chunk.mesh_step {
+                self.remeshing.remove(coord);
+                continue;
+            }
+            if requested == Some(step) {
+                continue;
+            }
+            self.remesh_jobs += 1;
+            self.remeshing.insert(*coord, (self.remesh_jobs, step));
+            let mut copy = chunk.clone();
+            copy.mesh_step = step;
+            self.workers
+                .submit(ChunkJob::Remesh(*coord, copy, self.remesh_jobs));
+        }
+    }
+
+    /// Puts the chunks and meshes that the workers finished into the world, until the frame's time for it runs out.
+    /// Chunks that were already generated right away, or that are too far from the center chunk by now, are thrown
+    /// out, and so are meshes whose rebuild was called off or that were built from a chunk that has changed since.
+    fn add_finished_work(
+        &mut self,
+        center: ChunkCoord,
+        deadline: Instant,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        while Instant::now() < deadline {
+            let Some(work) = self.workers.try_recv() else {
+                break;
+            };
+            match work {
+                ChunkWork::Generated(prepared) => {
+                    self.pending.remove(&prepared.coord);
+                    if self.chunks.contains_key(&prepared.coord)
+                        || chunk_distance(prepared.coord, center) > self.unload_distance()
+                    {
+                        continue;
+                    }
+                    self.add_prepared_chunk(*prepared, renderer, world, bvh);
+                }
+                ChunkWork::Remeshed {
+                    coord,
+                    job,
+                    step,
+                    mesh,
+                } => {
+                    if self.remeshing.get(&coord) != Some(&(job, step)) {
+                        continue;
+                    }
+                    self.remeshing.remove(&coord);
+                    if let Some(chunk) = self.chunks.get_mut(&coord) {
+                        chunk.mesh_step = step;
+                        chunk.replace_mesh(mesh, renderer, world);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Spawns the props of the chunks within the prop distance that don't have them yet, nearest first, until the
+    /// frame's time for it runs out.
+    fn populate_chunks(
+        &mut self,
+        center: ChunkCoord,
+        deadline: Instant,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        let prop_distance = self.prop_distance();
+        let mut empty: Vec<ChunkCoord> = self
+            .chunks
+            .iter()
+            .filter(|(coord, chunk)| {
+                !chunk.populated && chunk_distance(**coord, center) <= prop_distance
+            })
+            .map(|(coord, _)| *coord)
+            .collect();
+        empty.sort_by_key(|coord| chunk_distance(*coord, center));
+        for coord in empty {
+            if Instant::now() >= deadline {
+                break;
+            }
+            self.populate_chunk(coord, renderer, world, bvh);
+        }
+    }
+
+    /// Puts a prepared chunk's terrain into the world, without its props.
+    fn add_prepared_chunk(
+        &mut self,
+        prepared: PreparedChunk,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        let PreparedChunk {
+            coord,
+            mut chunk,
+            mesh,
+            water,
+        } = prepared;
+        // A copy of the chunk from around the other side of the map, like one that a save was loaded into, is stored
+        // first, so that this one comes back with its changes
+        if self.params.edges == MapEdges::Wrapping {
+            let wrapped = self.params.wrapped(coord);
+            let copies: Vec<ChunkCoord> = self
+                .chunks
+                .keys()
+                .copied()
+                .filter(|other| self.params.wrapped(*other) == wrapped)
+                .collect();
+            for copy in copies {
+                self.unload_chunk(copy, renderer, world, bvh);
+            }
+        }
+        chunk.add_to_world(mesh, water, renderer, world, bvh);
+        self.chunks.insert(coord, chunk);
+    }
+
+    /// Spawns a generated chunk's props, if it doesn't have them yet. A chunk that was stored when its props were
+    /// taken out gets its soil and props back from the store, instead of the ones it would be generated with.
+    fn populate_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        if self.chunks.get(&coord).is_none_or(|chunk| chunk.populated) {
+            return;
+        }
+        // Its mesh is rebuilt if it gets soil back, so one that's being built from how it was is out of date
+        self.remeshing.remove(&coord);
+        let generated = self.chunks[&coord].place_props();
+        let (props, soil) = self.take_stored(coord, generated);
+        let chunk = self.chunks.get_mut(&coord).unwrap();
+        chunk.populate(props, &soil, renderer, world, bvh);
+    }
+
+    /// The props and soil that a chunk is populated with, given the props it generates. If the chunk was stored, they
+    /// come from the store instead, which the chunk is taken out of.
+    fn take_stored(
+        &mut self,
+        coord: ChunkCoord,
+        generated: Vec<Prop>,
+    ) -> (Vec<Prop>, Vec<(usize, Soil)>) {
+        let wrapped = self.params.wrapped(coord);
+        let Some(stored) = self.stored.remove(&wrapped) else {
+            return (generated, vec![]);
+        };
+        // Animals are never removed, they're spawned again like the chunk is new
+        let offset = self.params.chunk_pos(coord) - self.params.chunk_pos(wrapped);
+        let props = generated
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| stored.removed.binary_search(index).is_err())
+            .map(|(_, prop)| prop)
+            .chain(stored.added.into_iter().map(|prop| prop.moved(offset)))
+            .collect();
+        (props, stored.soil)
+    }
+
+    /// Takes a chunk's props out of the world, and stores what the player changed about it.
+    fn depopulate_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        renderer: &RenderContext,
         world: &mut World,
         bvh: &mut BVH<Entity>,
     ) {
-        for y in -3..4 {
-            for x in -3..4 {
-                let chunk_offset = nalgebra_glm::vec2(x as f32, y as f32);
-                let chunk_pos = chunk_offset * (self.chunk_width as f32) + p;
-                let chunk = self.chunk_at_mut(chunk_pos);
-                chunk.generate(renderer, world, bvh);
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return;
+        };
+        let Some(stored) = chunk.depopulate(world, bvh) else {
+            return;
+        };
+        if !stored.soil.is_empty() {
+            // Its mesh loses the soil, so one that's being built with the soil is out of date
+            chunk.rebuild_mesh(renderer, world);
+            self.remeshing.remove(&coord);
+        }
+        self.store(coord, stored);
+    }
+
+    fn unload_chunk(
+        &mut self,
+        coord: ChunkCoord,
+        renderer: &RenderContext,
+        world: &mut World,
+        bvh: &mut BVH<Entity>,
+    ) {
+        let Some(chunk) = self.chunks.remove(&coord) else {
+            return;
+        };
+        self.remeshing.remove(&coord);
+        if let Some(stored) = chunk.unload(renderer, world, bvh) {
+            self.store(coord, stored);
+        }
+    }
+
+    /// Keeps what the player changed about a chunk whose props were taken out of the world.
+    fn store(&mut self, coord: ChunkCoord, mut stored: StoredChunk) {
+        // Kept where it would be on the map, in case the chunk wrapped around
+        let wrapped = self.params.wrapped(coord);
+        let offset = self.params.chunk_pos(wrapped) - self.params.chunk_pos(coord);
+        stored.added = stored
+            .added
+            .into_iter()
+            .map(|prop| prop.moved(offset))
+            .collect();
+        self.stored.insert(wrapped, stored);
+    }
+
+    /// Everything the player changed about the map, in both the generated and the stored chunks.
+    pub fn changes(&self, world: &World) -> MapChanges {
+        let mut by_chunk: HashMap<ChunkCoord, Vec<Prop>> = HashMap::new();
+        for prop in all_props(world) {
+            let coord = self.params.chunk_coord(prop.position().xy());
+            by_chunk.entry(coord).or_default().push(prop);
+        }
+        let mut changed: Vec<(ChunkCoord, StoredChunk)> = vec![];
+        for (coord, chunk) in self.chunks.iter().filter(|(_, chunk)| chunk.populated) {
+            let props = by_chunk.remove(coord).unwrap_or_default();
+            if let Some(mut stored) = chunk.changes(props) {
+                // Kept where it would be on the map, in case the chunk wrapped around
+                let wrapped = self.params.wrapped(*coord);
+                let offset = self.params.chunk_pos(wrapped) - chunk.pos();
+                stored.added = stored
+                    .added
+                    .into_iter()
+                    .map(|prop| prop.moved(offset))
+                    .collect();
+                changed.push((wrapped, stored));
             }
         }
+
+        let chunk_width = self.params.chunk_width;
+        let mut changes = MapChanges::default();
+        let stored = self.stored.iter().map(|(coord, stored)| (*coord, stored));
+        for (coord, stored) in changed
+            .iter()
+            .map(|(coord, stored)| (*coord, stored))
+            .chain(stored)
+        {
+            if !stored.removed.is_empty() {
+                changes.removed.push((coord, stored.removed.clone()));
+            }
+            changes.added.extend(stored.added.iter().cloned());
+            for (index, soil) in stored.soil.iter().filter(|(_, soil)| soil.tilled) {
+                let cell =
+                    nalgebra_glm::vec2((index % chunk_width) as f32, (index / chunk_width) as f32);
+                changes
+                    .soil
+                    .push((cell + self.params.chunk_pos(coord), *soil));
+            }
+        }
+        changes
+    }
+
+    /// Puts back the changes to the map that a save has, by storing them the same way as the changes to a chunk that
+    /// was unloaded. They're applied as the chunks are populated, so this has to be done before any of them are.
+    pub fn restore_changes(&mut self, changes: MapChanges) {
+        for (coord, mut removed) in changes.removed {
+            removed.sort_unstable();
+            self.stored_chunk(coord).removed = removed;
+        }
+        for prop in changes.added {
+            let coord = self.params.chunk_coord(prop.position().xy());
+            let wrapped = self.params.wrapped(coord);
+            let offset = self.params.chunk_pos(wrapped) - self.params.chunk_pos(coord);
+            self.stored_chunk(wrapped).added.push(prop.moved(offset));
+        }
+        let chunk_width = self.params.chunk_width;
+        for (cell, soil) in changes.soil {
+            let coord = self.params.chunk_coord(cell);
+            let local = cell - self.params.chunk_pos(coord);
+            let index = local.y as usize * chunk_width + local.x as usize;
+            self.stored_chunk(self.params.wrapped(coord))
+                .soil
+                .push((index, soil));
+        }
+    }
+
+    /// Stores the generated props of the chunk at a position that match, as if the player had taken them. Saves from
+    /// before removals were kept only have what replaced some of the props, like the stumps of felled trees.
+    pub fn remove_generated(&mut self, p: nalgebra_glm::Vec2, matches: impl Fn(&Prop) -> bool) {
+        let coord = self.params.wrapped(self.params.chunk_coord(p));
+        let mut chunk = Chunk::new(&self.params, coord);
+        chunk.generate_terrain();
+        let stored = self.stored_chunk(coord);
+        for (index, prop) in chunk.place_props().iter().enumerate() {
+            if let Err(at) = stored.removed.binary_search(&index) {
+                if matches(prop) {
+                    stored.removed.insert(at, index);
+                }
+            }
+        }
+    }
+
+    /// The store of the chunk at some wrapped chunk coordinates, which is made if it isn't there yet.
+    fn stored_chunk(&mut self, wrapped: ChunkCoord) -> &mut StoredChunk {
+        self.stored.entry(wrapped).or_default()
+    }
+
+    pub fn params(&self) -> MapParams {
+        self.params
     }
 
     pub fn chunkless_height(&mut self, pos: nalgebra_glm::Vec2) -> f32 {
-        let side_chunks = self.map_width / self.chunk_width;
-        let chunk_p =
-            nalgebra_glm::floor(&(pos / self.chunk_width as f32)) * self.chunk_width as f32;
-        let mut map = PerlinMap::new(self.chunk_width);
-        map.generate(self.level_of_detail, 10, self.seed, self.amplitude, chunk_p);
-        let retval = map.get_z_interpolated(pos - chunk_p);
-        retval
+        let chunk_p = self.params.chunk_pos(self.params.chunk_coord(pos));
+        let mut map = PerlinMap::new(self.params.chunk_width);
+        map.generate(
+            self.params.level_of_detail,
+            10,
+            self.params.seed,
+            self.params.amplitude,
+            chunk_p,
+            self.params.wrap(),
+        );
+        map.get_z_interpolated(pos - chunk_p)
     }
 
-    fn generate_chunks(
-        map_width: usize,
-        chunk_width: usize,
-        level_of_detail: f32,
-        seed: i32,
-        amplitude: f32,
-    ) -> Vec<Chunk> {
-        let mut chunks: Vec<Chunk> = vec![];
-        let side_chunks = map_width / chunk_width;
-        for y in 0..side_chunks {
-            for x in 0..side_chunks {
-                chunks.push(Chunk::new(
-                    chunk_width,
-                    nalgebra_glm::vec2((x * chunk_width) as f32, (y * chunk_width) as f32),
-                    level_of_detail,
-                    seed,
-                    amplitude,
-                ));
+    /// Finds where a ray first hits the terrain, within some distance along the ray. Only generated chunks are
+    /// considered, if the ray leaves them before hitting anything then None is returned.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<nalgebra_glm::Vec3> {
+        const STEP: f32 = 0.1;
+        const REFINEMENTS: usize = 8;
+        let above_ground = |t: f32| -> Option<bool> {
+            let p = ray.origin + ray.dir * t;
+            let chunk = self.chunk_at(p.xy())?;
+            Some(p.z > chunk.height_interpolated(p.xy() - chunk.pos()))
+        };
+
+        let mut prev_t = 0.0;
+        let mut t = 0.0;
+        while t <= max_distance {
+            let above = above_ground(t)?;
+            if !above {
+                // Bisect between the last point above ground and this one
+                let (mut lo, mut hi) = (prev_t, t);
+                for _ in 0..REFINEMENTS {
+                    let mid = 0.5 * (lo + hi);
+                    if above_ground(mid).unwrap_or(true) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                return Some(ray.origin + ray.dir * hi);
             }
+            prev_t = t;
+            t += STEP;
         }
-        chunks
+        None
+    }
+
+    /// Whether there's a river or lake to drink from at a position. The sea doesn't count.
+    pub fn has_fresh_water(&self, p: nalgebra_glm::Vec2) -> bool {
+        self.chunk_at(p)
+            .is_some_and(|chunk| chunk.has_fresh_water(p - chunk.pos()))
+    }
+
+    /// The biome at a position, if the chunk there has been generated.
+    pub fn biome_at(&self, p: nalgebra_glm::Vec2) -> Option<Biome> {
+        let chunk = self.chunk_at(p)?;
+        Some(chunk.biome(p - chunk.pos()))
     }
 
-    fn chunk_at(&self, p: nalgebra_glm::Vec2) -> &Chunk {
-        let side_chunks = self.map_width / self.chunk_width;
-        let chunk_p = p / self.chunk_width as f32;
-        &self.chunks[chunk_p.y as usize * side_chunks + chunk_p.x as usize]
+    /// Farming state of the terrain cell at a position, if the chunk there has been generated.
+    pub fn soil(&self, p: nalgebra_glm::Vec2) -> Option<Soil> {
+        let chunk = self.chunk_at(p)?;
+        chunk.soil(p - chunk.pos())
+    }
+
+    /// Changes the farming state of the terrain cell at a position. The chunk's mesh is rebuilt if needed.
+    pub fn set_soil(
+        &mut self,
+        p: nalgebra_glm::Vec2,
+        soil: Soil,
+        renderer: &RenderContext,
+        world: &mut World,
+    ) {
+        let coord = self.params.chunk_coord(p);
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return;
+        };
+        let chunk_pos = chunk.pos();
+        chunk.set_soil(p - chunk_pos, soil, renderer, world);
+        // A mesh that's being built from the old soil is out of date
+        self.remeshing.remove(&coord);
     }
 
-    fn chunk_at_mut(&mut self, p: nalgebra_glm::Vec2) -> &mut Chunk {
-        let side_chunks = self.map_width / self.chunk_width;
-        let chunk_p = p / self.chunk_width as f32;
-        &mut self.chunks[chunk_p.y as usize * side_chunks + chunk_p.x as usize]
+    /// The chunk at a position, if it has been generated.
+    fn chunk_at(&self, p: nalgebra_glm::Vec2) -> Option<&Chunk> {
+        self.chunks.get(&self.params.chunk_coord(p))
     }
 }
 
+/// How many chunks apart two chunks are, counting diagonal steps as one.
+fn chunk_distance(a: ChunkCoord, b: ChunkCoord) -> usize {
+    a.x.abs_diff(b.x).max(a.y.abs_diff(b.y)) as usize
+}
+
+// Terrain that hasn't been generated, or that's off the map, is flat sea floor
 impl HeightMap for ChunkedPerlinMap {
     fn height_nearest(&self, p: nalgebra_glm::Vec2) -> f32 {
-        let chunk = self.chunk_at(p);
-        chunk.height_nearest(p - chunk.pos())
+        self.chunk_at(p)
+            .map_or(0.0, |chunk| chunk.height_nearest(p - chunk.pos()))
     }
 
     fn height_interpolated(&self, p: nalgebra_glm::Vec2) -> f32 {
-        let chunk = self.chunk_at(p);
-        chunk.height_interpolated(p - chunk.pos())
+        self.chunk_at(p)
+            .map_or(0.0, |chunk| chunk.height_interpolated(p - chunk.pos()))
     }
 
     fn normal(&self, p: nalgebra_glm::Vec2) -> nalgebra_glm::Vec3 {
-        let chunk = self.chunk_at(p);
-        chunk.normal(p - chunk.pos())
+        self.chunk_at(p)
+            .map_or(nalgebra_glm::vec3(0.0, 0.0, 1.0), |chunk| {
+                chunk.normal(p - chunk.pos())
+            })
     }
 
     fn flow(&self, p: nalgebra_glm::Vec2) -> f32 {
-        let chunk = self.chunk_at(p);
-        chunk.flow(p - chunk.pos())
+        self.chunk_at(p)
+            .map_or(0.0, |chunk| chunk.flow(p - chunk.pos()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::save::SaveGame;
+
+    /// A map big enough that the chunks the tests look at, around chunk (512, 512), are nowhere near its edges.
+    fn test_params(seed: i32) -> MapParams {
+        MapParams {
+            map_width: 16384,
+            chunk_width: 16,
+            level_of_detail: 0.01,
+            seed,
+            amplitude: 1.0,
+            edges: MapEdges::Bounded,
+        }
+    }
+
+    /// A chunk with its terrain generated.
+    fn generated_chunk(params: &MapParams, coord: ChunkCoord) -> Chunk {
+        let mut chunk = Chunk::new(params, coord);
+        chunk.generate_terrain();
+        chunk
+    }
+
+    fn generate_props(seed: i32, coord: ChunkCoord) -> Vec<Prop> {
+        generated_chunk(&test_params(seed), coord).place_props()
+    }
+
+    #[test]
+    fn same_chunk_generates_the_same_props() {
+        // These seeds put land with props on the chunk
+        for seed in [1, 123456] {
+            let coord = ChunkCoord::new(512, 513);
+            let props = generate_props(seed, coord);
+            assert!(!props.is_empty());
+            assert_eq!(props, generate_props(seed, coord));
+        }
+    }
+
+    #[test]
+    fn untouched_chunk_stores_nothing() {
+        let chunk = generated_chunk(&test_params(1), ChunkCoord::new(512, 513));
+
+        // What unloading takes back out of the world after a while: no animals, in some other order, and with the
+        // bushes growing their berries back
+        let mut props: Vec<Prop> = chunk
+            .place_props()
+            .into_iter()
+            .filter(|prop| !prop.is_animal())
+            .rev()
+            .collect();
+        let mut regrown = false;
+        for prop in &mut props {
+            if let Prop::BerryBush { bush, .. } = prop {
+                for _ in 0..100 {
+                    bush.tick(0.01);
+                }
+                regrown |= bush.regrow_timer != BerryBush::new(0).regrow_timer;
+            }
+        }
+        assert!(regrown);
+        assert!(chunk.changes(props.clone()).is_none());
+
+        // Only what the player did is stored
+        let rock = props
+            .iter()
+            .position(|prop| matches!(prop, Prop::Rock { .. }))
+            .unwrap();
+        let rock_position = match props.remove(rock) {
+            Prop::Rock { position } => position,
+            _ => unreachable!(),
+        };
+        let dropped = Prop::Item {
+            item: Item::new(ItemKind::Stone),
+            position: rock_position,
+        };
+        props.push(dropped.clone());
+        let stored = chunk.changes(props).unwrap();
+        let rock_index = chunk
+            .place_props()
+            .iter()
+            .position(|prop| {
+                *prop
+                    == Prop::Rock {
+                        position: rock_position,
+                    }
+            })
+            .unwrap();
+        assert_eq!(stored.removed, vec![rock_index]);
+        assert_eq!(stored.added, vec![dropped]);
+        assert!(stored.soil.is_empty());
+    }
+
+    #[test]
+    fn taken_props_stay_gone_after_saving_and_loading() {
+        let params = test_params(1);
+        let coord = ChunkCoord::new(512, 513);
+        let chunk = generated_chunk(&params, coord);
+        let generated = chunk.place_props();
+
+        // The player picks up a rock and the berries of a bush, and walks away
+        let mut props: Vec<Prop> = generated
+            .iter()
+            .filter(|prop| !prop.is_animal())
+            .cloned()
+            .collect();
+        let rock = props
+            .iter()
+            .position(|prop| matches!(prop, Prop::Rock { .. }))
+            .unwrap();
+        let rock = props.remove(rock);
+        let mut picked = None;
+        for prop in &mut props {
+            if let Prop::BerryBush { bush, .. } = prop {
+                bush.berries = 0;
+                picked = Some(prop.clone());
+                break;
+            }
+        }
+        let picked = picked.unwrap();
+        let mut map = ChunkedPerlinMap::from_params(params);
+        map.store(coord, chunk.changes(props).unwrap());
+
+        let mut save = SaveGame::new(params);
+        save.add_map_changes(map.changes(&World::new()));
+        let loaded = SaveGame::parse(&save.to_text()).unwrap();
+        let mut reloaded = ChunkedPerlinMap::from_params(params);
+        reloaded.restore_changes(loaded.map_changes());
+
+        let (props, _) = reloaded.take_stored(coord, generated.clone());
+        assert!(!props.contains(&rock));
+        assert!(props.contains(&picked));
+        assert_eq!(props.len(), generated.len() - 1);
+    }
+
+    #[test]
+    fn erosion_lines_up_across_chunk_borders() {
+        let params = test_params(1);
+        let left = generated_chunk(&params, ChunkCoord::new(512, 513));
+        let right = generated_chunk(&params, ChunkCoord::new(513, 513));
+        let mut total_flow = 0.0;
+        for y in 0..=16 {
+            let y = y as f32;
+            let (on_left, on_right) = (nalgebra_glm::vec2(16.0, y), nalgebra_glm::vec2(0.0, y));
+            assert_eq!(left.map.height(on_left), right.map.height(on_right));
+            assert_eq!(left.map.flow(on_left), right.map.flow(on_right));
+            total_flow += left.map.flow(on_left);
+        }
+        assert!(total_flow > 0.0);
+    }
+
+    /// The normal of every mesh vertex that's on a line along the chunk's border, by how far along the line it is.
+    fn border_normals(
+        mesh: &ChunkMesh,
+        on_border: impl Fn(f32, f32) -> Option<f32>,
+    ) -> Vec<(f32, [f32; 3])> {
+        let (_, vertices, normals, _) = mesh;
+        vertices
+            .chunks(3)
+            .zip(normals.chunks(3))
+            .filter_map(|(vertex, normal)| {
+                let along = on_border(vertex[0], vertex[1])?;
+                Some((along, [normal[0], normal[1], normal[2]]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn neighboring_chunks_have_the_same_normals_on_their_border() {
+        let params = test_params(1);
+        let generate = |coord: ChunkCoord| generated_chunk(&params, coord).create_mesh();
+        let chunk = generate(ChunkCoord::new(512, 513));
+        let right = generate(ChunkCoord::new(513, 513));
+        let above = generate(ChunkCoord::new(512, 514));
+
+        let pairs = [
+            (
+                border_normals(&chunk, |x, y| (x == 16.0).then_some(y)),
+                border_normals(&right, |x, y| (x == 0.0).then_some(y)),
+            ),
+            (
+                border_normals(&chunk, |x, y| (y == 16.0).then_some(x)),
+                border_normals(&above, |x, y| (y == 0.0).then_some(x)),
+            ),
+        ];
+        for (ours, theirs) in pairs {
+            assert!(!ours.is_empty() && !theirs.is_empty());
+            for (along, normal) in &ours {
+                for (_, other) in theirs
+                    .iter()
+                    .filter(|(other_along, _)| other_along == along)
+                {
+                    assert_eq!(normal, other);
+                }
+            }
+            // Not every border is flat
+            assert!(ours.iter().any(|(_, normal)| normal[2] < 1.0));
+        }
+    }
+
+    #[test]
+    fn shared_vertices_still_give_every_triangle_its_own_look() {
+        let chunk = generated_chunk(&test_params(1), ChunkCoord::new(512, 513));
+        let mesh = chunk.create_mesh();
+        let (indices, vertices, _, uv) = &mesh;
+        let corner = |index: u32| {
+            let start = index as usize * 3;
+            nalgebra_glm::vec2(vertices[start], vertices[start + 1])
+        };
+
+        // The terrain comes before the skirts
+        let terrain_triangles = 2 * 16 * 16;
+        let mut looks = std::collections::HashSet::new();
+        for triangle in indices.chunks(3).take(terrain_triangles) {
+            let corners = [
+                corner(triangle[0]),
+                corner(triangle[1]),
+                corner(triangle[2]),
+            ];
+            let cell = nalgebra_glm::vec2(
+                corners.iter().map(|c| c.x).fold(f32::MAX, f32::min),
+                corners.iter().map(|c| c.y).fold(f32::MAX, f32::min),
+            );
+            let last = triangle[2] as usize * 3;
+            let face = chunk.face(cell, corners);
+            assert_eq!((uv[last], uv[last + 1]), face.uv);
+            looks.insert((uv[last].to_bits(), uv[last + 1].to_bits()));
+        }
+        // Not all of the terrain looks the same
+        assert!(looks.len() > 1);
+
+        // Far fewer vertices than three for every triangle
+        let terrain_vertices = indices[..terrain_triangles * 3].iter().max().unwrap() + 1;
+        assert!((terrain_vertices as usize) < terrain_triangles);
+        assert_eq!(unindexed(&mesh).1.len(), indices.len() * 3);
+    }
+
+    #[test]
+    fn flat_shaded_triangles_get_their_own_normal() {
+        let mut chunk = generated_chunk(&test_params(1), ChunkCoord::new(512, 513));
+        let smooth = chunk.create_mesh();
+        chunk.shading = Shading::Flat;
+        let flat = chunk.create_mesh();
+        let attribute = |data: &[f32], index: u32| {
+            let start = index as usize * 3;
+            nalgebra_glm::vec3(data[start], data[start + 1], data[start + 2])
+        };
+
+        let terrain_triangles = 2 * 16 * 16;
+        let (indices, vertices, normals, uv) = &flat;
+        for triangle in indices.chunks(3).take(terrain_triangles) {
+            let [a, b, c] = [0, 1, 2].map(|i| attribute(vertices, triangle[i]));
+            let normal = nalgebra_glm::cross(&(b - a), &(c - a)).normalize();
+            let last = triangle[2];
+            assert_eq!(attribute(uv, last).z, FLAT_UV_AND_NORMAL);
+            assert!(nalgebra_glm::distance(&attribute(normals, last), &normal) < 1e-5);
+        }
+        assert!(smooth.3.chunks(3).all(|uv| uv[2] == FLAT_UV));
+
+        // Spreading the mesh out keeps every triangle's normal
+        let (spread_indices, _, spread_normals, _) = unindexed(&flat);
+        for (triangle, spread) in indices
+            .chunks(3)
+            .zip(spread_indices.chunks(3))
+            .take(terrain_triangles)
+        {
+            for index in spread {
+                assert_eq!(
+                    attribute(&spread_normals, *index),
+                    attribute(normals, triangle[2])
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_steps_get_coarser_further_from_the_camera() {
+        let mut params = test_params(1);
+        let camera = nalgebra_glm::vec2(8.0, 8.0);
+        let steps: Vec<usize> = [0, 2, 4, 8, 20]
+            .into_iter()
+            .map(|x| params.mesh_step(ChunkCoord::new(x, 0), camera))
+            .collect();
+        assert_eq!(steps, vec![1, 2, 4, 8, 8]);
+
+        // Steps always fit evenly into a chunk
+        params.chunk_width = 12;
+        assert_eq!(params.mesh_step(ChunkCoord::new(20, 0), camera), 4);
+    }
+
+    #[test]
+    fn meshes_only_get_finer_once_the_camera_is_well_back_inside() {
+        let params = test_params(1);
+        let coord = ChunkCoord::new(0, 0);
+        let camera = |distance: f32| nalgebra_glm::vec2(8.0 + distance * 16.0, 8.0);
+
+        // Coarser as soon as the camera is past a LOD distance
+        assert_eq!(params.next_mesh_step(coord, camera(2.1), 1), 2);
+        // But not finer again until it's well back inside it
+        assert_eq!(params.next_mesh_step(coord, camera(1.9), 2), 2);
+        assert_eq!(params.next_mesh_step(coord, camera(1.4), 2), 1);
+        assert_eq!(params.next_mesh_step(coord, camera(3.9), 8), 4);
+        assert_eq!(params.next_mesh_step(coord, camera(1.0), 8), 1);
+    }
+
+    #[test]
+    fn wrapping_chunks_repeat_the_map() {
+        let params = MapParams {
+            map_width: 1024,
+            edges: MapEdges::Wrapping,
+            ..test_params(1)
+        };
+        let on_map = generated_chunk(&params, ChunkCoord::new(63, 2));
+        let wrapped = generated_chunk(&params, ChunkCoord::new(-1, 2));
+        for y in 0..=16 {
+            for x in 0..=16 {
+                let p = nalgebra_glm::vec2(x as f32, y as f32);
+                assert!((on_map.height_nearest(p) - wrapped.height_nearest(p)).abs() < 1e-3);
+            }
+        }
+        assert_eq!(on_map.prop_coord, wrapped.prop_coord);
+    }
+
+    #[test]
+    fn rivers_have_a_water_surface_over_them() {
+        let params = test_params(1);
+        let mut with_rivers = 0;
+        for x in 510..518 {
+            let chunk = generated_chunk(&params, ChunkCoord::new(x, 513));
+            let river_cells = (0..16 * 16)
+                .map(|i| nalgebra_glm::vec2((i % 16) as f32, (i / 16) as f32))
+                .filter(|cell| chunk.has_fresh_water(*cell))
+                .count();
+            let Some((indices, vertices, _, _)) = chunk.create_water_mesh() else {
+                assert_eq!(river_cells, 0);
+                continue;
+            };
+            with_rivers += 1;
+            assert_eq!(vertices.len(), river_cells * 4 * 3);
+            assert_eq!(indices.len(), river_cells * 6);
+            for vertex in vertices.chunks(3) {
+                let p = nalgebra_glm::vec2(vertex[0], vertex[1]);
+                assert!(vertex[2] > chunk.height_nearest(p));
+            }
+        }
+        assert!(with_rivers > 0);
+    }
+
+    #[test]
+    fn small_wrapping_maps_never_load_a_chunk_twice() {
+        for side_chunks in 3..12 {
+            let mut map =
+                ChunkedPerlinMap::new(side_chunks * 16, 16, 0.01, 1, 1.0, MapEdges::Wrapping);
+            map.set_render_distance(8);
+            map.set_unload_radius(10);
+            let loaded_width = 2 * map.unload_distance() + 1;
+            assert!(loaded_width <= side_chunks, "{} chunks wide", side_chunks);
+            assert!(map.render_distance() < map.unload_distance());
+        }
+    }
+
+    #[test]
+    fn chunk_seed_depends_on_world_seed_and_coordinates() {
+        let seed = chunk_seed(42, 512, 513);
+        assert_eq!(seed, chunk_seed(42, 512, 513));
+        assert_ne!(seed, chunk_seed(43, 512, 513));
+        assert_ne!(seed, chunk_seed(42, 513, 512));
+        assert_ne!(seed, chunk_seed(42, 512, 514));
     }
 }
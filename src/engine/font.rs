@@ -86,6 +86,22 @@ impl Font {
         }
     }
 
+    /// Width in pixels of the longest line of the text, if it were drawn.
+    pub fn measure(&self, text: &str) -> f32 {
+        text.lines()
+            .map(|line| {
+                line.chars()
+                    .filter(|c| c.is_ascii_graphic() || *c == ' ')
+                    .map(|c| self.get_glyph(c as u8).advance as f32)
+                    .sum::<f32>()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height as f32
+    }
+
     fn pack_gylphs(&mut self, font: &sdl2::ttf::Font, renderer: &RenderContext) {
         let mut x_offset: usize = 0;
         let mut y_offset: usize = 0;
@@ -23,6 +23,8 @@ pub(crate) mod physics;
 #[allow(unused)]
 pub(crate) mod plane;
 #[allow(unused)]
+pub(crate) mod point_light;
+#[allow(unused)]
 pub(crate) mod ray;
 #[allow(unused)]
 pub(crate) mod rectangle;
@@ -36,3 +38,5 @@ pub(crate) mod render_core;
 pub(crate) mod shadow_map;
 #[allow(unused)]
 pub(crate) mod sphere;
+#[allow(unused)]
+pub(crate) mod worker_pool;
@@ -18,7 +18,7 @@ static HASH: [u8; 256] = [
     250, 1, 8, 198, 250, 209, 92, 222, 173, 21, 88, 102, 219,
 ];
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct PerlinMap {
     cells: Vec<Cell>,
     map_width: usize,
@@ -30,17 +30,25 @@ struct Cell {
     pub flow: f32,
 }
 
+/// A raindrop that runs down the terrain while it's eroded. Its position is kept relative to the cell it fell on, so
+/// that it runs exactly the same way on every map it falls on, wherever the maps start.
 struct Particle {
     pub age: usize,
 
-    pub pos: nalgebra_glm::Vec3,
-    pub vel: nalgebra_glm::Vec3,
+    pub origin: nalgebra_glm::I32Vec2, // Cell the drop fell on
+    pub pos: nalgebra_glm::Vec2,       // Relative to the origin
+    pub vel: nalgebra_glm::Vec2,
 
     pub volume: f32,   // Total particle volume
-    pub sediment: f32, // Fraction of volume that is sediment
-    pub rain: f32,     // Separate
+    pub sediment: f32, // Amount of sediment carried
 }
 
+/// How many cells of terrain a map needs around the part of it that's used, for the erosion in that part to come out
+/// the same as on any other map. Raindrops can't run further than this.
+pub const EROSION_PADDING: usize = MAX_AGE + 2;
+const MAX_AGE: usize = 32; //< Steps a raindrop takes before it dries up, it moves less than a cell each step
+const DROP_SPACING: i32 = 2; //< A raindrop falls on every this many cells, in both directions
+
 pub trait HeightMap {
     fn height_nearest(&self, p: nalgebra_glm::Vec2) -> f32;
     fn height_interpolated(&self, p: nalgebra_glm::Vec2) -> f32;
@@ -49,60 +57,80 @@ pub trait HeightMap {
 }
 
 impl Particle {
-    fn new(pos: nalgebra_glm::Vec3) -> Self {
+    fn new(origin: nalgebra_glm::I32Vec2, pos: nalgebra_glm::Vec2) -> Self {
         Self {
             age: 0,
+            origin,
             pos,
-            vel: nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            vel: nalgebra_glm::vec2(0.0, 0.0),
             volume: 1.0,
             sediment: 0.0,
-            rain: 0.0,
         }
     }
 
-    fn descend(&mut self, map: &mut PerlinMap) -> bool {
-        loop {
-            const MIN_VOLUME: f32 = 0.01;
-            const DEPOSITION_RATE: f32 = 0.1;
-            const EVAPORATION_RATE: f32 = 0.1;
-            const MAX_AGE: usize = 100;
-            const ENTRAINMENT: f32 = 1.0;
-
-            if self.age > MAX_AGE {
-                break;
+    /// Runs the drop down the terrain until it dries up, reaches the sea, or runs off the map. Water that runs
+    /// downhill picks up sediment, and water that slows down or pools drops it again. The terrain itself isn't
+    /// changed, the drop's flow and erosion are added to `changes` instead, so that drops don't affect each other.
+    fn descend(&mut self, map: &PerlinMap, changes: &mut [Cell]) {
+        const MIN_VOLUME: f32 = 0.01;
+        const DEPOSITION_RATE: f32 = 0.1;
+        const EVAPORATION_RATE: f32 = 0.05;
+        const ENTRAINMENT: f32 = 1.0;
+        const FRICTION: f32 = 0.1;
+        const MAX_SPEED: f32 = 1.0;
+        const MAX_EROSION: f32 = 0.02; // Most a drop can dig out of a cell in a step
+        const SEA_HEIGHT: f32 = 0.5;
+
+        let mut last = None;
+        while self.age < MAX_AGE && self.volume > MIN_VOLUME {
+            let cell = self.cell();
+            let Some(index) = map.index(cell) else {
+                return;
+            };
+            let height = map.cells[index].height;
+            if height < SEA_HEIGHT {
+                // The rest of the sediment washes out to sea
+                changes[index].height += self.sediment;
+                return;
+            }
+            if last != Some(index) {
+                changes[index].flow += self.volume;
+                last = Some(index);
             }
-
-            let grad = map.get_normal(self.pos.xy());
 
             // Accelerate particle using classical mechanics
-            let old_pos = self.pos;
-            self.vel += grad / self.volume;
-            self.vel += nalgebra_glm::vec3(0.0, 0.1, 0.0);
-            if nalgebra_glm::length(&self.vel) > 0.0 {
-                self.vel = (2.0 as f32).sqrt() * nalgebra_glm::normalize(&self.vel);
+            let offset = self.pos - nalgebra_glm::floor(&self.pos);
+            self.vel += map.cell_normal(cell, offset).xy() / self.volume;
+            let speed = nalgebra_glm::length(&self.vel);
+            if speed > MAX_SPEED {
+                self.vel *= MAX_SPEED / speed;
             }
-            self.vel.z -= 1.0; // gravity
+            self.vel *= 1.0 - FRICTION;
             self.pos += self.vel;
-            let mut flying = false;
-            if self.pos.z < map.height(self.pos.xy()) {
-                self.pos.z = map.height(self.pos.xy());
-                flying = false;
-            }
-
-            // Update flow, momentum
-            if !flying {
-                if self.pos.z >= 0.5 {
-                    map.incr_flow(old_pos.xy(), self.rain);
-                } else {
-                    self.rain = 1.0;
-                }
-            }
-
-            self.rain *= 1.0 - EVAPORATION_RATE;
 
+            let Some(next) = map.index(self.cell()) else {
+                return;
+            };
+            let drop = height - map.cells[next].height;
+            let capacity =
+                (ENTRAINMENT * self.volume * nalgebra_glm::length(&self.vel) * drop).max(0.0);
+            let transfer = (DEPOSITION_RATE * (capacity - self.sediment)).min(MAX_EROSION);
+            self.sediment += transfer;
+            changes[index].height -= transfer;
+
+            self.volume *= 1.0 - EVAPORATION_RATE;
             self.age += 1;
         }
-        false
+
+        // What's left settles where the drop dried up
+        if let Some(index) = map.index(self.cell()) {
+            changes[index].height += self.sediment;
+        }
+    }
+
+    fn cell(&self) -> nalgebra_glm::I32Vec2 {
+        self.origin
+            + nalgebra_glm::I32Vec2::new(self.pos.x.floor() as i32, self.pos.y.floor() as i32)
     }
 }
 
@@ -122,6 +150,7 @@ impl PerlinMap {
         seed: i32,
         amplitude: f32,
         offset: nalgebra_glm::Vec2,
+        wrap: Option<usize>, //< World width the noise repeats at, if it should repeat
     ) {
         assert!(self.cells.len() == 0);
         for y in 0..self.map_width {
@@ -133,6 +162,7 @@ impl PerlinMap {
                         level_of_detail,
                         depth,
                         seed,
+                        wrap,
                     ) * amplitude,
                     flow: 0.0,
                 });
@@ -140,29 +170,53 @@ impl PerlinMap {
         }
     }
 
-    pub fn erode(&mut self, total_particles: usize, seed: u64) {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
-
-        let mut checkpoint = total_particles / 10;
-        for i in 0..total_particles {
-            if i > checkpoint {
-                checkpoint += total_particles / 10;
-                // println!(
-                //     " - {}%",
-                //     (i as f32 / total_particles as f32 * 100.0) as usize
-                // );
+    /// Erodes the terrain with rain, which carves out riverbeds and leaves sediment where the water slows down.
+    /// Where the rain ran is kept in each cell's flow, which is high along rivers and in lakes.
+    ///
+    /// Raindrops fall on cells that are lined up with world coordinates, and they all run over the terrain as it was
+    /// before any erosion. So two maps that overlap erode exactly the same where they overlap, as long as both have
+    /// `EROSION_PADDING` cells of terrain around the overlap.
+    pub fn erode(
+        &mut self,
+        offset: nalgebra_glm::I32Vec2, //< World position of the map's first cell
+        seed: i32,
+        wrap: Option<usize>, //< World width the terrain repeats at, if it repeats
+    ) {
+        let mut changes = vec![Cell::default(); self.cells.len()];
+        for y in 0..self.map_width as i32 {
+            for x in 0..self.map_width as i32 {
+                let mut world_cell = offset + nalgebra_glm::I32Vec2::new(x, y);
+                if let Some(wrap) = wrap {
+                    world_cell.x = world_cell.x.rem_euclid(wrap as i32);
+                    world_cell.y = world_cell.y.rem_euclid(wrap as i32);
+                }
+                if world_cell.x % DROP_SPACING != 0 || world_cell.y % DROP_SPACING != 0 {
+                    continue;
+                }
+                let mut particle = Particle::new(
+                    nalgebra_glm::I32Vec2::new(x, y),
+                    drop_offset(world_cell, seed),
+                );
+                particle.descend(self, &mut changes);
             }
+        }
 
-            let mut drop = Particle::new(nalgebra_glm::vec3(
-                rng.gen_range(0.0..self.map_width as f32),
-                rng.gen_range(0.0..self.map_width as f32),
-                10.0,
-            ));
-            if self.height(drop.pos.xy()) < 0.5 {
-                continue;
-            }
-            while drop.descend(self) {}
+        for (cell, change) in self.cells.iter_mut().zip(changes) {
+            cell.height += change.height;
+            cell.flow += change.flow;
+        }
+    }
+
+    /// A square part of the map, starting at a cell.
+    pub fn cropped(&self, x: usize, y: usize, width: usize) -> PerlinMap {
+        let mut cropped = PerlinMap::new(width);
+        for row in y..y + width {
+            let start = row * self.map_width + x;
+            cropped
+                .cells
+                .extend_from_slice(&self.cells[start..start + width]);
         }
+        cropped
     }
 
     pub fn cascade(&mut self, pos: nalgebra_glm::Vec2) {
@@ -244,13 +298,6 @@ impl PerlinMap {
         self.cells[p.x as usize + p.y as usize * self.map_width].flow
     }
 
-    fn incr_flow(&mut self, p: nalgebra_glm::Vec2, val: f32) {
-        if self.oob(p) {
-            return;
-        }
-        self.cells[p.x as usize + p.y as usize * self.map_width].flow += val
-    }
-
     pub fn get_z_interpolated(&self, p: nalgebra_glm::Vec2) -> f32 {
         assert!(!p.x.is_nan());
         // The coordinates of the tile's origin (bottom left corner)
@@ -293,6 +340,12 @@ impl PerlinMap {
         retval.z
     }
 
+    fn index(&self, cell: nalgebra_glm::I32Vec2) -> Option<usize> {
+        let width = self.map_width as i32;
+        ((0..width).contains(&cell.x) && (0..width).contains(&cell.y))
+            .then(|| (cell.x + cell.y * width) as usize)
+    }
+
     fn oob(&self, p: nalgebra_glm::Vec2) -> bool {
         p.x < 0.0 || p.y < 0.0 || p.x >= self.map_width as f32 || p.y >= self.map_width as f32
     }
@@ -301,31 +354,31 @@ impl PerlinMap {
         assert!(!p.x.is_nan());
         // The coordinates of the tile's origin (bottom left corner)
         let origin = nalgebra_glm::floor(&p);
+        let cell = nalgebra_glm::I32Vec2::new(origin.x as i32, origin.y as i32);
+        self.cell_normal(cell, p - origin)
+    }
 
-        // Coordinates inside the tile. [0,1]
-        let offset = p - origin;
-
-        let offsets = if offset.y <= 1.0 - offset.x {
-            // In bottom triangle
-            [
-                nalgebra_glm::vec2(0.0, 0.0), // Contains the origin
-                nalgebra_glm::vec2(1.0, 0.0),
-                nalgebra_glm::vec2(0.0, 1.0),
-            ]
+    /// Normal of the terrain at a point inside a cell, given relative to the cell's origin.
+    fn cell_normal(
+        &self,
+        cell: nalgebra_glm::I32Vec2,
+        offset: nalgebra_glm::Vec2,
+    ) -> nalgebra_glm::Vec3 {
+        let height = |x: i32, y: i32| {
+            self.index(cell + nalgebra_glm::I32Vec2::new(x, y))
+                .map_or(0.0, |index| self.cells[index].height)
+        };
+        // The cross product of the triangle's edges, worked out by hand since this is called a lot while eroding
+        let normal = if offset.y <= 1.0 - offset.x {
+            // In bottom triangle, which contains the origin
+            let origin = height(0, 0);
+            nalgebra_glm::vec3(origin - height(1, 0), origin - height(0, 1), 1.0)
         } else {
-            // In top triangle
-            [
-                nalgebra_glm::vec2(1.0, 0.0),
-                nalgebra_glm::vec2(1.0, 1.0), // Contains the anti-origin
-                nalgebra_glm::vec2(0.0, 1.0),
-            ]
+            // In top triangle, which contains the anti-origin
+            let anti_origin = height(1, 1);
+            nalgebra_glm::vec3(height(0, 1) - anti_origin, height(1, 0) - anti_origin, 1.0)
         };
-        let offsets: Vec<nalgebra_glm::Vec3> = offsets
-            .iter()
-            .map(|o| nalgebra_glm::vec3(origin.x + o.x, origin.y + o.y, self.height(origin + o)))
-            .collect();
-
-        tri_normal(offsets[0], offsets[1], offsets[2])
+        normal.normalize()
     }
 
     pub fn get_dot_prod(&self, p: nalgebra_glm::Vec2) -> f32 {
@@ -334,6 +387,23 @@ impl PerlinMap {
         nalgebra_glm::dot(&self.get_normal(p), &nalgebra_glm::vec3(0.0, 0.0, 1.0))
     }
 
+    /// Smooth normal at a cell's corner, from the heights of the cells around it. Cells on the edge of the map are
+    /// missing some of their neighbors, so the nearest cells on the map stand in for them.
+    pub fn vertex_normal(&self, x: usize, y: usize) -> nalgebra_glm::Vec3 {
+        let cell = nalgebra_glm::I32Vec2::new(x as i32, y as i32);
+        let last = self.map_width as i32 - 1;
+        let height = |x: i32, y: i32| {
+            let neighbor = (cell + nalgebra_glm::I32Vec2::new(x, y)).map(|c| c.clamp(0, last));
+            self.cells[self.index(neighbor).unwrap()].height
+        };
+        nalgebra_glm::vec3(
+            0.5 * (height(-1, 0) - height(1, 0)),
+            0.5 * (height(0, -1) - height(0, 1)),
+            1.0,
+        )
+        .normalize()
+    }
+
     pub fn create_bulge(&mut self) {
         for y in 0..self.map_width {
             for x in 0..self.map_width {
@@ -375,7 +445,30 @@ impl PerlinMap {
     }
 }
 
-fn perlin2d(x: f32, y: f32, freq: f32, depth: i32, seed: i32) -> f32 {
+/// Where in a cell the raindrop that falls on it lands.
+fn drop_offset(cell: nalgebra_glm::I32Vec2, seed: i32) -> nalgebra_glm::Vec2 {
+    let mut hash = (cell.x as u32).wrapping_mul(0x9E37_79B1)
+        ^ (cell.y as u32).wrapping_mul(0x85EB_CA77)
+        ^ (seed as u32).wrapping_mul(0xC2B2_AE3D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2C1B_3C6D);
+    hash ^= hash >> 12;
+    nalgebra_glm::vec2(
+        (hash & 0xFFFF) as f32 / 65536.0,
+        (hash >> 16) as f32 / 65536.0,
+    )
+}
+
+fn perlin2d(x: f32, y: f32, freq: f32, depth: i32, seed: i32, wrap: Option<usize>) -> f32 {
+    // Noise that repeats has to fit a whole number of lattice cells in the width it repeats at, so the frequency is
+    // nudged to the nearest one that does
+    let (freq, mut period) = match wrap {
+        Some(width) => {
+            let cells = (width as f32 * freq).round().max(1.0);
+            (cells / width as f32, Some(cells as i32))
+        }
+        None => (freq, None),
+    };
     let mut xa = x * freq;
     let mut ya = y * freq;
     let mut amp: f32 = 1.0;
@@ -383,9 +476,10 @@ fn perlin2d(x: f32, y: f32, freq: f32, depth: i32, seed: i32) -> f32 {
     let mut div: f32 = 256.0;
 
     for _ in 0..depth {
-        fin += noise2d(xa, ya, seed) * amp;
+        fin += noise2d(xa, ya, seed, period) * amp;
         xa *= 2.0;
         ya *= 2.0;
+        period = period.map(|period| period * 2);
         amp *= 0.5;
         div += 256.0 * amp;
     }
@@ -393,17 +487,19 @@ fn perlin2d(x: f32, y: f32, freq: f32, depth: i32, seed: i32) -> f32 {
     fin / div
 }
 
-fn noise2d(x: f32, y: f32, seed: i32) -> f32 {
-    let x_int = x as i32;
-    let y_int = y as i32;
+fn noise2d(x: f32, y: f32, seed: i32, period: Option<i32>) -> f32 {
+    // Floored rather than truncated, so that the noise carries on past zero into negative coordinates
+    let x_int = x.floor() as i32;
+    let y_int = y.floor() as i32;
     let x_frac: f32 = x - (x_int as f32);
     let y_frac: f32 = y - (y_int as f32);
+    let lattice = |n: i32| period.map_or(n, |period| n.rem_euclid(period));
 
     // Calculate noise values once
-    let s = noise2(x_int, y_int, seed);
-    let t = noise2(x_int + 1, y_int, seed);
-    let u = noise2(x_int, y_int + 1, seed);
-    let v = noise2(x_int + 1, y_int + 1, seed);
+    let s = noise2(lattice(x_int), lattice(y_int), seed);
+    let t = noise2(lattice(x_int + 1), lattice(y_int), seed);
+    let u = noise2(lattice(x_int), lattice(y_int + 1), seed);
+    let v = noise2(lattice(x_int + 1), lattice(y_int + 1), seed);
 
     // Calculate x smothing
     let low = smooth_inter(s, t, x_frac);
@@ -476,3 +572,27 @@ fn tri_normal(
     let normal = nalgebra_glm::cross(&edge1, &edge2).normalize();
     normal
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normals_on_the_edge_of_the_map_follow_the_terrain() {
+        // A gentle slope up along x, on a plateau
+        let mut map = PerlinMap::new(5);
+        map.cells = (0..5 * 5)
+            .map(|index| Cell {
+                height: 1.0 + 0.1 * (index % 5) as f32,
+                flow: 0.0,
+            })
+            .collect();
+        let inside = map.vertex_normal(2, 2);
+        assert!(inside.x < 0.0);
+        for (x, y) in [(0, 0), (0, 2), (4, 2), (2, 0), (2, 4), (4, 4)] {
+            let normal = map.vertex_normal(x, y);
+            assert!(normal.x < 0.0 && normal.x >= inside.x, "{:?}", (x, y));
+            assert_eq!(normal.y, 0.0);
+        }
+    }
+}
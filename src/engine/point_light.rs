@@ -0,0 +1,38 @@
+use hecs::World;
+
+/// The most point lights the 3d shader can light a frame with. Must match `MAX_POINT_LIGHTS` in `3d.frag`.
+pub const MAX_POINT_LIGHTS: usize = 8;
+
+/// A light that shines out in every direction from a point, and fades out to nothing at its radius.
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: nalgebra_glm::Vec3,
+    pub color: nalgebra_glm::Vec3,
+    pub radius: f32, //< A radius of 0 turns the light off
+}
+
+impl PointLight {
+    pub fn new(position: nalgebra_glm::Vec3, color: nalgebra_glm::Vec3, radius: f32) -> Self {
+        Self {
+            position,
+            color,
+            radius,
+        }
+    }
+}
+
+/// The lit point lights that are closest to a point, up to `MAX_POINT_LIGHTS` of them.
+pub(super) fn closest_point_lights(world: &World, point: nalgebra_glm::Vec3) -> Vec<PointLight> {
+    let mut lights: Vec<PointLight> = world
+        .query::<&PointLight>()
+        .iter()
+        .map(|(_, light)| *light)
+        .filter(|light| light.radius > 0.0)
+        .collect();
+    lights.sort_by(|a, b| {
+        nalgebra_glm::distance(&a.position, &point)
+            .total_cmp(&nalgebra_glm::distance(&b.position, &point))
+    });
+    lights.truncate(MAX_POINT_LIGHTS);
+    lights
+}
@@ -11,4 +11,11 @@ impl Rectangle {
             size: nalgebra_glm::vec2(w, h),
         }
     }
+
+    pub fn contains(&self, point: nalgebra_glm::Vec2) -> bool {
+        point.x >= self.pos.x
+            && point.y >= self.pos.y
+            && point.x < self.pos.x + self.size.x
+            && point.y < self.pos.y + self.size.y
+    }
 }
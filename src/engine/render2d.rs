@@ -162,6 +162,15 @@ impl RenderContext {
         font.draw(pos, text, self);
     }
 
+    /// Returns the size in pixels that the text would take up when drawn with the current font.
+    pub fn measure_text(&self, text: &str) -> nalgebra_glm::Vec2 {
+        let font = self.get_font_from_id(self.font.borrow().unwrap()).unwrap();
+        nalgebra_glm::vec2(
+            font.measure(text),
+            font.height() * text.lines().count().max(1) as f32,
+        )
+    }
+
     // TODO: Rename `copy_texture` or something, implement `fill_rect` with 2d-color.frag shader
     pub fn copy_texture(&self, dest: Rectangle, texture_id: TextureId, texture_dest: Rectangle) {
         let res = self.int_screen_resolution.borrow();
@@ -216,6 +225,20 @@ impl RenderContext {
         self.draw(quad_mesh.borrow(), model_matrix, view_matrix, proj_matrix);
     }
 
+    /// Copies the entire texture to the destination, useful for icons.
+    pub fn copy_whole_texture(&self, dest: Rectangle, texture_id: TextureId) {
+        let (texture_width, texture_height) = self
+            .get_texture_from_id(texture_id)
+            .unwrap()
+            .get_dimensions()
+            .unwrap();
+        self.copy_texture(
+            dest,
+            texture_id,
+            Rectangle::new(0.0, 0.0, texture_width as f32, texture_height as f32),
+        );
+    }
+
     pub fn fill_rect(&self, dest: Rectangle) {
         let res = self.int_screen_resolution.borrow();
         unsafe {
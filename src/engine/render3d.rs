@@ -9,6 +9,7 @@ use super::{
     frustrum::Frustrum,
     objects::*,
     physics::PositionComponent,
+    point_light::closest_point_lights,
     render_core::{ModelComponent, ProgramId, RenderContext},
     shadow_map::DirectionalLightSource,
 };
@@ -52,6 +53,31 @@ impl RenderContext {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
         }
 
+        // Only the point lights closest to the camera are used
+        let point_lights = closest_point_lights(world, self.camera.borrow().lookat());
+        let positions: Vec<f32> = point_lights
+            .iter()
+            .flat_map(|light| [light.position.x, light.position.y, light.position.z])
+            .collect();
+        let colors: Vec<f32> = point_lights
+            .iter()
+            .flat_map(|light| [light.color.x, light.color.y, light.color.z])
+            .collect();
+        let radii: Vec<f32> = point_lights.iter().map(|light| light.radius).collect();
+        let u_point_light_count = self.get_program_uniform("u_point_light_count").unwrap();
+        let u_point_light_positions = self.get_program_uniform("u_point_light_positions").unwrap();
+        let u_point_light_colors = self.get_program_uniform("u_point_light_colors").unwrap();
+        let u_point_light_radii = self.get_program_uniform("u_point_light_radii").unwrap();
+        unsafe {
+            gl::Uniform1i(u_point_light_count.id, point_lights.len() as i32);
+            if !point_lights.is_empty() {
+                let count = point_lights.len() as i32;
+                gl::Uniform3fv(u_point_light_positions.id, count, positions.as_ptr());
+                gl::Uniform3fv(u_point_light_colors.id, count, colors.as_ptr());
+                gl::Uniform1fv(u_point_light_radii.id, count, radii.as_ptr());
+            }
+        }
+
         let (light_view_matrix, light_proj_matrix) =
             directional_light.shadow_camera.view_proj_matrices();
         let u_light_matrix = Uniform::new(self.get_current_program_id(), "light_mvp").unwrap();
@@ -69,32 +95,75 @@ impl RenderContext {
         let mut rendered = 0;
 
         let (view_matrix, proj_matrix) = self.camera.borrow().view_proj_matrices();
+        let u_tint = self.get_program_uniform("u_tint").unwrap();
+        let camera_position = self.camera.borrow().position();
+        let mut see_through = vec![];
         for model_id in bvh.iter_frustrum(camera_frustrum, debug) {
             rendered += 1;
-            let mut model = world.get::<&mut ModelComponent>(model_id).unwrap();
-            let mesh = self.get_mesh_from_id(model.mesh_id).unwrap();
-            let texture = self.get_texture_from_id(model.texture_id).unwrap();
-            let model_matrix = model.get_model_matrix();
-
-            if model.outlined {
-                unsafe {
-                    gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
-                    gl::StencilMask(0xFF);
-                }
-            } else {
-                unsafe {
-                    gl::StencilMask(0x00);
-                }
+            let model = world.get::<&ModelComponent>(model_id).unwrap();
+            if model.tint.w < 1.0 {
+                let distance = nalgebra_glm::distance2(&model.get_position(), &camera_position);
+                see_through.push((distance, model_id));
+                continue;
             }
+            self.draw_model(&model, &u_tint, directional_light, view_matrix, proj_matrix);
+        }
 
-            texture.activate(gl::TEXTURE0);
-            texture.associate_uniform(self.get_current_program_id(), 0, "texture0");
+        // See-through models are drawn last and back to front, so that they blend over everything behind them. They
+        // shouldn't hide what's behind them either
+        see_through.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+        }
+        for (_, model_id) in see_through {
+            let model = world.get::<&ModelComponent>(model_id).unwrap();
+            self.draw_model(&model, &u_tint, directional_light, view_matrix, proj_matrix);
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+        }
+        // println!("{:?}", rendered);
+    }
 
-            directional_light.activate_framebuffer(self.get_current_program_id());
+    fn draw_model(
+        &self,
+        model: &ModelComponent,
+        u_tint: &Uniform,
+        directional_light: &DirectionalLightSource,
+        view_matrix: nalgebra_glm::Mat4,
+        proj_matrix: nalgebra_glm::Mat4,
+    ) {
+        let mesh = self.get_mesh_from_id(model.mesh_id).unwrap();
+        let texture = self.get_texture_from_id(model.texture_id).unwrap();
+        let model_matrix = model.get_model_matrix();
+
+        if model.outlined {
+            unsafe {
+                gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+                gl::StencilMask(0xFF);
+            }
+        } else {
+            unsafe {
+                gl::StencilMask(0x00);
+            }
+        }
 
-            self.draw(mesh.borrow(), model_matrix, view_matrix, proj_matrix);
+        unsafe {
+            gl::Uniform4f(
+                u_tint.id,
+                model.tint.x,
+                model.tint.y,
+                model.tint.z,
+                model.tint.w,
+            );
         }
-        // println!("{:?}", rendered);
+
+        texture.activate(gl::TEXTURE0);
+        texture.associate_uniform(self.get_current_program_id(), 0, "texture0");
+
+        directional_light.activate_framebuffer(self.get_current_program_id());
+
+        self.draw(mesh.borrow(), model_matrix, view_matrix, proj_matrix);
     }
 
     pub fn render_3d_outlines_system(&self, world: &mut World, bvh: &BVH<Entity>) {
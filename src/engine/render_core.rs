@@ -29,7 +29,8 @@ pub struct RenderContext {
 }
 
 struct ResourceManager<Resource, Id: OpaqueId> {
-    resources: Vec<Resource>,
+    resources: Vec<Option<Resource>>, //< Removed resources leave a hole, which the next added resource fills
+    free: Vec<usize>,
     keys: HashMap<&'static str, Id>,
 }
 
@@ -59,6 +60,7 @@ pub struct ModelComponent {
     model_matrix: nalgebra_glm::Mat4,
     pub shown: bool,
     pub outlined: bool,
+    pub tint: nalgebra_glm::Vec4, //< Multiplied with the model's color. Alpha below 1 makes it see-through
 }
 
 /// Stores the geometry of a mesh. Meshes are registered in the mesh manager, and can be potentially shared across
@@ -200,10 +202,19 @@ impl RenderContext {
         self.mesh_manager.borrow_mut().add(mesh, name)
     }
 
+    /// Frees a mesh's geometry. The id can be handed out again to a new mesh, so it must not be used afterwards.
+    pub fn remove_mesh(&self, id: MeshId) {
+        self.mesh_manager.borrow_mut().remove(id);
+    }
+
     pub fn add_mesh_from_obj(&self, obj_file_data: &[u8], name: Option<&'static str>) -> MeshId {
         self.add_mesh(Mesh::from_obj(obj_file_data), name)
     }
 
+    /// Uploads a mesh from its indices and a list of attributes, which are positions, normals and texture coordinates,
+    /// three floats each. The z of a texture coordinate says whether the triangles that end on the vertex take their
+    /// look from it alone: 0 for no, 1 for their texture coordinate, and 2 for their normal too. Every mesh but the
+    /// terrain's leaves it at 0.
     pub fn add_mesh_from_verts(
         &self,
         indices: Vec<u32>,
@@ -411,21 +422,41 @@ impl<Resource, Id: OpaqueId + Debug> ResourceManager<Resource, Id> {
     pub fn new() -> Self {
         Self {
             resources: vec![],
+            free: vec![],
             keys: HashMap::new(),
         }
     }
 
     pub fn add(&mut self, res: Resource, name: Option<&'static str>) -> Id {
-        let id = Id::new(self.resources.len());
-        self.resources.push(res);
+        let id = match self.free.pop() {
+            Some(index) => {
+                self.resources[index] = Some(res);
+                Id::new(index)
+            }
+            None => {
+                self.resources.push(Some(res));
+                Id::new(self.resources.len() - 1)
+            }
+        };
         if name.is_some() {
             self.keys.insert(name.unwrap(), id);
         }
         id
     }
 
+    /// Drops a resource, and forgets its name if it had one.
+    pub fn remove(&mut self, id: Id) {
+        let Some(resource) = self.resources.get_mut(id.as_usize()) else {
+            return;
+        };
+        if resource.take().is_some() {
+            self.free.push(id.as_usize());
+            self.keys.retain(|_, key| key.as_usize() != id.as_usize());
+        }
+    }
+
     pub fn get_from_id(&self, id: Id) -> Option<&Resource> {
-        self.resources.get(id.as_usize())
+        self.resources.get(id.as_usize())?.as_ref()
     }
 
     pub fn get_id_from_name(&self, name: &'static str) -> Option<Id> {
@@ -441,6 +472,7 @@ impl<Resource, Id: OpaqueId> Default for ResourceManager<Resource, Id> {
     fn default() -> Self {
         Self {
             resources: vec![],
+            free: vec![],
             keys: HashMap::new(),
         }
     }
@@ -491,6 +523,7 @@ impl ModelComponent {
             model_matrix: Self::construct_model_matrix(&position, &scale),
             shown: true,
             outlined: false,
+            tint: nalgebra_glm::vec4(1.0, 1.0, 1.0, 1.0),
         }
     }
 
@@ -564,6 +597,17 @@ impl Mesh {
         }
     }
 
+    /// How many bytes of VRAM the mesh's buffers take up. Every attribute has its own copy of the indices.
+    pub fn vram_bytes(&self) -> usize {
+        self.geometry
+            .iter()
+            .map(|data| {
+                data.vertex_data.len() * std::mem::size_of::<f32>()
+                    + self.indices.len() * std::mem::size_of::<u32>()
+            })
+            .sum()
+    }
+
     pub fn from_obj(obj_file_data: &[u8]) -> Self {
         let obj: Obj<TexturedVertex> = load_obj(&obj_file_data[..]).unwrap();
         let vb: Vec<TexturedVertex> = obj.vertices;
@@ -572,6 +616,11 @@ impl Mesh {
         let vertices = flatten_positions(&vb);
         let normals = flatten_normals(&vb);
         let uv = flatten_uv(&vb);
+        // Only terrain meshes give whole triangles the look of their last vertex
+        assert!(
+            uv.chunks(3).all(|uv| uv[2] == 0.0),
+            "OBJ texture coordinates can't have a w"
+        );
 
         let data = vec![&vertices, &normals, &uv];
 
@@ -616,3 +665,27 @@ fn vec_u32_from_vec_u16(input: &Vec<u16>) -> Vec<u32> {
     }
     retval
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obj_meshes_never_use_flat_attributes() {
+        let objs: [&[u8]; 7] = [
+            include_bytes!("../../res/bush.obj"),
+            include_bytes!("../../res/chest.obj"),
+            include_bytes!("../../res/cone.obj"),
+            include_bytes!("../../res/cube.obj"),
+            include_bytes!("../../res/mob.obj"),
+            include_bytes!("../../res/quad-xy.obj"),
+            include_bytes!("../../res/quad.obj"),
+        ];
+        for data in objs {
+            let obj: Obj<TexturedVertex> = load_obj(data).unwrap();
+            let uv = flatten_uv(&obj.vertices);
+            assert!(!uv.is_empty());
+            assert!(uv.chunks(3).all(|uv| uv[2] == 0.0));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+/// A few threads that do jobs in the background. Every job is done by the same function, and the results are picked
+/// up with `try_recv` in the order the jobs finish, which isn't always the order they were sent in. Once the pool is
+/// dropped, the threads stop after the job they're on.
+pub struct WorkerPool<Job, Output> {
+    jobs: Sender<Job>,
+    outputs: Receiver<Output>,
+}
+
+impl<Job: Send + 'static, Output: Send + 'static> WorkerPool<Job, Output> {
+    pub fn new(threads: usize, work: fn(Job) -> Output) -> Self {
+        let (jobs, job_receiver) = channel::<Job>();
+        let (output_sender, outputs) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..threads.max(1) {
+            let job_receiver = job_receiver.clone();
+            let output_sender = output_sender.clone();
+            std::thread::spawn(move || loop {
+                // The lock is let go of before the job is done, so that the other threads can take jobs too
+                let job = job_receiver.lock().unwrap().recv();
+                let Ok(job) = job else {
+                    return;
+                };
+                if output_sender.send(work(job)).is_err() {
+                    return;
+                }
+            });
+        }
+        Self { jobs, outputs }
+    }
+
+    /// A pool with a thread for every core, except for one that's left for the main thread.
+    pub fn with_available_threads(work: fn(Job) -> Output) -> Self {
+        let cores = std::thread::available_parallelism().map_or(2, |cores| cores.get());
+        Self::new(cores - 1, work)
+    }
+
+    pub fn submit(&self, job: Job) {
+        self.jobs.send(job).unwrap();
+    }
+
+    /// A finished job's output, if there is one yet.
+    pub fn try_recv(&self) -> Option<Output> {
+        self.outputs.try_recv().ok()
+    }
+}
@@ -0,0 +1,335 @@
+use hecs::{Entity, World};
+use rand::Rng;
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    chunked_map::{ChunkedPerlinMap, WATER_LEVEL},
+    perlin::HeightMap,
+    render_core::{ModelComponent, RenderContext},
+};
+
+use super::items::ItemKind;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimalKind {
+    Rabbit,
+    Deer,
+}
+
+/// What an animal is currently doing. Each state lasts for some amount of ticks before the animal picks a new one.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnimalState {
+    Idle {
+        ticks_left: usize,
+    },
+    Wander {
+        direction: nalgebra_glm::Vec2,
+        ticks_left: usize,
+    },
+    Graze {
+        ticks_left: usize,
+    },
+    Flee {
+        ticks_left: usize,
+    },
+}
+
+/// A wild creature that wanders around, and runs away from the player. Leaves a carcass behind when killed.
+pub struct Animal {
+    pub kind: AnimalKind,
+    pub state: AnimalState,
+    pub health: usize,
+}
+
+/// What's left of an animal after it's killed. It has to be butchered with a knife to get the meat and hide.
+pub struct Carcass {
+    pub kind: AnimalKind,
+}
+
+const FLEE_TICKS: usize = 120;
+const SHORE_MARGIN: f32 = 0.1; //< How far above the water animals stay
+
+impl AnimalKind {
+    pub const ALL: [AnimalKind; 2] = [AnimalKind::Rabbit, AnimalKind::Deer];
+
+    /// Identifier used to refer to the animal in data files, like save files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            AnimalKind::Rabbit => "rabbit",
+            AnimalKind::Deer => "deer",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<AnimalKind> {
+        AnimalKind::ALL.iter().copied().find(|kind| kind.id() == id)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimalKind::Rabbit => "Rabbit",
+            AnimalKind::Deer => "Deer",
+        }
+    }
+
+    pub fn mesh_name(&self) -> &'static str {
+        "mob"
+    }
+
+    pub fn texture_name(&self) -> &'static str {
+        match self {
+            AnimalKind::Rabbit => "rabbit",
+            AnimalKind::Deer => "deer",
+        }
+    }
+
+    pub fn scale(&self) -> nalgebra_glm::Vec3 {
+        match self {
+            AnimalKind::Rabbit => nalgebra_glm::vec3(2.0, 1.5, 0.6),
+            AnimalKind::Deer => nalgebra_glm::vec3(6.0, 3.0, 2.5),
+        }
+    }
+
+    /// Size of the animal when it's lying dead on the ground.
+    pub fn carcass_scale(&self) -> nalgebra_glm::Vec3 {
+        let scale = self.scale();
+        nalgebra_glm::vec3(scale.x * 3.0, scale.y, scale.z * 0.25)
+    }
+
+    /// How many hits it takes to kill the animal.
+    pub fn max_health(&self) -> usize {
+        match self {
+            AnimalKind::Rabbit => 1,
+            AnimalKind::Deer => 3,
+        }
+    }
+
+    /// Distance moved per tick when wandering.
+    pub fn walk_speed(&self) -> f32 {
+        match self {
+            AnimalKind::Rabbit => 0.01,
+            AnimalKind::Deer => 0.015,
+        }
+    }
+
+    /// Distance moved per tick when fleeing. Always a little slower than the player runs, so that they can be caught.
+    pub fn run_speed(&self) -> f32 {
+        match self {
+            AnimalKind::Rabbit => 0.05,
+            AnimalKind::Deer => 0.055,
+        }
+    }
+
+    /// How close the player can get before the animal runs away.
+    pub fn fear_radius(&self) -> f32 {
+        match self {
+            AnimalKind::Rabbit => 2.0,
+            AnimalKind::Deer => 4.0,
+        }
+    }
+
+    /// Items that are dropped when the animal's carcass is butchered.
+    pub fn drops(&self) -> &'static [ItemKind] {
+        match self {
+            AnimalKind::Rabbit => &[ItemKind::Meat, ItemKind::Hide],
+            AnimalKind::Deer => &[ItemKind::Meat, ItemKind::Meat, ItemKind::Hide],
+        }
+    }
+}
+
+impl Animal {
+    pub fn new(kind: AnimalKind) -> Self {
+        Self {
+            kind,
+            state: AnimalState::Idle { ticks_left: 60 },
+            health: kind.max_health(),
+        }
+    }
+
+    /// Hurts the animal, which makes it run away. Returns true if the animal died.
+    pub fn hit(&mut self, damage: usize) -> bool {
+        self.health = self.health.saturating_sub(damage);
+        self.state = AnimalState::Flee {
+            ticks_left: FLEE_TICKS,
+        };
+        self.health == 0
+    }
+
+    /// Advances the state machine by a tick, and returns the displacement the animal wants to move by.
+    fn think(
+        &mut self,
+        position: nalgebra_glm::Vec2,
+        player_position: nalgebra_glm::Vec2,
+        rng: &mut impl Rng,
+    ) -> nalgebra_glm::Vec2 {
+        let to_player = player_position - position;
+        if nalgebra_glm::length(&to_player) < self.kind.fear_radius() {
+            self.state = AnimalState::Flee {
+                ticks_left: FLEE_TICKS,
+            };
+        }
+
+        match &mut self.state {
+            AnimalState::Idle { ticks_left } | AnimalState::Graze { ticks_left } => {
+                *ticks_left = ticks_left.saturating_sub(1);
+                if *ticks_left == 0 {
+                    self.state = random_state(rng);
+                }
+                nalgebra_glm::zero()
+            }
+            AnimalState::Wander {
+                direction,
+                ticks_left,
+            } => {
+                let direction = *direction;
+                *ticks_left = ticks_left.saturating_sub(1);
+                if *ticks_left == 0 {
+                    self.state = random_state(rng);
+                }
+                direction * self.kind.walk_speed()
+            }
+            AnimalState::Flee { ticks_left } => {
+                *ticks_left = ticks_left.saturating_sub(1);
+                if *ticks_left == 0 {
+                    self.state = AnimalState::Idle { ticks_left: 60 };
+                }
+                if to_player.norm_squared() > 0.0 {
+                    -to_player.normalize() * self.kind.run_speed()
+                } else {
+                    nalgebra_glm::zero()
+                }
+            }
+        }
+    }
+}
+
+fn random_state(rng: &mut impl Rng) -> AnimalState {
+    match rng.gen_range(0..3) {
+        0 => AnimalState::Idle {
+            ticks_left: rng.gen_range(60..240),
+        },
+        1 => {
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            AnimalState::Wander {
+                direction: nalgebra_glm::vec2(angle.cos(), angle.sin()),
+                ticks_left: rng.gen_range(60..180),
+            }
+        }
+        _ => AnimalState::Graze {
+            ticks_left: rng.gen_range(120..360),
+        },
+    }
+}
+
+/// Spawns an animal into the world at a position, along with its BVH leaf.
+pub fn spawn_animal(
+    kind: AnimalKind,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name(kind.mesh_name()).unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(kind.texture_name())
+        .unwrap();
+    let entity = world.spawn((
+        ModelComponent::new(mesh, texture, position, kind.scale()),
+        Animal::new(kind),
+    ));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(mesh)
+            .scale(kind.scale())
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Spawns the carcass of an animal into the world at a position, along with its BVH leaf.
+pub fn spawn_carcass(
+    kind: AnimalKind,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name(kind.mesh_name()).unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(kind.texture_name())
+        .unwrap();
+    let model = ModelComponent::new(mesh, texture, position, kind.carcass_scale());
+    let aabb = renderer.get_model_aabb(&model);
+    let entity = world.spawn((model, Carcass { kind }));
+    let node_id = bvh.insert(entity, aabb);
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Moves all of the animals around the map. Animals won't walk into the water.
+pub fn animal_system(
+    world: &mut World,
+    map: &ChunkedPerlinMap,
+    bvh: &mut BVH<Entity>,
+    renderer: &RenderContext,
+    player_position: nalgebra_glm::Vec3,
+) {
+    let mut rng = rand::thread_rng();
+    for (_, (animal, model, bvh_node)) in
+        world.query_mut::<(&mut Animal, &mut ModelComponent, &BVHNodeComponent)>()
+    {
+        let position = model.get_position();
+        let step = animal.think(position.xy(), player_position.xy(), &mut rng);
+        if step.norm_squared() == 0.0 {
+            continue;
+        }
+
+        let next_xy = position.xy() + step;
+        let next_z = map.height_interpolated(next_xy);
+        if next_z < WATER_LEVEL + SHORE_MARGIN {
+            // Turn around at the shore
+            animal.state = AnimalState::Idle { ticks_left: 30 };
+            continue;
+        }
+        let next_position = nalgebra_glm::vec3(next_xy.x, next_xy.y, next_z);
+        model.set_position(next_position);
+        bvh.move_obj(
+            bvh_node.node_id,
+            &renderer.get_model_aabb(model),
+            &(next_position - position),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::scenes::gameplay::{FRICTION, REACH, WALK_SPEED};
+
+    #[test]
+    fn player_can_catch_up_to_fleeing_animals() {
+        for kind in [AnimalKind::Rabbit, AnimalKind::Deer] {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+            let mut animal = Animal::new(kind);
+            let mut position = nalgebra_glm::vec2(0.0, 0.0);
+            // Start just close enough to scare it, and run straight at it the way the player walks
+            let mut player = nalgebra_glm::vec2(kind.fear_radius() - 0.1, 0.0);
+            let mut velocity = nalgebra_glm::vec2(0.0, 0.0);
+            let caught = (0..60 * 10).any(|_| {
+                position += animal.think(position, player, &mut rng);
+                velocity += (position - player).normalize() * WALK_SPEED;
+                player += velocity;
+                velocity *= FRICTION;
+                nalgebra_glm::distance(&position, &player) <= REACH.min(kind.fear_radius()) / 2.0
+            });
+            assert!(caught, "couldn't catch up to a {}", kind.name());
+        }
+    }
+}
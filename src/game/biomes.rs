@@ -0,0 +1,233 @@
+use super::{animals::AnimalKind, farming::CropKind};
+
+/// What kind of land a spot is, which decides how the terrain looks, what grows on it and what lives there.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Beach,
+    Grassland,
+    Forest,
+    Swamp,
+    RockyHighland,
+    Tundra,
+}
+
+const BEACH_HEIGHT: f32 = 0.75; //< Land below this is beach
+const HIGHLAND_HEIGHT: f32 = 2.5; //< Land above this is rocky highland
+const SWAMP_HEIGHT: f32 = 1.4; //< Swamps only form in lowlands below this
+const SWAMP_HYDRATION: f32 = 0.64;
+const FOREST_HYDRATION: f32 = 0.6;
+const TUNDRA_TEMPERATURE: f32 = 0.22;
+const LAPSE_RATE: f32 = 0.04; //< How much colder it gets per unit of height
+
+impl Biome {
+    /// The biome of a spot, given its height, hydration and temperature. Temperature comes from a noise layer, and it
+    /// gets colder the higher up the spot is.
+    pub fn classify(height: f32, hydration: f32, temperature: f32) -> Biome {
+        if height < BEACH_HEIGHT {
+            Biome::Beach
+        } else if temperature - height * LAPSE_RATE < TUNDRA_TEMPERATURE {
+            Biome::Tundra
+        } else if height > HIGHLAND_HEIGHT {
+            Biome::RockyHighland
+        } else if hydration > SWAMP_HYDRATION && height < SWAMP_HEIGHT {
+            Biome::Swamp
+        } else if hydration > FOREST_HYDRATION {
+            Biome::Forest
+        } else {
+            Biome::Grassland
+        }
+    }
+
+    /// Where the biome's color is in the terrain texture.
+    pub fn terrain_uv(&self) -> (f32, f32) {
+        match self {
+            Biome::Beach => (3.0 / 9.0, 0.0),
+            Biome::Grassland => (0.0, 0.0),
+            Biome::Forest => (4.5 / 8.0, 2.5 / 8.0),
+            Biome::Swamp => (6.5 / 8.0, 2.5 / 8.0),
+            Biome::RockyHighland => (5.0 / 9.0, 0.0),
+            Biome::Tundra => (0.5 / 8.0, 4.5 / 8.0),
+        }
+    }
+
+    /// Chance that a spot picked for a tree gets one.
+    pub fn tree_density(&self) -> f32 {
+        match self {
+            Biome::Beach => 0.0,
+            Biome::Grassland => 0.15,
+            Biome::Forest => 0.8,
+            Biome::Swamp => 0.3,
+            Biome::RockyHighland => 0.05,
+            Biome::Tundra => 0.1,
+        }
+    }
+
+    /// Chance that a spot picked for a berry bush gets one.
+    pub fn bush_density(&self) -> f32 {
+        match self {
+            Biome::Beach => 0.0,
+            Biome::Grassland => 0.4,
+            Biome::Forest => 0.5,
+            Biome::Swamp => 0.2,
+            Biome::RockyHighland => 0.1,
+            Biome::Tundra => 0.1,
+        }
+    }
+
+    /// Chance that a spot picked for a rock gets one.
+    pub fn rock_density(&self) -> f32 {
+        match self {
+            Biome::Beach => 0.3,
+            Biome::Grassland => 0.4,
+            Biome::Forest => 0.3,
+            Biome::Swamp => 0.1,
+            Biome::RockyHighland => 0.9,
+            Biome::Tundra => 0.6,
+        }
+    }
+
+    /// Chance that a spot picked for clay gets some. Clay is only found in the wet mud of beaches and swamps.
+    pub fn clay_density(&self) -> f32 {
+        match self {
+            Biome::Beach => 1.0,
+            Biome::Swamp => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    /// The crop that grows wild in the biome, if any does.
+    pub fn wild_crop(&self) -> Option<CropKind> {
+        match self {
+            Biome::Grassland => Some(CropKind::Wheat),
+            Biome::Forest | Biome::Swamp => Some(CropKind::Potato),
+            _ => None,
+        }
+    }
+
+    /// The animal that lives in the biome, and the chance that a chunk of it has one, if any does.
+    pub fn animal(&self) -> Option<(AnimalKind, f64)> {
+        match self {
+            Biome::Grassland => Some((AnimalKind::Rabbit, 0.3)),
+            Biome::Forest => Some((AnimalKind::Deer, 0.4)),
+            Biome::Swamp => Some((AnimalKind::Deer, 0.1)),
+            Biome::RockyHighland => Some((AnimalKind::Rabbit, 0.1)),
+            Biome::Tundra => Some((AnimalKind::Rabbit, 0.15)),
+            Biome::Beach => None,
+        }
+    }
+
+    /// Sound that plays every now and then while the player is in the biome.
+    pub fn ambient_sound(&self) -> &'static str {
+        match self {
+            Biome::Beach => "res/ambient-waves.wav",
+            Biome::Grassland => "res/ambient-crickets.wav",
+            Biome::Forest => "res/ambient-birds.wav",
+            Biome::Swamp => "res/ambient-frogs.wav",
+            Biome::RockyHighland | Biome::Tundra => "res/ambient-wind.wav",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little past a threshold.
+    const NUDGE: f32 = 0.001;
+    /// Warm enough that nothing below the highlands is tundra.
+    const WARM: f32 = 1.0;
+    const DRY: f32 = 0.0;
+    const WET: f32 = 1.0;
+
+    #[test]
+    fn beaches_are_below_the_beach_height_whatever_the_weather() {
+        assert_eq!(
+            Biome::classify(BEACH_HEIGHT - NUDGE, DRY, WARM),
+            Biome::Beach
+        );
+        assert_eq!(
+            Biome::classify(BEACH_HEIGHT - NUDGE, WET, 0.0),
+            Biome::Beach
+        );
+        assert_eq!(Biome::classify(BEACH_HEIGHT, DRY, WARM), Biome::Grassland);
+        assert_eq!(
+            Biome::classify(BEACH_HEIGHT + NUDGE, DRY, WARM),
+            Biome::Grassland
+        );
+    }
+
+    #[test]
+    fn tundra_is_cold_and_gets_colder_higher_up() {
+        let height = 1.0;
+        let coldest_grassland = TUNDRA_TEMPERATURE + height * LAPSE_RATE;
+        assert_eq!(
+            Biome::classify(height, DRY, coldest_grassland - NUDGE),
+            Biome::Tundra
+        );
+        assert_eq!(
+            Biome::classify(height, DRY, coldest_grassland + NUDGE),
+            Biome::Grassland
+        );
+        // The same temperature is tundra further up, even before the highlands
+        assert_eq!(
+            Biome::classify(2.0, DRY, coldest_grassland + NUDGE),
+            Biome::Tundra
+        );
+        // And it beats swamps and forests
+        assert_eq!(
+            Biome::classify(height, WET, coldest_grassland - NUDGE),
+            Biome::Tundra
+        );
+    }
+
+    #[test]
+    fn highlands_are_above_the_highland_height() {
+        assert_eq!(
+            Biome::classify(HIGHLAND_HEIGHT - NUDGE, DRY, WARM),
+            Biome::Grassland
+        );
+        assert_eq!(
+            Biome::classify(HIGHLAND_HEIGHT, DRY, WARM),
+            Biome::Grassland
+        );
+        assert_eq!(
+            Biome::classify(HIGHLAND_HEIGHT + NUDGE, DRY, WARM),
+            Biome::RockyHighland
+        );
+        assert_eq!(
+            Biome::classify(HIGHLAND_HEIGHT + NUDGE, WET, WARM),
+            Biome::RockyHighland
+        );
+    }
+
+    #[test]
+    fn wet_lowlands_are_swamps_and_wet_uplands_are_forests() {
+        let lowland = 1.0;
+        assert_eq!(
+            Biome::classify(lowland, FOREST_HYDRATION, WARM),
+            Biome::Grassland
+        );
+        assert_eq!(
+            Biome::classify(lowland, FOREST_HYDRATION + NUDGE, WARM),
+            Biome::Forest
+        );
+        assert_eq!(
+            Biome::classify(lowland, SWAMP_HYDRATION, WARM),
+            Biome::Forest
+        );
+        assert_eq!(
+            Biome::classify(lowland, SWAMP_HYDRATION + NUDGE, WARM),
+            Biome::Swamp
+        );
+
+        assert_eq!(
+            Biome::classify(SWAMP_HEIGHT - NUDGE, WET, WARM),
+            Biome::Swamp
+        );
+        assert_eq!(Biome::classify(SWAMP_HEIGHT, WET, WARM), Biome::Forest);
+        assert_eq!(
+            Biome::classify(SWAMP_HEIGHT + NUDGE, WET, WARM),
+            Biome::Forest
+        );
+    }
+}
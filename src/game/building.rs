@@ -0,0 +1,183 @@
+use hecs::{Entity, World};
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    render_core::{ModelComponent, RenderContext},
+};
+
+use super::{
+    fire::{fire_light, Fire},
+    items::ItemKind,
+};
+
+/// Every kind of thing that can be built from a blueprint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StructureKind {
+    Firepit,
+    LeanTo,
+}
+
+/// A structure that has been placed, but still needs materials before it is built.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blueprint {
+    pub kind: StructureKind,
+    pub remaining: Vec<(ItemKind, usize)>, //< Materials that still need to be delivered, and how many of each
+}
+
+/// A finished structure.
+pub struct Structure {
+    pub kind: StructureKind,
+}
+
+/// Blueprints are see-through, until they're built.
+pub const BLUEPRINT_TINT: nalgebra_glm::Vec4 = nalgebra_glm::Vec4::new(0.6, 0.8, 1.0, 0.6);
+
+impl StructureKind {
+    pub const ALL: [StructureKind; 2] = [StructureKind::Firepit, StructureKind::LeanTo];
+
+    /// Identifier used to refer to the structure in data files, like save files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            StructureKind::Firepit => "firepit",
+            StructureKind::LeanTo => "lean-to",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<StructureKind> {
+        StructureKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.id() == id)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructureKind::Firepit => "Firepit",
+            StructureKind::LeanTo => "Thatch Lean-to",
+        }
+    }
+
+    pub fn mesh_name(&self) -> &'static str {
+        match self {
+            StructureKind::Firepit => "cube",
+            StructureKind::LeanTo => "tree",
+        }
+    }
+
+    pub fn texture_name(&self) -> &'static str {
+        match self {
+            StructureKind::Firepit => "firepit",
+            StructureKind::LeanTo => "thatch",
+        }
+    }
+
+    pub fn scale(&self) -> nalgebra_glm::Vec3 {
+        match self {
+            StructureKind::Firepit => nalgebra_glm::vec3(0.6, 0.6, 0.1),
+            StructureKind::LeanTo => nalgebra_glm::vec3(1.6, 1.6, 1.2),
+        }
+    }
+
+    /// What needs to be delivered to a blueprint of the structure for it to be built.
+    pub fn materials(&self) -> &'static [(ItemKind, usize)] {
+        match self {
+            StructureKind::Firepit => &[(ItemKind::Stone, 4), (ItemKind::Stick, 3)],
+            StructureKind::LeanTo => &[(ItemKind::Stick, 6), (ItemKind::Wheat, 4)],
+        }
+    }
+}
+
+impl Blueprint {
+    pub fn new(kind: StructureKind) -> Self {
+        Self {
+            kind,
+            remaining: kind.materials().to_vec(),
+        }
+    }
+
+    pub fn needs(&self, item: ItemKind) -> bool {
+        self.remaining.iter().any(|(kind, _)| *kind == item)
+    }
+
+    /// Puts an item towards the blueprint. Returns false if the blueprint doesn't need it.
+    pub fn deliver(&mut self, item: ItemKind) -> bool {
+        let Some(index) = self.remaining.iter().position(|(kind, _)| *kind == item) else {
+            return false;
+        };
+        self.remaining[index].1 -= 1;
+        if self.remaining[index].1 == 0 {
+            self.remaining.remove(index);
+        }
+        true
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Lists the remaining materials, like "4 Stone, 3 Stick".
+    pub fn describe_remaining(&self) -> String {
+        describe_materials(&self.remaining)
+    }
+}
+
+/// Lists some materials, like "4 Stone, 3 Stick".
+pub fn describe_materials(materials: &[(ItemKind, usize)]) -> String {
+    materials
+        .iter()
+        .map(|(item, count)| format!("{} {}", count, item.name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Spawns the model of a structure into the world at a position, along with its BVH leaf. Whether it's a blueprint
+/// or a finished structure is up to the caller.
+pub fn spawn_structure(
+    kind: StructureKind,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name(kind.mesh_name()).unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(kind.texture_name())
+        .unwrap();
+    let model = ModelComponent::new(mesh, texture, position, kind.scale());
+    let aabb = renderer.get_model_aabb(&model);
+    let entity = world.spawn((model,));
+    let node_id = bvh.insert(entity, aabb);
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Spawns a blueprint into the world at a position, along with its BVH leaf.
+pub fn spawn_blueprint(
+    blueprint: Blueprint,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let entity = spawn_structure(blueprint.kind, position, renderer, world, bvh);
+    world.get::<&mut ModelComponent>(entity).unwrap().tint = BLUEPRINT_TINT;
+    world.insert_one(entity, blueprint).unwrap();
+    entity
+}
+
+/// Turns a structure's model into a finished structure. Firepits get a fire, which has to be fed before it burns.
+pub fn complete_structure(entity: Entity, kind: StructureKind, world: &mut World) {
+    world.insert_one(entity, Structure { kind }).unwrap();
+    let position = {
+        let mut model = world.get::<&mut ModelComponent>(entity).unwrap();
+        model.tint = nalgebra_glm::vec4(1.0, 1.0, 1.0, 1.0);
+        model.get_position()
+    };
+    if kind == StructureKind::Firepit {
+        world
+            .insert(entity, (Fire::new(), fire_light(position)))
+            .unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use hecs::{Entity, World};
+
+use crate::engine::render_core::{ModelComponent, RenderContext};
+
+use super::{
+    fire::Fire,
+    items::{ItemKind, Pickable},
+};
+
+/// How done a piece of food on a fire is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CookState {
+    Raw,
+    Cooked,
+    Burnt,
+}
+
+/// Put on a food item that's sitting on a fire. It only cooks while the fire is burning, and burns if it's left on
+/// for too long.
+pub struct Cooking {
+    pub fire: Entity,
+    pub slot: usize, //< Which spot on the fire the item is in, see `COOKING_SLOTS`
+    pub state: CookState,
+    pub progress: f32, //< In-game days spent cooking in the current state
+}
+
+/// Where items sit on a fire, relative to the fire's position. A fire can only cook this many items at once.
+pub const COOKING_SLOTS: [nalgebra_glm::Vec3; 4] = [
+    nalgebra_glm::Vec3::new(-0.15, -0.15, 0.1),
+    nalgebra_glm::Vec3::new(0.15, -0.15, 0.1),
+    nalgebra_glm::Vec3::new(-0.15, 0.15, 0.1),
+    nalgebra_glm::Vec3::new(0.15, 0.15, 0.1),
+];
+const BURN_DAYS: f32 = 0.03; //< In-game days cooked food can sit on a fire before it burns
+
+impl Cooking {
+    pub fn new(fire: Entity, slot: usize) -> Self {
+        Self {
+            fire,
+            slot,
+            state: CookState::Raw,
+            progress: 0.0,
+        }
+    }
+}
+
+/// The first free spot on a fire to cook an item in, if there is one.
+pub fn free_cooking_slot(world: &World, fire: Entity) -> Option<usize> {
+    let mut taken = [false; COOKING_SLOTS.len()];
+    for (_, cooking) in world.query::<&Cooking>().iter() {
+        if cooking.fire == fire {
+            taken[cooking.slot] = true;
+        }
+    }
+    taken.iter().position(|taken| !taken)
+}
+
+/// Cooks all of the items on burning fires. Raw food turns into cooked food, and cooked food turns into burnt food.
+pub fn cooking_system(world: &mut World, renderer: &RenderContext, days: f32) {
+    let burning: Vec<Entity> = world
+        .query::<&Fire>()
+        .iter()
+        .filter(|(_, fire)| fire.is_burning())
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for (_, (cooking, pickable, model)) in
+        world.query_mut::<(&mut Cooking, &mut Pickable, &mut ModelComponent)>()
+    {
+        if !burning.contains(&cooking.fire) {
+            continue;
+        }
+        cooking.progress += days;
+        let next = match cooking.state {
+            CookState::Raw if cooking.progress >= pickable.item_kind.cook_days() => pickable
+                .item_kind
+                .cooked()
+                .map(|cooked| (CookState::Cooked, cooked)),
+            CookState::Cooked if cooking.progress >= BURN_DAYS => {
+                Some((CookState::Burnt, ItemKind::BurntFood))
+            }
+            _ => None,
+        };
+        let Some((state, kind)) = next else {
+            continue;
+        };
+        cooking.state = state;
+        cooking.progress = 0.0;
+        pickable.item_kind = kind;
+        pickable.label = kind.name();
+        model.texture_id = renderer
+            .get_texture_id_from_name(kind.texture_name())
+            .unwrap();
+        model.set_scale(kind.scale());
+    }
+}
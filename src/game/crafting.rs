@@ -0,0 +1,212 @@
+use super::items::ItemKind;
+
+/// Crafting that needs more input from the player than just the two items, so it has its own menu.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CraftingMenu {
+    Knapping,
+    Molding,
+}
+
+/// What happens when a recipe is crafted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecipeOutput {
+    /// What ends up in the hand that held the first ingredient, and in the hand that held the second.
+    Items(Option<ItemKind>, Option<ItemKind>),
+    Menu(CraftingMenu),
+}
+
+/// One line of the recipe file. An ingredient of None matches any item.
+struct Recipe {
+    first: Option<ItemKind>,
+    second: Option<ItemKind>,
+    output: RecipeOutput,
+}
+
+/// Every way two held items can be crafted together. Loaded from `res/recipes.txt`.
+pub struct Recipes {
+    recipes: Vec<Recipe>,
+}
+
+impl CraftingMenu {
+    fn from_id(id: &str) -> Option<CraftingMenu> {
+        match id {
+            "knapping" => Some(CraftingMenu::Knapping),
+            "molding" => Some(CraftingMenu::Molding),
+            _ => None,
+        }
+    }
+}
+
+impl Recipes {
+    pub fn from_file(filename: &str) -> Self {
+        let text = std::fs::read_to_string(filename)
+            .unwrap_or_else(|err| panic!("couldn't read {}: {}", filename, err));
+        Self::parse(&text).unwrap_or_else(|err| panic!("{}: {}", filename, err))
+    }
+
+    /// Parses the recipe file format. Returns an error message with the line number of the first bad line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut recipes = vec![];
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let recipe =
+                parse_recipe(line).map_err(|err| format!("line {}: {}", line_number + 1, err))?;
+            recipes.push(recipe);
+        }
+        Ok(Self { recipes })
+    }
+
+    /// Finds what crafting two items together makes. The ingredients can be in either order, and the output items
+    /// are given in the same order as the arguments.
+    pub fn find(&self, a: ItemKind, b: ItemKind) -> Option<RecipeOutput> {
+        let matches = |pattern: Option<ItemKind>, item: ItemKind| pattern.is_none_or(|p| p == item);
+        for recipe in &self.recipes {
+            if matches(recipe.first, a) && matches(recipe.second, b) {
+                return Some(recipe.output);
+            } else if matches(recipe.first, b) && matches(recipe.second, a) {
+                return Some(match recipe.output {
+                    RecipeOutput::Items(first, second) => RecipeOutput::Items(second, first),
+                    menu => menu,
+                });
+            }
+        }
+        None
+    }
+}
+
+fn parse_recipe(line: &str) -> Result<Recipe, String> {
+    let (ingredients, output) = line
+        .split_once("=>")
+        .ok_or_else(|| String::from("expected `=>`"))?;
+    let (first, second) = ingredients
+        .split_once('+')
+        .ok_or_else(|| String::from("expected two ingredients separated by `+`"))?;
+    let first = parse_ingredient(first.trim())?;
+    let second = parse_ingredient(second.trim())?;
+
+    let output = output.trim();
+    let output = if let Some(menu) = output.strip_prefix("menu ") {
+        let menu = menu.trim();
+        RecipeOutput::Menu(
+            CraftingMenu::from_id(menu).ok_or_else(|| format!("unknown menu `{}`", menu))?,
+        )
+    } else if let Some((first, second)) = output.split_once('+') {
+        RecipeOutput::Items(parse_output(first.trim())?, parse_output(second.trim())?)
+    } else {
+        RecipeOutput::Items(parse_output(output)?, None)
+    };
+
+    Ok(Recipe {
+        first,
+        second,
+        output,
+    })
+}
+
+fn parse_ingredient(id: &str) -> Result<Option<ItemKind>, String> {
+    if id == "*" {
+        Ok(None)
+    } else {
+        parse_item(id).map(Some)
+    }
+}
+
+fn parse_output(id: &str) -> Result<Option<ItemKind>, String> {
+    if id == "-" {
+        Ok(None)
+    } else {
+        parse_item(id).map(Some)
+    }
+}
+
+fn parse_item(id: &str) -> Result<ItemKind, String> {
+    ItemKind::from_id(id).ok_or_else(|| format!("unknown item `{}`", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipe_file_parses() {
+        let recipes = Recipes::parse(include_str!("../../res/recipes.txt")).unwrap();
+        assert_eq!(
+            recipes.find(ItemKind::Stone, ItemKind::Stone),
+            Some(RecipeOutput::Menu(CraftingMenu::Knapping))
+        );
+        assert_eq!(
+            recipes.find(ItemKind::Log, ItemKind::Clay),
+            Some(RecipeOutput::Menu(CraftingMenu::Molding))
+        );
+        assert_eq!(
+            recipes.find(ItemKind::AxeHead, ItemKind::Stick),
+            Some(RecipeOutput::Items(Some(ItemKind::StoneAxe), None))
+        );
+    }
+
+    #[test]
+    fn ingredients_match_in_either_order() {
+        let recipes = Recipes::parse("hide + stick => stick + hide").unwrap();
+        assert_eq!(
+            recipes.find(ItemKind::Hide, ItemKind::Stick),
+            Some(RecipeOutput::Items(
+                Some(ItemKind::Stick),
+                Some(ItemKind::Hide)
+            ))
+        );
+        // The outputs follow the order the items were given in
+        assert_eq!(
+            recipes.find(ItemKind::Stick, ItemKind::Hide),
+            Some(RecipeOutput::Items(
+                Some(ItemKind::Hide),
+                Some(ItemKind::Stick)
+            ))
+        );
+        assert_eq!(recipes.find(ItemKind::Hide, ItemKind::Hide), None);
+    }
+
+    #[test]
+    fn wildcards_match_any_item_and_the_first_recipe_wins() {
+        let recipes = Recipes::parse(
+            "# Comments and blank lines are skipped
+
+            stone + stick => stone-hammer + -
+            stone + * => - + -",
+        )
+        .unwrap();
+        assert_eq!(
+            recipes.find(ItemKind::Stick, ItemKind::Stone),
+            Some(RecipeOutput::Items(None, Some(ItemKind::StoneHammer)))
+        );
+        assert_eq!(
+            recipes.find(ItemKind::Log, ItemKind::Stone),
+            Some(RecipeOutput::Items(None, None))
+        );
+        assert_eq!(recipes.find(ItemKind::Log, ItemKind::Stick), None);
+    }
+
+    #[test]
+    fn bad_lines_are_reported_with_their_line_number() {
+        let error = |text: &str| Recipes::parse(text).err().unwrap();
+        assert_eq!(error("stone + stone"), "line 1: expected `=>`");
+        assert_eq!(
+            error("# Recipes\nstone => stick"),
+            "line 2: expected two ingredients separated by `+`"
+        );
+        assert_eq!(
+            error("\n\nrock + stone => stick"),
+            "line 3: unknown item `rock`"
+        );
+        assert_eq!(
+            error("stone + stone => stick + rock"),
+            "line 1: unknown item `rock`"
+        );
+        assert_eq!(
+            error("clay + * => menu smelting"),
+            "line 1: unknown menu `smelting`"
+        );
+    }
+}
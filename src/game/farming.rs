@@ -0,0 +1,206 @@
+use hecs::{Entity, World};
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    chunked_map::ChunkedPerlinMap,
+    perlin::HeightMap,
+    render_core::{ModelComponent, RenderContext},
+};
+
+use super::items::ItemKind;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CropKind {
+    Potato,
+    Wheat,
+}
+
+/// A plant that grows through stages over in-game days, and can be harvested once mature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crop {
+    pub kind: CropKind,
+    pub stage: usize,
+    pub growth: f32,              //< In-game days spent in the current stage
+    pub cell: nalgebra_glm::Vec2, //< World-space origin of the terrain cell the crop is in
+}
+
+const WATERED_GROWTH_MULTIPLIER: f32 = 2.0;
+
+impl CropKind {
+    pub const ALL: [CropKind; 2] = [CropKind::Potato, CropKind::Wheat];
+
+    /// Identifier used to refer to the crop in data files, like save files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            CropKind::Potato => "potato",
+            CropKind::Wheat => "wheat",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<CropKind> {
+        CropKind::ALL.iter().copied().find(|kind| kind.id() == id)
+    }
+
+    /// The crop that grows when an item is planted in tilled soil, if the item can be planted at all.
+    pub fn from_seed(item: ItemKind) -> Option<CropKind> {
+        match item {
+            ItemKind::Potato => Some(CropKind::Potato),
+            ItemKind::Wheat => Some(CropKind::Wheat),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CropKind::Potato => "Potatoes",
+            CropKind::Wheat => "Wheat",
+        }
+    }
+
+    /// How many stages the crop grows through. The last stage is mature.
+    pub fn stages(&self) -> usize {
+        match self {
+            CropKind::Potato => 4,
+            CropKind::Wheat => 4,
+        }
+    }
+
+    /// In-game days spent in each stage, if the crop is never watered.
+    pub fn days_per_stage(&self) -> f32 {
+        match self {
+            CropKind::Potato => 0.5,
+            CropKind::Wheat => 0.4,
+        }
+    }
+
+    /// What item is harvested from a mature crop, and how many of them.
+    pub fn harvest(&self) -> (ItemKind, usize) {
+        match self {
+            CropKind::Potato => (ItemKind::Potato, 2),
+            CropKind::Wheat => (ItemKind::Wheat, 2),
+        }
+    }
+
+    pub fn mesh_name(&self) -> &'static str {
+        "bush"
+    }
+
+    pub fn texture_name(&self) -> &'static str {
+        match self {
+            CropKind::Potato => "tree",
+            CropKind::Wheat => "wheat",
+        }
+    }
+
+    /// Size of the crop's model at a growth stage.
+    pub fn scale(&self, stage: usize) -> nalgebra_glm::Vec3 {
+        let t = (stage + 1) as f32 / self.stages() as f32;
+        match self {
+            CropKind::Potato => nalgebra_glm::vec3(0.4, 0.4, 0.3) * t,
+            CropKind::Wheat => nalgebra_glm::vec3(0.3, 0.3, 0.6) * t,
+        }
+    }
+}
+
+impl Crop {
+    pub fn new(kind: CropKind, stage: usize, cell: nalgebra_glm::Vec2) -> Self {
+        Self {
+            kind,
+            stage: stage.min(kind.stages() - 1),
+            growth: 0.0,
+            cell,
+        }
+    }
+
+    pub fn is_mature(&self) -> bool {
+        self.stage + 1 >= self.kind.stages()
+    }
+
+    /// Grows the crop by some in-game days. Returns true if it advanced to the next stage.
+    pub fn tick(&mut self, days: f32) -> bool {
+        if self.is_mature() {
+            return false;
+        }
+        self.growth += days;
+        if self.growth < self.kind.days_per_stage() {
+            return false;
+        }
+        self.growth -= self.kind.days_per_stage();
+        self.stage += 1;
+        true
+    }
+}
+
+/// Spawns a crop in the middle of a terrain cell, along with its BVH leaf. The BVH leaf is sized for the mature crop.
+pub fn spawn_crop(
+    crop: Crop,
+    map: &ChunkedPerlinMap,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let center = crop.cell + nalgebra_glm::vec2(0.5, 0.5);
+    let position = nalgebra_glm::vec3(center.x, center.y, map.height_interpolated(center));
+    spawn_crop_at(crop, position, renderer, world, bvh)
+}
+
+/// Spawns a crop at a position that's already known, along with its BVH leaf.
+pub fn spawn_crop_at(
+    crop: Crop,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer
+        .get_mesh_id_from_name(crop.kind.mesh_name())
+        .unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(crop.kind.texture_name())
+        .unwrap();
+    let mature_scale = crop.kind.scale(crop.kind.stages() - 1);
+    let model = ModelComponent::new(mesh, texture, position, crop.kind.scale(crop.stage));
+    let entity = world.spawn((model, crop));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(mesh)
+            .scale(mature_scale)
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Grows all of the crops. Crops in watered soil grow faster, but use up the water when they reach the next stage.
+pub fn crop_system(
+    world: &mut World,
+    map: &mut ChunkedPerlinMap,
+    renderer: &RenderContext,
+    days: f32,
+) {
+    let mut dried_cells = vec![];
+    for (_, (crop, model)) in world.query_mut::<(&mut Crop, &mut ModelComponent)>() {
+        let watered = map.soil(crop.cell).is_some_and(|soil| soil.watered);
+        let multiplier = if watered {
+            WATERED_GROWTH_MULTIPLIER
+        } else {
+            1.0
+        };
+        if crop.tick(days * multiplier) {
+            model.set_scale(crop.kind.scale(crop.stage));
+            if watered {
+                dried_cells.push(crop.cell);
+            }
+        }
+    }
+
+    for cell in dried_cells {
+        if let Some(mut soil) = map.soil(cell) {
+            soil.watered = false;
+            map.set_soil(cell, soil, renderer, world);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use hecs::World;
+
+use crate::engine::point_light::PointLight;
+
+use super::items::ItemKind;
+
+/// A fire that burns fuel items. While it burns, it lights up and warms up everything around it.
+pub struct Fire {
+    pub fuel: f32, //< In-game days the fire will keep burning for
+}
+
+pub const WARMTH_RADIUS: f32 = 3.0; //< How close the player has to be to a fire to be warmed by it
+const MAX_FUEL: f32 = 1.0;
+const LIGHT_RADIUS: f32 = 6.0;
+const LIGHT_HEIGHT: f32 = 0.5; //< How far above the fire's base the light is
+const LIGHT_COLOR: nalgebra_glm::Vec3 = nalgebra_glm::Vec3::new(1.0, 0.55, 0.2);
+
+impl Fire {
+    pub fn new() -> Self {
+        Self { fuel: 0.0 }
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.fuel > 0.0
+    }
+
+    /// Whether an item can be thrown on the fire. Fires can only hold so much fuel at once.
+    pub fn can_add(&self, item: ItemKind) -> bool {
+        item.fuel_value().is_some() && self.fuel < MAX_FUEL
+    }
+
+    /// Throws an item on the fire. Returns false if it can't be burned.
+    pub fn add_fuel(&mut self, item: ItemKind) -> bool {
+        if !self.can_add(item) {
+            return false;
+        }
+        self.fuel = (self.fuel + item.fuel_value().unwrap()).min(MAX_FUEL);
+        true
+    }
+
+    /// Burns fuel for some in-game days.
+    pub fn burn(&mut self, days: f32) {
+        self.fuel = (self.fuel - days).max(0.0);
+    }
+
+    /// How much the fire warms something some distance away from it, in [0, 1].
+    pub fn warmth_at(&self, distance: f32) -> f32 {
+        if !self.is_burning() {
+            return 0.0;
+        }
+        (1.0 - distance / WARMTH_RADIUS).clamp(0.0, 1.0)
+    }
+}
+
+/// The light a fire at a position gives off. It starts out dark, until the fire is fed.
+pub fn fire_light(position: nalgebra_glm::Vec3) -> PointLight {
+    PointLight::new(
+        position + nalgebra_glm::vec3(0.0, 0.0, LIGHT_HEIGHT),
+        LIGHT_COLOR,
+        0.0,
+    )
+}
+
+/// Burns the fuel of all of the fires, and makes their lights flicker. Fires that run out of fuel go dark.
+pub fn fire_system(world: &mut World, days: f32, ticks: usize) {
+    for (entity, (fire, light)) in world.query_mut::<(&mut Fire, &mut PointLight)>() {
+        fire.burn(days);
+        if !fire.is_burning() {
+            light.radius = 0.0;
+            continue;
+        }
+        // Each fire flickers a little differently
+        let t = ticks as f32 + entity.id() as f32 * 37.0;
+        let flicker = 1.0 + 0.06 * (t * 0.31).sin() + 0.04 * (t * 0.77 + 1.3).sin();
+        light.radius = LIGHT_RADIUS * flicker;
+    }
+}
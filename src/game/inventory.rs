@@ -0,0 +1,110 @@
+use super::{
+    items::{Item, ItemKind},
+    tools::Tool,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// The two hand inventory of the player. The dominant hand is the one that grabs, uses and drops items.
+#[derive(Clone, Debug)]
+pub struct Hands {
+    pub left: Option<Item>,
+    pub right: Option<Item>,
+    pub dominant: Hand,
+}
+
+impl Hand {
+    /// Identifier used to refer to the hand in data files, like save files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Hand::Left => "left",
+            Hand::Right => "right",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Hand> {
+        [Hand::Left, Hand::Right]
+            .into_iter()
+            .find(|hand| hand.id() == id)
+    }
+
+    pub fn other(&self) -> Hand {
+        match self {
+            Hand::Left => Hand::Right,
+            Hand::Right => Hand::Left,
+        }
+    }
+}
+
+impl Hands {
+    pub fn new() -> Self {
+        Self {
+            left: None,
+            right: None,
+            dominant: Hand::Right,
+        }
+    }
+
+    /// What kind of item is in a hand.
+    pub fn get(&self, hand: Hand) -> Option<ItemKind> {
+        self.slot(hand).map(|item| item.kind)
+    }
+
+    pub fn slot(&self, hand: Hand) -> Option<Item> {
+        match hand {
+            Hand::Left => self.left,
+            Hand::Right => self.right,
+        }
+    }
+
+    pub fn slot_mut(&mut self, hand: Hand) -> &mut Option<Item> {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+
+    pub fn dominant_item(&self) -> Option<ItemKind> {
+        self.get(self.dominant)
+    }
+
+    pub fn off_item(&self) -> Option<ItemKind> {
+        self.get(self.dominant.other())
+    }
+
+    /// The tool in the dominant hand, if it's holding one.
+    pub fn dominant_tool_mut(&mut self) -> Option<&mut Tool> {
+        self.slot_mut(self.dominant)
+            .as_mut()
+            .and_then(|item| item.tool.as_mut())
+    }
+
+    pub fn swap_dominant(&mut self) {
+        self.dominant = self.dominant.other();
+    }
+
+    /// Removes and returns whatever is in the dominant hand.
+    pub fn take_dominant(&mut self) -> Option<Item> {
+        self.slot_mut(self.dominant).take()
+    }
+
+    /// Tries to put an item in the dominant hand, and then the off hand. Returns false if both hands are full.
+    pub fn give(&mut self, item: Item) -> bool {
+        for hand in [self.dominant, self.dominant.other()] {
+            let slot = self.slot_mut(hand);
+            if slot.is_none() {
+                *slot = Some(item);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.left.is_some() && self.right.is_some()
+    }
+}
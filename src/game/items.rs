@@ -0,0 +1,363 @@
+use hecs::{Entity, World};
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    render_core::{ModelComponent, RenderContext},
+};
+
+use super::tools::{Tool, ToolKind};
+
+/// Every kind of thing that can be held in a hand, or dropped into the world.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Stone,
+    Berry,
+    Potato,
+    Wheat,
+    Meat,
+    CookedMeat,
+    BakedPotato,
+    BurntFood,
+    Hide,
+    Stick,
+    Log,
+    Clay,
+    ClayPot,
+    ClayBowl,
+    AxeHead,
+    KnifeBlade,
+    StoneAxe,
+    StoneKnife,
+    SpearPoint,
+    StoneSpear,
+    HoeBlade,
+    StoneHoe,
+    StoneHammer,
+}
+
+/// A single item, like one held in a hand. Tools keep track of how worn out they are.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub tool: Option<Tool>,
+}
+
+/// Something in the world that can be grabbed by clicking and holding on it, and put into a hand.
+pub struct Pickable {
+    pub item_kind: ItemKind,
+    pub label: &'static str, //< Shown in the "Grab X" action billboard
+}
+
+impl ItemKind {
+    pub const ALL: [ItemKind; 23] = [
+        ItemKind::Stone,
+        ItemKind::Berry,
+        ItemKind::Potato,
+        ItemKind::Wheat,
+        ItemKind::Meat,
+        ItemKind::CookedMeat,
+        ItemKind::BakedPotato,
+        ItemKind::BurntFood,
+        ItemKind::Hide,
+        ItemKind::Stick,
+        ItemKind::Log,
+        ItemKind::Clay,
+        ItemKind::ClayPot,
+        ItemKind::ClayBowl,
+        ItemKind::AxeHead,
+        ItemKind::KnifeBlade,
+        ItemKind::StoneAxe,
+        ItemKind::StoneKnife,
+        ItemKind::SpearPoint,
+        ItemKind::StoneSpear,
+        ItemKind::HoeBlade,
+        ItemKind::StoneHoe,
+        ItemKind::StoneHammer,
+    ];
+
+    /// Identifier used to refer to the item in data files, like the recipe list.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ItemKind::Stone => "stone",
+            ItemKind::Berry => "berry",
+            ItemKind::Potato => "potato",
+            ItemKind::Wheat => "wheat",
+            ItemKind::Meat => "meat",
+            ItemKind::CookedMeat => "cooked-meat",
+            ItemKind::BakedPotato => "baked-potato",
+            ItemKind::BurntFood => "burnt-food",
+            ItemKind::Hide => "hide",
+            ItemKind::Stick => "stick",
+            ItemKind::Log => "log",
+            ItemKind::Clay => "clay",
+            ItemKind::ClayPot => "clay-pot",
+            ItemKind::ClayBowl => "clay-bowl",
+            ItemKind::AxeHead => "axe-head",
+            ItemKind::KnifeBlade => "knife-blade",
+            ItemKind::StoneAxe => "stone-axe",
+            ItemKind::StoneKnife => "stone-knife",
+            ItemKind::SpearPoint => "spear-point",
+            ItemKind::StoneSpear => "stone-spear",
+            ItemKind::HoeBlade => "hoe-blade",
+            ItemKind::StoneHoe => "stone-hoe",
+            ItemKind::StoneHammer => "stone-hammer",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<ItemKind> {
+        ItemKind::ALL.iter().copied().find(|kind| kind.id() == id)
+    }
+
+    /// Human readable name, used in the UI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Stone => "Stone",
+            ItemKind::Berry => "Berry",
+            ItemKind::Potato => "Potato",
+            ItemKind::Wheat => "Wheat",
+            ItemKind::Meat => "Raw Meat",
+            ItemKind::CookedMeat => "Cooked Meat",
+            ItemKind::BakedPotato => "Baked Potato",
+            ItemKind::BurntFood => "Burnt Food",
+            ItemKind::Hide => "Hide",
+            ItemKind::Stick => "Stick",
+            ItemKind::Log => "Log",
+            ItemKind::Clay => "Clay",
+            ItemKind::ClayPot => "Clay Pot",
+            ItemKind::ClayBowl => "Clay Bowl",
+            ItemKind::AxeHead => "Axe Head",
+            ItemKind::KnifeBlade => "Knife Blade",
+            ItemKind::StoneAxe => "Stone Axe",
+            ItemKind::StoneKnife => "Stone Knife",
+            ItemKind::SpearPoint => "Spear Point",
+            ItemKind::StoneSpear => "Stone Spear",
+            ItemKind::HoeBlade => "Hoe Blade",
+            ItemKind::StoneHoe => "Stone Hoe",
+            ItemKind::StoneHammer => "Stone Hammer",
+        }
+    }
+
+    /// Name of the mesh used when the item is in the world.
+    pub fn mesh_name(&self) -> &'static str {
+        match self {
+            ItemKind::Stone => "cube",
+            ItemKind::Berry => "cube",
+            ItemKind::Potato => "cube",
+            ItemKind::Wheat => "cube",
+            ItemKind::Meat => "cube",
+            ItemKind::CookedMeat => "cube",
+            ItemKind::BakedPotato => "cube",
+            ItemKind::BurntFood => "cube",
+            ItemKind::Hide => "cube",
+            ItemKind::Stick => "cube",
+            ItemKind::Log => "cube",
+            ItemKind::Clay => "cube",
+            ItemKind::ClayPot => "cube",
+            ItemKind::ClayBowl => "cube",
+            ItemKind::AxeHead => "cube",
+            ItemKind::KnifeBlade => "cube",
+            ItemKind::StoneAxe => "cube",
+            ItemKind::StoneKnife => "cube",
+            ItemKind::SpearPoint => "cube",
+            ItemKind::StoneSpear => "cube",
+            ItemKind::HoeBlade => "cube",
+            ItemKind::StoneHoe => "cube",
+            ItemKind::StoneHammer => "cube",
+        }
+    }
+
+    /// Name of the texture used when the item is in the world.
+    pub fn texture_name(&self) -> &'static str {
+        match self {
+            ItemKind::Stone => "rock",
+            ItemKind::Berry => "berry",
+            ItemKind::Potato => "potato",
+            ItemKind::Wheat => "wheat",
+            ItemKind::Meat => "meat",
+            ItemKind::CookedMeat => "cooked-meat",
+            ItemKind::BakedPotato => "baked-potato",
+            ItemKind::BurntFood => "burnt-food",
+            ItemKind::Hide => "hide",
+            ItemKind::Stick => "stick",
+            ItemKind::Log => "log",
+            ItemKind::Clay => "clay",
+            ItemKind::ClayPot => "clay",
+            ItemKind::ClayBowl => "clay",
+            ItemKind::AxeHead => "tool-blank",
+            ItemKind::KnifeBlade => "tool-blank",
+            ItemKind::StoneAxe => "stone-tool",
+            ItemKind::StoneKnife => "stone-tool",
+            ItemKind::SpearPoint => "tool-blank",
+            ItemKind::StoneSpear => "stone-tool",
+            ItemKind::HoeBlade => "tool-blank",
+            ItemKind::StoneHoe => "stone-tool",
+            ItemKind::StoneHammer => "stone-tool",
+        }
+    }
+
+    /// Name of the texture used for the small icon in the hand inventory slots.
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            ItemKind::Stone => "rock",
+            ItemKind::Berry => "berry",
+            ItemKind::Potato => "potato",
+            ItemKind::Wheat => "wheat",
+            ItemKind::Meat => "meat",
+            ItemKind::CookedMeat => "cooked-meat",
+            ItemKind::BakedPotato => "baked-potato",
+            ItemKind::BurntFood => "burnt-food",
+            ItemKind::Hide => "hide",
+            ItemKind::Stick => "stick",
+            ItemKind::Log => "log",
+            ItemKind::Clay => "clay",
+            ItemKind::ClayPot => "clay",
+            ItemKind::ClayBowl => "clay",
+            ItemKind::AxeHead => "tool-blank",
+            ItemKind::KnifeBlade => "tool-blank",
+            ItemKind::StoneAxe => "stone-tool",
+            ItemKind::StoneKnife => "stone-tool",
+            ItemKind::SpearPoint => "tool-blank",
+            ItemKind::StoneSpear => "stone-tool",
+            ItemKind::HoeBlade => "tool-blank",
+            ItemKind::StoneHoe => "stone-tool",
+            ItemKind::StoneHammer => "stone-tool",
+        }
+    }
+
+    /// How big the item is when it is in the world.
+    pub fn scale(&self) -> nalgebra_glm::Vec3 {
+        match self {
+            ItemKind::Stone => nalgebra_glm::vec3(0.2, 0.2, 0.2),
+            ItemKind::Berry => nalgebra_glm::vec3(0.06, 0.06, 0.06),
+            ItemKind::Potato => nalgebra_glm::vec3(0.1, 0.08, 0.08),
+            ItemKind::Wheat => nalgebra_glm::vec3(0.04, 0.04, 0.3),
+            ItemKind::Meat => nalgebra_glm::vec3(0.12, 0.08, 0.06),
+            ItemKind::CookedMeat => nalgebra_glm::vec3(0.11, 0.07, 0.05),
+            ItemKind::BakedPotato => nalgebra_glm::vec3(0.1, 0.08, 0.08),
+            ItemKind::BurntFood => nalgebra_glm::vec3(0.09, 0.06, 0.05),
+            ItemKind::Hide => nalgebra_glm::vec3(0.3, 0.25, 0.02),
+            ItemKind::Stick => nalgebra_glm::vec3(0.04, 0.04, 0.4),
+            ItemKind::Log => nalgebra_glm::vec3(0.5, 0.12, 0.12),
+            ItemKind::Clay => nalgebra_glm::vec3(0.15, 0.15, 0.1),
+            ItemKind::ClayPot => nalgebra_glm::vec3(0.2, 0.2, 0.25),
+            ItemKind::ClayBowl => nalgebra_glm::vec3(0.2, 0.2, 0.1),
+            ItemKind::AxeHead => nalgebra_glm::vec3(0.15, 0.1, 0.04),
+            ItemKind::KnifeBlade => nalgebra_glm::vec3(0.04, 0.15, 0.02),
+            ItemKind::StoneAxe => nalgebra_glm::vec3(0.08, 0.08, 0.5),
+            ItemKind::StoneKnife => nalgebra_glm::vec3(0.04, 0.04, 0.3),
+            ItemKind::SpearPoint => nalgebra_glm::vec3(0.1, 0.06, 0.02),
+            ItemKind::StoneSpear => nalgebra_glm::vec3(0.05, 0.05, 0.9),
+            ItemKind::HoeBlade => nalgebra_glm::vec3(0.15, 0.08, 0.02),
+            ItemKind::StoneHoe => nalgebra_glm::vec3(0.06, 0.06, 0.6),
+            ItemKind::StoneHammer => nalgebra_glm::vec3(0.08, 0.08, 0.4),
+        }
+    }
+
+    /// What kind of tool the item is, if it is one.
+    pub fn tool_kind(&self) -> Option<ToolKind> {
+        match self {
+            ItemKind::StoneAxe => Some(ToolKind::Axe),
+            ItemKind::StoneKnife => Some(ToolKind::Knife),
+            ItemKind::StoneSpear => Some(ToolKind::Spear),
+            ItemKind::StoneHoe => Some(ToolKind::Hoe),
+            ItemKind::StoneHammer => Some(ToolKind::Hammer),
+            _ => None,
+        }
+    }
+
+    /// How many in-game days a fire burns for when the item is thrown on it, if it burns at all.
+    pub fn fuel_value(&self) -> Option<f32> {
+        match self {
+            ItemKind::Stick => Some(0.05),
+            ItemKind::Log => Some(0.25),
+            ItemKind::Wheat => Some(0.01),
+            _ => None,
+        }
+    }
+
+    /// How much hunger is restored by eating the item, if it can be eaten at all.
+    pub fn food_value(&self) -> Option<f32> {
+        match self {
+            ItemKind::Berry => Some(0.05),
+            ItemKind::Potato => Some(0.1),
+            ItemKind::Meat => Some(0.1),
+            ItemKind::CookedMeat => Some(0.3),
+            ItemKind::BakedPotato => Some(0.2),
+            ItemKind::BurntFood => Some(0.02),
+            _ => None,
+        }
+    }
+
+    /// Chance of getting sick from eating the item, in [0, 1].
+    pub fn sickness_chance(&self) -> f32 {
+        match self {
+            ItemKind::Meat => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    /// What the item turns into when it's cooked on a fire, if it can be cooked.
+    pub fn cooked(&self) -> Option<ItemKind> {
+        match self {
+            ItemKind::Meat => Some(ItemKind::CookedMeat),
+            ItemKind::Potato => Some(ItemKind::BakedPotato),
+            _ => None,
+        }
+    }
+
+    /// In-game days the item has to sit on a burning fire to cook.
+    pub fn cook_days(&self) -> f32 {
+        match self {
+            ItemKind::Potato => 0.03,
+            _ => 0.02,
+        }
+    }
+}
+
+impl Item {
+    /// A brand new item. Tools start out with full durability.
+    pub fn new(kind: ItemKind) -> Self {
+        Self {
+            kind,
+            tool: kind.tool_kind().map(Tool::new),
+        }
+    }
+}
+
+/// Spawns a pickable item into the world at a position, along with its BVH leaf. Tools keep their durability as a
+/// `Tool` component, so that they can be picked back up.
+pub fn spawn_item(
+    item: Item,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let kind = item.kind;
+    let mesh = renderer.get_mesh_id_from_name(kind.mesh_name()).unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(kind.texture_name())
+        .unwrap();
+    let scale_vec = kind.scale();
+    let entity = world.spawn((
+        ModelComponent::new(mesh, texture, position, scale_vec),
+        Pickable {
+            item_kind: kind,
+            label: kind.name(),
+        },
+    ));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(mesh)
+            .scale(scale_vec)
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    if let Some(tool) = item.tool {
+        world.insert_one(entity, tool).unwrap();
+    }
+    entity
+}
@@ -0,0 +1,114 @@
+use super::items::ItemKind;
+
+pub const KNAPPING_GRID_SIZE: usize = 8;
+
+/// A shape that a stone can be knapped into, and the tool blank it makes. '#' is stone, '.' is chipped away.
+pub struct KnappingTemplate {
+    pub output: ItemKind,
+    pub shape: [&'static str; KNAPPING_GRID_SIZE],
+}
+
+pub const KNAPPING_TEMPLATES: [KnappingTemplate; 4] = [
+    KnappingTemplate {
+        output: ItemKind::AxeHead,
+        shape: [
+            "..####..", //
+            ".######.", //
+            "########", //
+            "########", //
+            ".######.", //
+            "..####..", //
+            "...##...", //
+            "...##...", //
+        ],
+    },
+    KnappingTemplate {
+        output: ItemKind::KnifeBlade,
+        shape: [
+            "...##...", //
+            "...##...", //
+            "..###...", //
+            "..###...", //
+            "..###...", //
+            "..###...", //
+            "...#....", //
+            "...#....", //
+        ],
+    },
+    KnappingTemplate {
+        output: ItemKind::SpearPoint,
+        shape: [
+            "...##...", //
+            "..####..", //
+            "..####..", //
+            ".######.", //
+            ".######.", //
+            "..####..", //
+            "...##...", //
+            "...##...", //
+        ],
+    },
+    KnappingTemplate {
+        output: ItemKind::HoeBlade,
+        shape: [
+            "........", //
+            "........", //
+            "########", //
+            "########", //
+            ".######.", //
+            "...##...", //
+            "...##...", //
+            "........", //
+        ],
+    },
+];
+
+/// How a knapping session ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KnappingOutcome {
+    Made(ItemKind),
+    Broke,
+    Cancelled,
+}
+
+/// A stone being chipped into shape. Cells are true while there's still stone there.
+pub struct KnappingGrid {
+    pub cells: [[bool; KNAPPING_GRID_SIZE]; KNAPPING_GRID_SIZE],
+}
+
+impl KnappingTemplate {
+    pub fn has_stone(&self, x: usize, y: usize) -> bool {
+        self.shape[y].as_bytes()[x] == b'#'
+    }
+}
+
+impl KnappingGrid {
+    pub fn new() -> Self {
+        Self {
+            cells: [[true; KNAPPING_GRID_SIZE]; KNAPPING_GRID_SIZE],
+        }
+    }
+
+    /// Chips the stone away at a cell. Returns false if it was already chipped.
+    pub fn chip(&mut self, x: usize, y: usize) -> bool {
+        std::mem::replace(&mut self.cells[y][x], false)
+    }
+
+    /// The template that the stone is exactly shaped like, if any.
+    pub fn matching_template(&self) -> Option<&'static KnappingTemplate> {
+        KNAPPING_TEMPLATES
+            .iter()
+            .find(|template| self.cells_match(|x, y| template.has_stone(x, y) == self.cells[y][x]))
+    }
+
+    /// Whether some template can still be made by chipping away more stone. If not, the stone is ruined.
+    pub fn can_still_make_something(&self) -> bool {
+        KNAPPING_TEMPLATES
+            .iter()
+            .any(|template| self.cells_match(|x, y| !template.has_stone(x, y) || self.cells[y][x]))
+    }
+
+    fn cells_match(&self, f: impl Fn(usize, usize) -> bool) -> bool {
+        (0..KNAPPING_GRID_SIZE).all(|y| (0..KNAPPING_GRID_SIZE).all(|x| f(x, y)))
+    }
+}
@@ -0,0 +1,34 @@
+#[allow(unused)]
+pub(crate) mod animals;
+#[allow(unused)]
+pub(crate) mod biomes;
+#[allow(unused)]
+pub(crate) mod building;
+#[allow(unused)]
+pub(crate) mod cooking;
+#[allow(unused)]
+pub(crate) mod crafting;
+#[allow(unused)]
+pub(crate) mod farming;
+#[allow(unused)]
+pub(crate) mod fire;
+#[allow(unused)]
+pub(crate) mod inventory;
+#[allow(unused)]
+pub(crate) mod items;
+#[allow(unused)]
+pub(crate) mod knapping;
+#[allow(unused)]
+pub(crate) mod molding;
+#[allow(unused)]
+pub(crate) mod plants;
+#[allow(unused)]
+pub(crate) mod props;
+#[allow(unused)]
+pub(crate) mod save;
+#[allow(unused)]
+pub(crate) mod tools;
+#[allow(unused)]
+pub(crate) mod trees;
+#[allow(unused)]
+pub(crate) mod vitals;
@@ -0,0 +1,135 @@
+use super::items::ItemKind;
+
+pub const MOLDING_GRID_SIZE: usize = 6;
+/// How many cells of the grid a lump of clay can fill.
+pub const MOLDING_CLAY_CELLS: usize = 12;
+
+/// A shape that a lump of clay can be molded into, and the item it makes. '#' is clay, '.' is empty.
+pub struct MoldingTemplate {
+    pub output: ItemKind,
+    pub shape: [&'static str; MOLDING_GRID_SIZE],
+}
+
+pub const MOLDING_TEMPLATES: [MoldingTemplate; 2] = [
+    MoldingTemplate {
+        output: ItemKind::ClayPot,
+        shape: [
+            "......", //
+            ".#..#.", //
+            ".#..#.", //
+            ".#..#.", //
+            ".####.", //
+            "......", //
+        ],
+    },
+    MoldingTemplate {
+        output: ItemKind::ClayBowl,
+        shape: [
+            "......", //
+            "......", //
+            "#....#", //
+            ".#..#.", //
+            "..##..", //
+            "......", //
+        ],
+    },
+];
+
+/// How a molding session ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoldingOutcome {
+    Made(ItemKind),
+    Cancelled,
+}
+
+/// A lump of clay being pressed into shape. Cells are true where there's clay. Unlike knapping, clay can be put back
+/// where it was taken from, as long as there's some of the lump left.
+pub struct MoldingGrid {
+    pub cells: [[bool; MOLDING_GRID_SIZE]; MOLDING_GRID_SIZE],
+}
+
+impl MoldingTemplate {
+    pub fn has_clay(&self, x: usize, y: usize) -> bool {
+        self.shape[y].as_bytes()[x] == b'#'
+    }
+}
+
+impl MoldingGrid {
+    pub fn new() -> Self {
+        Self {
+            cells: [[false; MOLDING_GRID_SIZE]; MOLDING_GRID_SIZE],
+        }
+    }
+
+    /// Takes the clay away from a cell, or presses some onto it. Returns false if there's no clay left to press on.
+    pub fn press(&mut self, x: usize, y: usize) -> bool {
+        if !self.cells[y][x] && self.clay_left() == 0 {
+            return false;
+        }
+        self.cells[y][x] = !self.cells[y][x];
+        true
+    }
+
+    /// How many more cells the rest of the lump can fill.
+    pub fn clay_left(&self) -> usize {
+        let used = self.cells.iter().flatten().filter(|cell| **cell).count();
+        MOLDING_CLAY_CELLS - used
+    }
+
+    /// The template that the clay is exactly shaped like, if any.
+    pub fn matching_template(&self) -> Option<&'static MoldingTemplate> {
+        MOLDING_TEMPLATES.iter().find(|template| {
+            (0..MOLDING_GRID_SIZE).all(|y| {
+                (0..MOLDING_GRID_SIZE).all(|x| template.has_clay(x, y) == self.cells[y][x])
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressing_a_template_makes_its_item() {
+        for template in &MOLDING_TEMPLATES {
+            let clay = template
+                .shape
+                .iter()
+                .flat_map(|row| row.bytes())
+                .filter(|cell| *cell == b'#');
+            assert!(clay.count() <= MOLDING_CLAY_CELLS);
+
+            let mut grid = MoldingGrid::new();
+            for y in 0..MOLDING_GRID_SIZE {
+                for x in 0..MOLDING_GRID_SIZE {
+                    if template.has_clay(x, y) {
+                        assert!(grid.press(x, y));
+                    }
+                }
+            }
+            assert_eq!(
+                grid.matching_template().map(|found| found.output),
+                Some(template.output)
+            );
+        }
+    }
+
+    #[test]
+    fn the_lump_only_fills_so_many_cells() {
+        let mut grid = MoldingGrid::new();
+        let cells =
+            (0..MOLDING_GRID_SIZE).flat_map(|y| (0..MOLDING_GRID_SIZE).map(move |x| (x, y)));
+        for (x, y) in cells.clone().take(MOLDING_CLAY_CELLS) {
+            assert!(grid.press(x, y));
+        }
+        assert_eq!(grid.clay_left(), 0);
+        let (x, y) = cells.clone().nth(MOLDING_CLAY_CELLS).unwrap();
+        assert!(!grid.press(x, y));
+
+        // Taking clay away gives it back to the lump
+        assert!(grid.press(0, 0));
+        assert_eq!(grid.clay_left(), 1);
+        assert!(grid.press(x, y));
+    }
+}
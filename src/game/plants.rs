@@ -0,0 +1,105 @@
+use hecs::{Entity, World};
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    render_core::{ModelComponent, RenderContext},
+};
+
+use super::items::ItemKind;
+
+/// A wild bush that grows berries, which can be picked and eaten.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BerryBush {
+    pub berries: usize,
+    pub regrow_timer: f32, //< In-game days until the next berry grows
+}
+
+const MAX_BERRIES: usize = 3;
+const DAYS_PER_BERRY: f32 = 0.5;
+
+impl BerryBush {
+    pub fn new(berries: usize) -> Self {
+        Self {
+            berries: berries.min(MAX_BERRIES),
+            regrow_timer: DAYS_PER_BERRY,
+        }
+    }
+
+    /// Grows the bush by some amount of in-game days. Returns true if the bush went from bare to fruiting.
+    pub fn tick(&mut self, days: f32) -> bool {
+        if self.berries >= MAX_BERRIES {
+            return false;
+        }
+        self.regrow_timer -= days;
+        if self.regrow_timer > 0.0 {
+            return false;
+        }
+        self.regrow_timer += DAYS_PER_BERRY;
+        self.berries += 1;
+        self.berries == 1
+    }
+
+    /// Picks a berry off of the bush, if there are any.
+    pub fn harvest(&mut self) -> Option<ItemKind> {
+        if self.berries == 0 {
+            return None;
+        }
+        if self.berries == MAX_BERRIES {
+            // The bush was full and wasn't growing, start regrowing from scratch
+            self.regrow_timer = DAYS_PER_BERRY;
+        }
+        self.berries -= 1;
+        Some(ItemKind::Berry)
+    }
+
+    pub fn has_berries(&self) -> bool {
+        self.berries > 0
+    }
+
+    /// Berried bushes use a different texture than bare ones.
+    pub fn texture_name(&self) -> &'static str {
+        if self.has_berries() {
+            "berry-bush"
+        } else {
+            "tree"
+        }
+    }
+}
+
+/// Spawns a berry bush into the world at a position, along with its BVH leaf.
+pub fn spawn_berry_bush(
+    bush: BerryBush,
+    position: nalgebra_glm::Vec3,
+    scale: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name("bush").unwrap();
+    let texture = renderer
+        .get_texture_id_from_name(bush.texture_name())
+        .unwrap();
+    let entity = world.spawn((ModelComponent::new(mesh, texture, position, scale), bush));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(mesh)
+            .scale(scale)
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Regrows berries on all berry bushes, and updates their visual state.
+pub fn berry_bush_system(world: &mut World, renderer: &RenderContext, days: f32) {
+    for (_, (bush, model)) in world.query_mut::<(&mut BerryBush, &mut ModelComponent)>() {
+        if bush.tick(days) {
+            model.texture_id = renderer
+                .get_texture_id_from_name(bush.texture_name())
+                .unwrap();
+        }
+    }
+}
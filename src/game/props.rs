@@ -0,0 +1,322 @@
+use hecs::{Entity, World};
+
+use crate::{
+    engine::{
+        bvh::{BVHNodeComponent, BVH},
+        render_core::{ModelComponent, RenderContext},
+    },
+    scenes::gameplay::Rock,
+};
+
+use super::{
+    animals::{spawn_animal, spawn_carcass, Animal, AnimalKind, Carcass},
+    building::{
+        complete_structure, spawn_blueprint, spawn_structure, Blueprint, Structure, StructureKind,
+    },
+    farming::{spawn_crop_at, Crop},
+    fire::Fire,
+    items::{spawn_item, Item, ItemKind, Pickable},
+    plants::{spawn_berry_bush, BerryBush},
+    tools::Tool,
+    trees::{spawn_regrowth, spawn_tree, Regrowth, Tree},
+};
+
+/// Something that sits on the terrain, in world space. Chunks are generated as a list of props, and the props of a
+/// chunk are taken back out of the world when it's unloaded so that it can be put back the way it was.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Prop {
+    Rock {
+        position: nalgebra_glm::Vec3,
+    },
+    Tree {
+        position: nalgebra_glm::Vec3,
+        scale: nalgebra_glm::Vec3,
+        health: usize,
+    },
+    Item {
+        item: Item,
+        position: nalgebra_glm::Vec3,
+    },
+    BerryBush {
+        bush: BerryBush,
+        position: nalgebra_glm::Vec3,
+        scale: nalgebra_glm::Vec3,
+    },
+    Crop {
+        crop: Crop,
+        position: nalgebra_glm::Vec3, //< Center of the cell the crop is in
+    },
+    Regrowth {
+        regrowth: Regrowth,
+        position: nalgebra_glm::Vec3,
+    },
+    Structure {
+        kind: StructureKind,
+        position: nalgebra_glm::Vec3,
+        fuel: f32, //< Fuel left in the fire, if the structure is a firepit
+    },
+    Blueprint {
+        blueprint: Blueprint,
+        position: nalgebra_glm::Vec3,
+    },
+    Carcass {
+        kind: AnimalKind,
+        position: nalgebra_glm::Vec3,
+    },
+    Animal {
+        kind: AnimalKind,
+        position: nalgebra_glm::Vec3,
+    },
+}
+
+const ROCK_SCALE: f32 = 0.2;
+
+impl Prop {
+    pub fn is_animal(&self) -> bool {
+        matches!(self, Prop::Animal { .. })
+    }
+
+    /// Whether the prop is still the way its chunk generated it, apart from what changes by itself over time: bushes
+    /// growing their berries back.
+    pub fn unchanged_from(&self, generated: &Prop) -> bool {
+        match (self, generated) {
+            (
+                Prop::BerryBush {
+                    bush,
+                    position,
+                    scale,
+                },
+                Prop::BerryBush {
+                    bush: generated_bush,
+                    position: generated_position,
+                    scale: generated_scale,
+                },
+            ) => {
+                position == generated_position
+                    && scale == generated_scale
+                    && bush.berries >= generated_bush.berries
+            }
+            _ => self == generated,
+        }
+    }
+
+    pub fn position(&self) -> nalgebra_glm::Vec3 {
+        match self {
+            Prop::Rock { position }
+            | Prop::Tree { position, .. }
+            | Prop::Item { position, .. }
+            | Prop::BerryBush { position, .. }
+            | Prop::Crop { position, .. }
+            | Prop::Regrowth { position, .. }
+            | Prop::Structure { position, .. }
+            | Prop::Blueprint { position, .. }
+            | Prop::Carcass { position, .. }
+            | Prop::Animal { position, .. } => *position,
+        }
+    }
+
+    /// The same prop, somewhere else on the map.
+    pub fn moved(mut self, offset: nalgebra_glm::Vec2) -> Prop {
+        let offset = nalgebra_glm::vec3(offset.x, offset.y, 0.0);
+        match &mut self {
+            Prop::Crop { crop, position } => {
+                crop.cell += offset.xy();
+                *position += offset;
+            }
+            Prop::Rock { position }
+            | Prop::Tree { position, .. }
+            | Prop::Item { position, .. }
+            | Prop::BerryBush { position, .. }
+            | Prop::Regrowth { position, .. }
+            | Prop::Structure { position, .. }
+            | Prop::Blueprint { position, .. }
+            | Prop::Carcass { position, .. }
+            | Prop::Animal { position, .. } => *position += offset,
+        }
+        self
+    }
+}
+
+/// Spawns a prop into the world, along with its BVH leaf.
+pub fn spawn_prop(
+    prop: Prop,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    match prop {
+        Prop::Rock { position } => spawn_rock(position, renderer, world, bvh),
+        Prop::Tree {
+            position,
+            scale,
+            health,
+        } => {
+            let entity = spawn_tree(position, scale, renderer, world, bvh);
+            world.get::<&mut Tree>(entity).unwrap().health = health;
+            entity
+        }
+        Prop::Item { item, position } => spawn_item(item, position, renderer, world, bvh),
+        Prop::BerryBush {
+            bush,
+            position,
+            scale,
+        } => spawn_berry_bush(bush, position, scale, renderer, world, bvh),
+        Prop::Crop { crop, position } => spawn_crop_at(crop, position, renderer, world, bvh),
+        Prop::Regrowth { regrowth, position } => {
+            spawn_regrowth(regrowth, position, renderer, world, bvh)
+        }
+        Prop::Structure {
+            kind,
+            position,
+            fuel,
+        } => {
+            let entity = spawn_structure(kind, position, renderer, world, bvh);
+            complete_structure(entity, kind, world);
+            if let Ok(mut fire) = world.get::<&mut Fire>(entity) {
+                fire.fuel = fuel;
+            }
+            entity
+        }
+        Prop::Blueprint {
+            blueprint,
+            position,
+        } => spawn_blueprint(blueprint, position, renderer, world, bvh),
+        Prop::Carcass { kind, position } => spawn_carcass(kind, position, renderer, world, bvh),
+        Prop::Animal { kind, position } => spawn_animal(kind, position, renderer, world, bvh),
+    }
+}
+
+fn spawn_rock(
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name("cube").unwrap();
+    let texture = renderer.get_texture_id_from_name("rock").unwrap();
+    let scale_vec = nalgebra_glm::vec3(ROCK_SCALE, ROCK_SCALE, ROCK_SCALE);
+    let entity = world.spawn((
+        ModelComponent::new(mesh, texture, position, scale_vec),
+        Rock {},
+        Pickable {
+            item_kind: ItemKind::Stone,
+            label: "Stone",
+        },
+    ));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(mesh)
+            .scale(scale_vec * 0.5)
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Takes the props in an area out of the world and the BVH, and returns them so that they can be spawned again later.
+/// Animals are taken out too, but aren't returned, since they'd have wandered off anyways.
+pub fn take_props(
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+    min: nalgebra_glm::Vec2,
+    max: nalgebra_glm::Vec2,
+) -> Vec<Prop> {
+    let mut props = vec![];
+    let mut taken = vec![];
+    for (entity, model) in world.query::<&ModelComponent>().iter() {
+        let position = model.get_position();
+        if position.x < min.x || position.y < min.y || position.x >= max.x || position.y >= max.y {
+            continue;
+        }
+        if world.get::<&Animal>(entity).is_ok() {
+            taken.push(entity);
+        } else if let Some(prop) = prop_of(world, entity, model) {
+            taken.push(entity);
+            props.push(prop);
+        }
+    }
+
+    for entity in taken {
+        if let Ok(bvh_node) = world.get::<&BVHNodeComponent>(entity) {
+            bvh.remove(bvh_node.node_id);
+        }
+        world.despawn(entity).unwrap();
+    }
+    props
+}
+
+/// Every prop in the world, wherever it is.
+pub fn all_props(world: &World) -> Vec<Prop> {
+    world
+        .query::<&ModelComponent>()
+        .iter()
+        .filter_map(|(entity, model)| prop_of(world, entity, model))
+        .collect()
+}
+
+/// What prop an entity is, if it's one at all.
+fn prop_of(world: &World, entity: Entity, model: &ModelComponent) -> Option<Prop> {
+    let position = model.get_position();
+    if world.get::<&Rock>(entity).is_ok() {
+        return Some(Prop::Rock { position });
+    }
+    if let Ok(tree) = world.get::<&Tree>(entity) {
+        return Some(Prop::Tree {
+            position,
+            scale: model.get_scale(),
+            health: tree.health,
+        });
+    }
+    if let Ok(pickable) = world.get::<&Pickable>(entity) {
+        let tool = world.get::<&Tool>(entity).ok().map(|tool| *tool);
+        return Some(Prop::Item {
+            item: Item {
+                kind: pickable.item_kind,
+                tool,
+            },
+            position,
+        });
+    }
+    if let Ok(bush) = world.get::<&BerryBush>(entity) {
+        return Some(Prop::BerryBush {
+            bush: (*bush).clone(),
+            position,
+            scale: model.get_scale(),
+        });
+    }
+    if let Ok(crop) = world.get::<&Crop>(entity) {
+        return Some(Prop::Crop {
+            crop: (*crop).clone(),
+            position,
+        });
+    }
+    if let Ok(regrowth) = world.get::<&Regrowth>(entity) {
+        return Some(Prop::Regrowth {
+            regrowth: (*regrowth).clone(),
+            position,
+        });
+    }
+    if let Ok(structure) = world.get::<&Structure>(entity) {
+        return Some(Prop::Structure {
+            kind: structure.kind,
+            position,
+            fuel: world.get::<&Fire>(entity).map_or(0.0, |fire| fire.fuel),
+        });
+    }
+    if let Ok(blueprint) = world.get::<&Blueprint>(entity) {
+        return Some(Prop::Blueprint {
+            blueprint: (*blueprint).clone(),
+            position,
+        });
+    }
+    if let Ok(carcass) = world.get::<&Carcass>(entity) {
+        return Some(Prop::Carcass {
+            kind: carcass.kind,
+            position,
+        });
+    }
+    None
+}
@@ -0,0 +1,768 @@
+use std::str::{FromStr, SplitWhitespace};
+
+use crate::engine::chunked_map::{ChunkCoord, MapChanges, MapEdges, MapParams, Soil};
+
+use super::{
+    animals::AnimalKind,
+    building::{Blueprint, StructureKind},
+    farming::{Crop, CropKind},
+    inventory::{Hand, Hands},
+    items::{Item, ItemKind},
+    plants::BerryBush,
+    props::Prop,
+    trees::{Regrowth, TreeStage},
+    vitals::Vitals,
+};
+
+/// Version of the save format written by this build. Saves from newer versions can't be loaded.
+pub const SAVE_VERSION: u32 = 3;
+/// The first version of the save format that keeps the generated props that the player took or changed.
+pub const REMOVED_PROPS_VERSION: u32 = 3;
+
+/// A structure or blueprint that the player placed.
+pub struct SavedStructure {
+    pub kind: StructureKind,
+    pub position: nalgebra_glm::Vec3,
+    pub remaining: Option<Vec<(ItemKind, usize)>>, //< Materials still needed, if it's still a blueprint
+    pub fuel: f32, //< Fuel left in the fire, if the structure is a firepit
+}
+
+/// Everything needed to put the game back the way it was. The terrain isn't stored, it's generated again from the
+/// map's parameters, so only what the player changed about the world is kept.
+///
+/// Saves are plain text files with one record per line, like `player 8193 8192 2.5`. The first word of a line says
+/// what the record is, and the rest are its fields. To keep old saves loading, fields are only ever added to the end
+/// of a record, and fields that are missing get a default value. Records that aren't known are skipped.
+pub struct SaveGame {
+    pub version: u32, //< Version of the save format that the save was written in
+    pub map: MapParams,
+    pub terrain_version: u32, //< Version of the terrain generator that the world was played on
+    pub ticks: usize,         //< In-game time
+    pub position: nalgebra_glm::Vec3,
+    pub spawn_point: nalgebra_glm::Vec3,
+    pub vitals: Vitals,
+    pub hands: Hands,
+    pub soil: Vec<(nalgebra_glm::Vec2, Soil)>, //< Cells that were tilled, by their world-space origin
+    pub crops: Vec<Crop>,                      //< Crops that were planted, or have grown since
+    pub regrowth: Vec<(nalgebra_glm::Vec3, Regrowth)>, //< Felled trees, and where they were
+    pub structures: Vec<SavedStructure>,
+    pub items: Vec<(nalgebra_glm::Vec3, Item)>, //< Items that the player left lying around
+    pub removed: Vec<(ChunkCoord, Vec<usize>)>, //< Generated props that were taken or changed, by chunk and index
+    pub rocks: Vec<nalgebra_glm::Vec3>,
+    pub trees: Vec<(nalgebra_glm::Vec3, nalgebra_glm::Vec3, usize)>, //< Chopped at, with scale and health
+    pub bushes: Vec<(nalgebra_glm::Vec3, nalgebra_glm::Vec3, BerryBush)>, //< Picked, with scale
+    pub carcasses: Vec<(nalgebra_glm::Vec3, AnimalKind)>,
+}
+
+/// The fields of a record, read one at a time.
+struct Fields<'a> {
+    tokens: SplitWhitespace<'a>,
+}
+
+impl SaveGame {
+    /// A save for a world that nothing has happened in yet.
+    pub fn new(map: MapParams) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            map,
+            terrain_version: 1,
+            ticks: 0,
+            position: nalgebra_glm::zero(),
+            spawn_point: nalgebra_glm::zero(),
+            vitals: Vitals::new(),
+            hands: Hands::new(),
+            soil: vec![],
+            crops: vec![],
+            regrowth: vec![],
+            structures: vec![],
+            items: vec![],
+            removed: vec![],
+            rocks: vec![],
+            trees: vec![],
+            bushes: vec![],
+            carcasses: vec![],
+        }
+    }
+
+    /// The same save without any of the changes to the world, and with the player back at their spawn point. Changes
+    /// made on terrain from an older terrain generator wouldn't fit the terrain that's generated now.
+    pub fn without_world_changes(self) -> Self {
+        Self {
+            position: self.spawn_point,
+            soil: vec![],
+            crops: vec![],
+            regrowth: vec![],
+            structures: vec![],
+            items: vec![],
+            removed: vec![],
+            rocks: vec![],
+            trees: vec![],
+            bushes: vec![],
+            carcasses: vec![],
+            ..self
+        }
+    }
+
+    /// Adds what the player changed about the map to the save.
+    pub fn add_map_changes(&mut self, changes: MapChanges) {
+        self.soil.extend(changes.soil);
+        self.removed.extend(changes.removed);
+        for prop in changes.added {
+            match prop {
+                Prop::Rock { position } => self.rocks.push(position),
+                Prop::Tree {
+                    position,
+                    scale,
+                    health,
+                } => self.trees.push((position, scale, health)),
+                Prop::Item { item, position } => self.items.push((position, item)),
+                Prop::BerryBush {
+                    bush,
+                    position,
+                    scale,
+                } => self.bushes.push((position, scale, bush)),
+                Prop::Crop { crop, .. } => self.crops.push(crop),
+                Prop::Regrowth { regrowth, position } => self.regrowth.push((position, regrowth)),
+                Prop::Structure {
+                    kind,
+                    position,
+                    fuel,
+                } => self.structures.push(SavedStructure {
+                    kind,
+                    position,
+                    remaining: None,
+                    fuel,
+                }),
+                Prop::Blueprint {
+                    blueprint,
+                    position,
+                } => self.structures.push(SavedStructure {
+                    kind: blueprint.kind,
+                    position,
+                    remaining: Some(blueprint.remaining),
+                    fuel: 0.0,
+                }),
+                Prop::Carcass { kind, position } => self.carcasses.push((position, kind)),
+                Prop::Animal { .. } => {} // Animals wander off, so they're never kept
+            }
+        }
+    }
+
+    /// The changes to the map that the save keeps.
+    pub fn map_changes(&self) -> MapChanges {
+        let mut added: Vec<Prop> = vec![];
+        added.extend(self.rocks.iter().map(|position| Prop::Rock {
+            position: *position,
+        }));
+        added.extend(
+            self.trees
+                .iter()
+                .map(|(position, scale, health)| Prop::Tree {
+                    position: *position,
+                    scale: *scale,
+                    health: *health,
+                }),
+        );
+        added.extend(self.items.iter().map(|(position, item)| Prop::Item {
+            item: *item,
+            position: *position,
+        }));
+        added.extend(
+            self.bushes
+                .iter()
+                .map(|(position, scale, bush)| Prop::BerryBush {
+                    bush: bush.clone(),
+                    position: *position,
+                    scale: *scale,
+                }),
+        );
+        // The crop's height is worked out from the terrain once its chunk is populated
+        added.extend(self.crops.iter().map(|crop| Prop::Crop {
+            crop: crop.clone(),
+            position: nalgebra_glm::vec3(crop.cell.x + 0.5, crop.cell.y + 0.5, 0.0),
+        }));
+        added.extend(
+            self.regrowth
+                .iter()
+                .map(|(position, regrowth)| Prop::Regrowth {
+                    regrowth: regrowth.clone(),
+                    position: *position,
+                }),
+        );
+        added.extend(
+            self.structures
+                .iter()
+                .map(|structure| match &structure.remaining {
+                    Some(remaining) => Prop::Blueprint {
+                        blueprint: Blueprint {
+                            kind: structure.kind,
+                            remaining: remaining.clone(),
+                        },
+                        position: structure.position,
+                    },
+                    None => Prop::Structure {
+                        kind: structure.kind,
+                        position: structure.position,
+                        fuel: structure.fuel,
+                    },
+                }),
+        );
+        added.extend(self.carcasses.iter().map(|(position, kind)| Prop::Carcass {
+            kind: *kind,
+            position: *position,
+        }));
+        MapChanges {
+            removed: self.removed.clone(),
+            added,
+            soil: self.soil.clone(),
+        }
+    }
+
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(filename)
+            .map_err(|err| format!("couldn't read {}: {}", filename, err))?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", filename, err))
+    }
+
+    /// Writes the save to a file. The old save is only replaced once the new one has been written completely.
+    pub fn to_file(&self, filename: &str) -> Result<(), String> {
+        let temp_filename = format!("{}.tmp", filename);
+        std::fs::write(&temp_filename, self.to_text())
+            .and_then(|_| std::fs::rename(&temp_filename, filename))
+            .map_err(|err| format!("couldn't write {}: {}", filename, err))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            String::from("# Survival save file"),
+            format!("version {}", SAVE_VERSION),
+            format!(
+                "map {} {} {} {} {} {} {}",
+                self.map.map_width,
+                self.map.chunk_width,
+                self.map.level_of_detail,
+                self.map.seed,
+                self.map.amplitude,
+                self.map.edges.id(),
+                self.terrain_version
+            ),
+            format!("time {}", self.ticks),
+            format!("player {}", vec3_text(self.position)),
+            format!("spawn {}", vec3_text(self.spawn_point)),
+            format!(
+                "vitals {} {} {} {} {}",
+                self.vitals.hunger,
+                self.vitals.thirst,
+                self.vitals.energy,
+                self.vitals.temperature,
+                self.vitals.sick_ticks
+            ),
+            format!("dominant {}", self.hands.dominant.id()),
+        ];
+        for hand in [Hand::Left, Hand::Right] {
+            if let Some(item) = self.hands.slot(hand) {
+                let durability = item.tool.map_or(0, |tool| tool.durability);
+                lines.push(format!(
+                    "hand {} {} {}",
+                    hand.id(),
+                    item.kind.id(),
+                    durability
+                ));
+            }
+        }
+        for (cell, soil) in &self.soil {
+            lines.push(format!(
+                "soil {} {} {} {}",
+                cell.x, cell.y, soil.watered as u8, soil.planted as u8
+            ));
+        }
+        for crop in &self.crops {
+            lines.push(format!(
+                "crop {} {} {} {} {}",
+                crop.kind.id(),
+                crop.cell.x,
+                crop.cell.y,
+                crop.stage,
+                crop.growth
+            ));
+        }
+        for (position, regrowth) in &self.regrowth {
+            lines.push(format!(
+                "regrowth {} {} {} {}",
+                vec3_text(*position),
+                vec3_text(regrowth.tree_scale),
+                regrowth.stage.id(),
+                regrowth.growth
+            ));
+        }
+        for structure in &self.structures {
+            let remaining = structure
+                .remaining
+                .as_ref()
+                .map_or(String::from("-"), |remaining| {
+                    remaining
+                        .iter()
+                        .map(|(item, count)| format!("{}:{}", item.id(), count))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                });
+            lines.push(format!(
+                "structure {} {} {} {}",
+                structure.kind.id(),
+                vec3_text(structure.position),
+                remaining,
+                structure.fuel
+            ));
+        }
+        for (position, item) in &self.items {
+            let durability = item.tool.map_or(0, |tool| tool.durability);
+            lines.push(format!(
+                "item {} {} {}",
+                item.kind.id(),
+                vec3_text(*position),
+                durability
+            ));
+        }
+        for (coord, removed) in &self.removed {
+            let indices: Vec<String> = removed.iter().map(|index| index.to_string()).collect();
+            lines.push(format!(
+                "removed {} {} {}",
+                coord.x,
+                coord.y,
+                indices.join(" ")
+            ));
+        }
+        for position in &self.rocks {
+            lines.push(format!("rock {}", vec3_text(*position)));
+        }
+        for (position, scale, health) in &self.trees {
+            lines.push(format!(
+                "tree {} {} {}",
+                vec3_text(*position),
+                vec3_text(*scale),
+                health
+            ));
+        }
+        for (position, scale, bush) in &self.bushes {
+            lines.push(format!(
+                "bush {} {} {} {}",
+                vec3_text(*position),
+                vec3_text(*scale),
+                bush.berries,
+                bush.regrow_timer
+            ));
+        }
+        for (position, kind) in &self.carcasses {
+            lines.push(format!("carcass {} {}", kind.id(), vec3_text(*position)));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Parses the save file format. Returns an error message with the line number of the first bad line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut save: Option<SaveGame> = None;
+        let mut version = None;
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            parse_record(line, &mut version, &mut save)
+                .map_err(|err| format!("line {}: {}", line_number + 1, err))?;
+        }
+        let Some(version) = version else {
+            return Err(String::from("missing `version`"));
+        };
+        let mut save = save.ok_or_else(|| String::from("missing `map`"))?;
+        save.version = version;
+        Ok(save)
+    }
+}
+
+fn parse_record(
+    line: &str,
+    version: &mut Option<u32>,
+    save: &mut Option<SaveGame>,
+) -> Result<(), String> {
+    let mut fields = Fields {
+        tokens: line.split_whitespace(),
+    };
+    let record: String = fields.next("record")?;
+
+    // The version and map come first, everything else needs them
+    match record.as_str() {
+        "version" => {
+            let found: u32 = fields.next("version")?;
+            if found > SAVE_VERSION {
+                return Err(format!(
+                    "save is version {}, but only up to version {} can be loaded",
+                    found, SAVE_VERSION
+                ));
+            }
+            *version = Some(found);
+            return Ok(());
+        }
+        "map" => {
+            let mut map = MapParams {
+                map_width: fields.next("map width")?,
+                chunk_width: fields.next("chunk width")?,
+                level_of_detail: fields.next("level of detail")?,
+                seed: fields.next("seed")?,
+                amplitude: fields.next("amplitude")?,
+                edges: MapEdges::Bounded,
+            };
+            let id: String = fields.next_or(String::from(map.edges.id()))?;
+            map.edges =
+                MapEdges::from_id(&id).ok_or_else(|| format!("unknown map edges `{}`", id))?;
+            let mut new_save = SaveGame::new(map);
+            new_save.terrain_version = fields.next_or(new_save.terrain_version)?;
+            *save = Some(new_save);
+            return Ok(());
+        }
+        _ => {}
+    }
+    if version.is_none() {
+        return Err(String::from("expected `version` first"));
+    }
+    let save = save
+        .as_mut()
+        .ok_or_else(|| format!("expected `map` before `{}`", record))?;
+
+    match record.as_str() {
+        "time" => save.ticks = fields.next("time")?,
+        "player" => save.position = fields.vec3()?,
+        "spawn" => save.spawn_point = fields.vec3()?,
+        "vitals" => {
+            save.vitals.hunger = fields.next_or(1.0)?;
+            save.vitals.thirst = fields.next_or(1.0)?;
+            save.vitals.energy = fields.next_or(1.0)?;
+            save.vitals.temperature = fields.next_or(1.0)?;
+            save.vitals.sick_ticks = fields.next_or(0)?;
+        }
+        "dominant" => {
+            let id: String = fields.next("hand")?;
+            save.hands.dominant =
+                Hand::from_id(&id).ok_or_else(|| format!("unknown hand `{}`", id))?;
+        }
+        "hand" => {
+            let id: String = fields.next("hand")?;
+            let hand = Hand::from_id(&id).ok_or_else(|| format!("unknown hand `{}`", id))?;
+            let mut item = Item::new(parse_item(&fields.next::<String>("item")?)?);
+            if let Some(tool) = &mut item.tool {
+                tool.durability = fields.next_or(tool.durability)?;
+            }
+            *save.hands.slot_mut(hand) = Some(item);
+        }
+        "soil" => {
+            let cell = fields.vec2()?;
+            let soil = Soil {
+                tilled: true,
+                watered: fields.next_or(0u8)? != 0,
+                planted: fields.next_or(0u8)? != 0,
+            };
+            save.soil.push((cell, soil));
+        }
+        "crop" => {
+            let id: String = fields.next("crop")?;
+            let kind = CropKind::from_id(&id).ok_or_else(|| format!("unknown crop `{}`", id))?;
+            let cell = fields.vec2()?;
+            let mut crop = Crop::new(kind, fields.next_or(0)?, cell);
+            crop.growth = fields.next_or(0.0)?;
+            save.crops.push(crop);
+        }
+        "regrowth" => {
+            let position = fields.vec3()?;
+            let mut regrowth = Regrowth::new(fields.vec3()?);
+            let id: String = fields.next_or(String::from("stump"))?;
+            regrowth.stage =
+                TreeStage::from_id(&id).ok_or_else(|| format!("unknown tree stage `{}`", id))?;
+            regrowth.growth = fields.next_or(0.0)?;
+            save.regrowth.push((position, regrowth));
+        }
+        "structure" => {
+            let id: String = fields.next("structure")?;
+            let kind =
+                StructureKind::from_id(&id).ok_or_else(|| format!("unknown structure `{}`", id))?;
+            let position = fields.vec3()?;
+            let remaining: String = fields.next_or(String::from("-"))?;
+            let remaining = if remaining == "-" {
+                None
+            } else {
+                Some(parse_materials(&remaining)?)
+            };
+            save.structures.push(SavedStructure {
+                kind,
+                position,
+                remaining,
+                fuel: fields.next_or(0.0)?,
+            });
+        }
+        "item" => {
+            let mut item = Item::new(parse_item(&fields.next::<String>("item")?)?);
+            let position = fields.vec3()?;
+            if let Some(tool) = &mut item.tool {
+                tool.durability = fields.next_or(tool.durability)?;
+            }
+            save.items.push((position, item));
+        }
+        "removed" => {
+            let coord = ChunkCoord::new(fields.next("chunk x")?, fields.next("chunk y")?);
+            save.removed.push((coord, fields.rest("prop index")?));
+        }
+        "rock" => save.rocks.push(fields.vec3()?),
+        "tree" => {
+            let position = fields.vec3()?;
+            let scale = fields.vec3()?;
+            save.trees.push((position, scale, fields.next("health")?));
+        }
+        "bush" => {
+            let position = fields.vec3()?;
+            let scale = fields.vec3()?;
+            let mut bush = BerryBush::new(fields.next("berries")?);
+            bush.regrow_timer = fields.next_or(bush.regrow_timer)?;
+            save.bushes.push((position, scale, bush));
+        }
+        "carcass" => {
+            let id: String = fields.next("animal")?;
+            let kind =
+                AnimalKind::from_id(&id).ok_or_else(|| format!("unknown animal `{}`", id))?;
+            save.carcasses.push((fields.vec3()?, kind));
+        }
+        _ => {} // Written by a newer version, or not used anymore
+    }
+    Ok(())
+}
+
+/// Parses a list of materials, like `stick:3,wheat:4`.
+fn parse_materials(text: &str) -> Result<Vec<(ItemKind, usize)>, String> {
+    text.split(',')
+        .map(|material| {
+            let (id, count) = material
+                .split_once(':')
+                .ok_or_else(|| format!("expected `item:count`, found `{}`", material))?;
+            let count = count
+                .parse()
+                .map_err(|_| format!("bad material count `{}`", count))?;
+            Ok((parse_item(id)?, count))
+        })
+        .collect()
+}
+
+fn parse_item(id: &str) -> Result<ItemKind, String> {
+    ItemKind::from_id(id).ok_or_else(|| format!("unknown item `{}`", id))
+}
+
+fn vec3_text(v: nalgebra_glm::Vec3) -> String {
+    format!("{} {} {}", v.x, v.y, v.z)
+}
+
+impl<'a> Fields<'a> {
+    /// Reads a field that has to be there.
+    fn next<T: FromStr>(&mut self, what: &str) -> Result<T, String> {
+        let token = self
+            .tokens
+            .next()
+            .ok_or_else(|| format!("missing {}", what))?;
+        token
+            .parse()
+            .map_err(|_| format!("bad {} `{}`", what, token))
+    }
+
+    /// Reads a field that older saves might not have.
+    fn next_or<T: FromStr>(&mut self, default: T) -> Result<T, String> {
+        match self.tokens.next() {
+            Some(token) => token.parse().map_err(|_| format!("bad field `{}`", token)),
+            None => Ok(default),
+        }
+    }
+
+    /// Reads the rest of the fields, which all have to be the same kind.
+    fn rest<T: FromStr>(&mut self, what: &str) -> Result<Vec<T>, String> {
+        self.tokens
+            .by_ref()
+            .map(|token| {
+                token
+                    .parse()
+                    .map_err(|_| format!("bad {} `{}`", what, token))
+            })
+            .collect()
+    }
+
+    fn vec2(&mut self) -> Result<nalgebra_glm::Vec2, String> {
+        Ok(nalgebra_glm::vec2(self.next("x")?, self.next("y")?))
+    }
+
+    fn vec3(&mut self) -> Result<nalgebra_glm::Vec3, String> {
+        Ok(nalgebra_glm::vec3(
+            self.next("x")?,
+            self.next("y")?,
+            self.next("z")?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "version 2\nmap 16384 16 0.01 42 1 bounded 2\n";
+
+    #[test]
+    fn saves_read_back_the_way_they_were_written() {
+        let mut save = SaveGame::new(MapParams {
+            map_width: 4096,
+            chunk_width: 16,
+            level_of_detail: 0.01,
+            seed: -7,
+            amplitude: 1.5,
+            edges: MapEdges::Wrapping,
+        });
+        save.terrain_version = 2;
+        save.ticks = 1234;
+        save.position = nalgebra_glm::vec3(2049.5, 2048.25, 2.5);
+        save.spawn_point = nalgebra_glm::vec3(2049.0, 2048.0, 2.5);
+        save.vitals.hunger = 0.5;
+        save.vitals.sick_ticks = 30;
+        save.hands.dominant = Hand::Left;
+        let mut axe = Item::new(ItemKind::StoneAxe);
+        axe.tool.as_mut().unwrap().durability = 3;
+        *save.hands.slot_mut(Hand::Right) = Some(axe);
+        let cell = nalgebra_glm::vec2(2050.0, 2047.0);
+        save.soil.push((
+            cell,
+            Soil {
+                tilled: true,
+                watered: true,
+                planted: true,
+            },
+        ));
+        let mut crop = Crop::new(CropKind::Potato, 1, cell);
+        crop.growth = 0.25;
+        save.crops.push(crop);
+        save.regrowth.push((
+            nalgebra_glm::vec3(2040.0, 2041.0, 1.0),
+            Regrowth::new(nalgebra_glm::vec3(1.0, 1.0, 2.0)),
+        ));
+        save.structures.push(SavedStructure {
+            kind: StructureKind::LeanTo,
+            position: nalgebra_glm::vec3(2045.0, 2046.0, 1.5),
+            remaining: Some(vec![(ItemKind::Stick, 3)]),
+            fuel: 0.0,
+        });
+        save.items
+            .push((nalgebra_glm::vec3(2049.0, 2049.0, 1.25), axe));
+        save.removed.push((ChunkCoord::new(128, -1), vec![0, 3, 4]));
+        save.rocks.push(nalgebra_glm::vec3(2044.0, 2043.0, 1.0));
+        save.trees.push((
+            nalgebra_glm::vec3(2042.0, 2043.0, 1.0),
+            nalgebra_glm::vec3(1.0, 1.0, 2.0),
+            2,
+        ));
+        let mut bush = BerryBush::new(0);
+        bush.regrow_timer = 0.125;
+        save.bushes.push((
+            nalgebra_glm::vec3(2041.0, 2043.0, 1.0),
+            nalgebra_glm::vec3(0.5, 0.5, 0.5),
+            bush,
+        ));
+        save.carcasses
+            .push((nalgebra_glm::vec3(2040.0, 2043.0, 1.0), AnimalKind::Deer));
+
+        let text = save.to_text();
+        let loaded = SaveGame::parse(&text).unwrap();
+        assert_eq!(loaded.to_text(), text);
+        assert_eq!(loaded.map.edges, MapEdges::Wrapping);
+        assert_eq!(loaded.terrain_version, 2);
+        assert_eq!(loaded.position, save.position);
+        assert_eq!(loaded.hands.slot(Hand::Right), Some(axe));
+        assert_eq!(loaded.crops, save.crops);
+        assert_eq!(loaded.items, save.items);
+        assert_eq!(loaded.removed, save.removed);
+        assert_eq!(loaded.bushes, save.bushes);
+        assert_eq!(loaded.version, SAVE_VERSION);
+    }
+
+    #[test]
+    fn older_saves_get_defaults_for_what_they_are_missing() {
+        // As written by version 1, before maps had edges or terrain versions
+        let text = "# Survival save file
+version 1
+map 16384 16 0.01 42 1
+time 100
+player 8193 8192 2.5
+vitals 0.5 0.25
+soil 8190 8191 1
+crop wheat 8190 8191
+";
+        let save = SaveGame::parse(text).unwrap();
+        assert_eq!(save.map.edges, MapEdges::Bounded);
+        assert_eq!(save.terrain_version, 1);
+        assert_eq!(save.ticks, 100);
+        assert_eq!(save.vitals.thirst, 0.25);
+        assert_eq!(save.vitals.energy, 1.0);
+        let (cell, soil) = save.soil[0];
+        assert_eq!(cell, nalgebra_glm::vec2(8190.0, 8191.0));
+        assert!(soil.tilled && soil.watered && !soil.planted);
+        assert_eq!(save.crops[0].stage, 0);
+        assert!(save.items.is_empty());
+        assert_eq!(save.version, 1);
+        assert!(save.removed.is_empty());
+    }
+
+    #[test]
+    fn bad_lines_are_reported_with_their_line_number() {
+        let newer = format!("version {}\n", SAVE_VERSION + 1);
+        let cases = [
+            (
+                newer.as_str(),
+                format!(
+                    "line 1: save is version {}, but only up to version {} can be loaded",
+                    SAVE_VERSION + 1,
+                    SAVE_VERSION
+                ),
+            ),
+            (
+                "version 2\n\n# Comments and blank lines count\ntime 5\n",
+                String::from("line 4: expected `map` before `time`"),
+            ),
+            ("time 5\n", String::from("line 1: expected `version` first")),
+            (
+                "version 2\nmap 16384 16 fine 42 1\n",
+                String::from("line 2: bad level of detail `fine`"),
+            ),
+            (
+                "version 2\nmap 16384 16 0.01 42 1 round\n",
+                String::from("line 2: unknown map edges `round`"),
+            ),
+            (
+                &format!("{}player 1 2\n", HEADER),
+                String::from("line 3: missing z"),
+            ),
+            (
+                &format!("{}time 5\nitem pebble 1 2 3\n", HEADER),
+                String::from("line 4: unknown item `pebble`"),
+            ),
+            (
+                &format!("{}removed 3 4 1 two\n", HEADER),
+                String::from("line 3: bad prop index `two`"),
+            ),
+            (
+                &format!("{}carcass wolf 1 2 3\n", HEADER),
+                String::from("line 3: unknown animal `wolf`"),
+            ),
+            (
+                &format!("{}structure firepit 1 2 3 stick:many\n", HEADER),
+                String::from("line 3: bad material count `many`"),
+            ),
+            (
+                "map 16384 16 0.01 42 1\n",
+                String::from("missing `version`"),
+            ),
+            ("version 2\n", String::from("missing `map`")),
+        ];
+        for (text, error) in cases {
+            assert_eq!(SaveGame::parse(text).err(), Some(error), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+/// Every kind of tool. A tool is used by clicking on something with it in the dominant hand.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToolKind {
+    Axe,
+    Knife,
+    Spear,
+    Hoe,
+    Hammer,
+}
+
+/// A tool, and how many more uses it has before it breaks. Put on tool items dropped in the world, and kept on them
+/// while they're held.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tool {
+    pub kind: ToolKind,
+    pub durability: usize,
+}
+
+/// The kinds of things that tools can be used on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToolTarget {
+    Tree,
+    Rock,
+    Carcass,
+    Animal,
+    Ground,
+}
+
+/// What happens when a tool is used on a target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToolAction {
+    Chop,
+    Break,
+    Butcher,
+    Stab,
+    Till,
+}
+
+/// Which tool does what to what. Anything not in here can't be done.
+const TOOL_ACTIONS: [(ToolKind, ToolTarget, ToolAction); 5] = [
+    (ToolKind::Axe, ToolTarget::Tree, ToolAction::Chop),
+    (ToolKind::Hammer, ToolTarget::Rock, ToolAction::Break),
+    (ToolKind::Knife, ToolTarget::Carcass, ToolAction::Butcher),
+    (ToolKind::Spear, ToolTarget::Animal, ToolAction::Stab),
+    (ToolKind::Hoe, ToolTarget::Ground, ToolAction::Till),
+];
+
+impl ToolKind {
+    /// How many times the tool can be used before it breaks.
+    pub fn max_durability(&self) -> usize {
+        match self {
+            ToolKind::Axe => 40,
+            ToolKind::Knife => 30,
+            ToolKind::Spear => 20,
+            ToolKind::Hoe => 50,
+            ToolKind::Hammer => 30,
+        }
+    }
+
+    /// How many ticks a swing of the tool takes. The action happens at the end of the swing.
+    pub fn swing_ticks(&self) -> usize {
+        match self {
+            ToolKind::Axe => 30,
+            ToolKind::Knife => 15,
+            ToolKind::Spear => 20,
+            ToolKind::Hoe => 25,
+            ToolKind::Hammer => 35,
+        }
+    }
+}
+
+impl Tool {
+    pub fn new(kind: ToolKind) -> Self {
+        Self {
+            kind,
+            durability: kind.max_durability(),
+        }
+    }
+
+    /// Wears the tool down by one use. Returns true if that broke it.
+    pub fn wear(&mut self) -> bool {
+        self.durability = self.durability.saturating_sub(1);
+        self.durability == 0
+    }
+
+    /// How much durability is left, in [0, 1].
+    pub fn condition(&self) -> f32 {
+        self.durability as f32 / self.kind.max_durability() as f32
+    }
+}
+
+impl ToolAction {
+    /// Verb shown on the action billboard, like "Chop" in "Chop Tree".
+    pub fn verb(&self) -> &'static str {
+        match self {
+            ToolAction::Chop => "Chop",
+            ToolAction::Break => "Break",
+            ToolAction::Butcher => "Butcher",
+            ToolAction::Stab => "Stab",
+            ToolAction::Till => "Till",
+        }
+    }
+}
+
+/// Looks up what using a tool on a target does, if anything.
+pub fn tool_action(tool: ToolKind, target: ToolTarget) -> Option<ToolAction> {
+    TOOL_ACTIONS
+        .iter()
+        .find(|(kind, tool_target, _)| *kind == tool && *tool_target == target)
+        .map(|(_, _, action)| *action)
+}
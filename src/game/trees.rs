@@ -0,0 +1,206 @@
+use hecs::{Entity, World};
+
+use crate::engine::{
+    bvh::{BVHNodeComponent, BVH},
+    render_core::{ModelComponent, RenderContext},
+};
+
+/// A fully grown tree. It takes a few chops with an axe to fell.
+pub struct Tree {
+    pub health: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TreeStage {
+    Stump,
+    Sapling,
+}
+
+/// A felled tree that is growing back. It starts as a stump, sprouts into a sapling, and then grows back into a tree
+/// the same size as the one that was cut down.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regrowth {
+    pub stage: TreeStage,
+    pub growth: f32,                    //< In-game days spent in the current stage
+    pub tree_scale: nalgebra_glm::Vec3, //< Scale of the tree that will grow back
+}
+
+const TREE_HEALTH: usize = 4;
+const STUMP_DAYS: f32 = 2.0;
+const SAPLING_DAYS: f32 = 3.0;
+
+impl Tree {
+    pub fn new() -> Self {
+        Self {
+            health: TREE_HEALTH,
+        }
+    }
+
+    /// Takes a chop out of the tree. Returns true if that felled it.
+    pub fn chop(&mut self) -> bool {
+        self.health = self.health.saturating_sub(1);
+        self.health == 0
+    }
+}
+
+impl TreeStage {
+    /// Identifier used to refer to the stage in data files, like save files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            TreeStage::Stump => "stump",
+            TreeStage::Sapling => "sapling",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<TreeStage> {
+        [TreeStage::Stump, TreeStage::Sapling]
+            .into_iter()
+            .find(|stage| stage.id() == id)
+    }
+
+    pub fn mesh_name(&self) -> &'static str {
+        match self {
+            TreeStage::Stump => "cube",
+            TreeStage::Sapling => "tree",
+        }
+    }
+
+    pub fn texture_name(&self) -> &'static str {
+        match self {
+            TreeStage::Stump => "log",
+            TreeStage::Sapling => "tree",
+        }
+    }
+
+    /// In-game days spent in the stage before moving on to the next one.
+    pub fn days(&self) -> f32 {
+        match self {
+            TreeStage::Stump => STUMP_DAYS,
+            TreeStage::Sapling => SAPLING_DAYS,
+        }
+    }
+
+    /// Size of the model at this stage, given the size of the full grown tree.
+    pub fn scale(&self, tree_scale: nalgebra_glm::Vec3) -> nalgebra_glm::Vec3 {
+        match self {
+            TreeStage::Stump => nalgebra_glm::vec3(0.15, 0.15, 0.1).component_mul(&tree_scale),
+            TreeStage::Sapling => tree_scale * 0.3,
+        }
+    }
+}
+
+impl Regrowth {
+    pub fn new(tree_scale: nalgebra_glm::Vec3) -> Self {
+        Self {
+            stage: TreeStage::Stump,
+            growth: 0.0,
+            tree_scale,
+        }
+    }
+
+    /// Grows by some in-game days. Returns true if it moved on to the next stage, or grew back into a tree if it
+    /// was already a sapling.
+    pub fn tick(&mut self, days: f32) -> bool {
+        self.growth += days;
+        if self.growth < self.stage.days() {
+            return false;
+        }
+        self.growth -= self.stage.days();
+        if self.stage == TreeStage::Stump {
+            self.stage = TreeStage::Sapling;
+        }
+        true
+    }
+}
+
+/// Spawns a full grown tree into the world at a position, along with its BVH leaf.
+pub fn spawn_tree(
+    position: nalgebra_glm::Vec3,
+    scale: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let mesh = renderer.get_mesh_id_from_name("tree").unwrap();
+    let texture = renderer.get_texture_id_from_name("tree").unwrap();
+    let entity = world.spawn((
+        ModelComponent::new(mesh, texture, position, scale),
+        Tree::new(),
+    ));
+    let node_id = bvh.insert(
+        entity,
+        renderer
+            .get_mesh_aabb(renderer.get_mesh_id_from_name("cube").unwrap())
+            .scale(scale)
+            .translate(position),
+    );
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+/// Spawns a regrowing tree into the world at a position, along with its BVH leaf. Felled trees start out as a fresh
+/// `Regrowth::new` stump.
+pub fn spawn_regrowth(
+    regrowth: Regrowth,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+    world: &mut World,
+    bvh: &mut BVH<Entity>,
+) -> Entity {
+    let model = regrowth_model(&regrowth, position, renderer);
+    let aabb = renderer.get_model_aabb(&model);
+    let entity = world.spawn((model, regrowth));
+    let node_id = bvh.insert(entity, aabb);
+    world
+        .insert_one(entity, BVHNodeComponent { node_id })
+        .unwrap();
+    entity
+}
+
+fn regrowth_model(
+    regrowth: &Regrowth,
+    position: nalgebra_glm::Vec3,
+    renderer: &RenderContext,
+) -> ModelComponent {
+    ModelComponent::new(
+        renderer
+            .get_mesh_id_from_name(regrowth.stage.mesh_name())
+            .unwrap(),
+        renderer
+            .get_texture_id_from_name(regrowth.stage.texture_name())
+            .unwrap(),
+        position,
+        regrowth.stage.scale(regrowth.tree_scale),
+    )
+}
+
+/// Grows stumps into saplings, and saplings back into trees.
+pub fn tree_system(world: &mut World, bvh: &mut BVH<Entity>, renderer: &RenderContext, days: f32) {
+    let mut grown = vec![];
+    for (entity, (regrowth, model, bvh_node)) in
+        world.query_mut::<(&mut Regrowth, &mut ModelComponent, &mut BVHNodeComponent)>()
+    {
+        let was_sapling = regrowth.stage == TreeStage::Sapling;
+        if !regrowth.tick(days) {
+            continue;
+        }
+        if was_sapling {
+            grown.push((entity, model.get_position(), regrowth.tree_scale));
+            continue;
+        }
+        // The BVH leaf is swapped out, since the sapling isn't the same shape as the stump
+        *model = regrowth_model(regrowth, model.get_position(), renderer);
+        bvh.remove(bvh_node.node_id);
+        bvh_node.node_id = bvh.insert(entity, renderer.get_model_aabb(model));
+    }
+
+    for (entity, position, scale) in grown {
+        if let Ok(bvh_node) = world.get::<&BVHNodeComponent>(entity) {
+            bvh.remove(bvh_node.node_id);
+        }
+        world.despawn(entity).unwrap();
+        spawn_tree(position, scale, renderer, world, bvh);
+    }
+}
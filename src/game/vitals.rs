@@ -0,0 +1,151 @@
+/// The survival stats of a creature. All of these are in [0, 1], where 1 is perfectly fine and 0 is deadly.
+#[derive(Clone, Debug)]
+pub struct Vitals {
+    pub hunger: f32,
+    pub thirst: f32,
+    pub energy: f32,
+    pub temperature: f32,
+    pub sick_ticks: usize, //< How much longer the creature is sick for. Sickness drains hunger and thirst faster
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Need {
+    Hunger,
+    Thirst,
+    Energy,
+    Temperature,
+}
+
+// All rates are per tick. There are about 60 ticks in a second.
+const HUNGER_RATE: f32 = 1.0 / (60.0 * 60.0 * 20.0);
+const THIRST_RATE: f32 = 1.0 / (60.0 * 60.0 * 12.0);
+const ENERGY_RATE: f32 = 1.0 / (60.0 * 60.0 * 8.0);
+const ENERGY_RECOVERY_RATE: f32 = 1.0 / (60.0 * 60.0 * 4.0);
+const COOLING_RATE: f32 = 1.0 / (60.0 * 60.0 * 10.0);
+const WARMING_RATE: f32 = 1.0 / (60.0 * 60.0 * 2.0);
+const EXERTION_MULTIPLIER: f32 = 2.0; //< How much faster hunger and thirst decay when at full exertion
+const SICKNESS_MULTIPLIER: f32 = 3.0; //< How much faster hunger and thirst decay when sick
+const SICK_TICKS: usize = 60 * 60 * 2;
+
+impl Vitals {
+    pub fn new() -> Self {
+        Self {
+            hunger: 1.0,
+            thirst: 1.0,
+            energy: 1.0,
+            temperature: 1.0,
+            sick_ticks: 0,
+        }
+    }
+
+    /// Decays the vitals by one tick.
+    /// - exertion: [0, 1], how hard the creature is working, ie walking speed over top walking speed
+    /// - warmth: [-1, 1], how warm the surroundings are. Negative cools the creature down, positive warms it up.
+    ///
+    /// Returns the need that ran out this tick, if any.
+    pub fn tick(&mut self, exertion: f32, warmth: f32) -> Option<Need> {
+        let exertion = exertion.clamp(0.0, 1.0);
+        let mut activity = 1.0 + exertion * (EXERTION_MULTIPLIER - 1.0);
+        if self.is_sick() {
+            self.sick_ticks -= 1;
+            activity *= SICKNESS_MULTIPLIER;
+        }
+
+        self.hunger -= HUNGER_RATE * activity;
+        self.thirst -= THIRST_RATE * activity;
+        if exertion > 0.0 {
+            self.energy -= ENERGY_RATE * exertion;
+        } else if !self.is_sick() {
+            self.energy += ENERGY_RECOVERY_RATE;
+        }
+        if warmth < 0.0 {
+            self.temperature += COOLING_RATE * warmth;
+        } else {
+            self.temperature += WARMING_RATE * warmth;
+        }
+
+        self.clamp();
+        self.depleted()
+    }
+
+    /// Adds (or removes, if amount is negative) some amount to a need.
+    pub fn restore(&mut self, need: Need, amount: f32) {
+        *self.get_mut(need) += amount;
+        self.clamp();
+    }
+
+    /// Makes the creature sick for a while. Getting sick again while already sick starts the sickness over.
+    pub fn make_sick(&mut self) {
+        self.sick_ticks = SICK_TICKS;
+    }
+
+    pub fn is_sick(&self) -> bool {
+        self.sick_ticks > 0
+    }
+
+    pub fn get(&self, need: Need) -> f32 {
+        match need {
+            Need::Hunger => self.hunger,
+            Need::Thirst => self.thirst,
+            Need::Energy => self.energy,
+            Need::Temperature => self.temperature,
+        }
+    }
+
+    pub fn get_mut(&mut self, need: Need) -> &mut f32 {
+        match need {
+            Need::Hunger => &mut self.hunger,
+            Need::Thirst => &mut self.thirst,
+            Need::Energy => &mut self.energy,
+            Need::Temperature => &mut self.temperature,
+        }
+    }
+
+    /// The need that is closest to running out, and its value.
+    pub fn most_pressing(&self) -> (Need, f32) {
+        Need::ALL
+            .iter()
+            .map(|need| (*need, self.get(*need)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap()
+    }
+
+    /// The first need that has completely run out, if any.
+    pub fn depleted(&self) -> Option<Need> {
+        Need::ALL
+            .iter()
+            .copied()
+            .find(|need| self.get(*need) <= 0.0)
+    }
+
+    fn clamp(&mut self) {
+        for need in Need::ALL {
+            let value = self.get_mut(need);
+            *value = value.clamp(0.0, 1.0);
+        }
+    }
+}
+
+impl Need {
+    pub const ALL: [Need; 4] = [Need::Hunger, Need::Thirst, Need::Energy, Need::Temperature];
+
+    /// What the player is feeling when this need is the most pressing.
+    pub fn feeling(&self) -> &'static str {
+        match self {
+            Need::Hunger => "Hunger",
+            Need::Thirst => "Thirst",
+            Need::Energy => "Energy",
+            Need::Temperature => "Warmth",
+        }
+    }
+
+    /// How the player died when this need ran out.
+    pub fn death_message(&self) -> &'static str {
+        match self {
+            Need::Hunger => "You starved",
+            Need::Thirst => "You died of thirst",
+            Need::Energy => "You collapsed from exhaustion",
+            Need::Temperature => "You froze to death",
+        }
+    }
+}
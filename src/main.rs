@@ -1,10 +1,13 @@
 mod engine;
+mod game;
 mod scenes;
 
 use std::cell::RefCell;
 
 use engine::app::*;
-use scenes::gameplay::Gameplay;
+use engine::chunked_map::MapEdges;
+use scenes::gameplay::{Gameplay, WorldOptions};
+use scenes::mesh_benchmark::MeshBenchmark;
 
 // TODO:
 // - [x] BVH terrain chunks
@@ -17,33 +20,231 @@ use scenes::gameplay::Gameplay;
 //     - [x] font cacheing
 //     - [x] hunger bar
 //         - [x] 9-slices
-// - [ ] wild berries that appear on bushes
-//     - [ ] two "hand" inventory menus, with the right-one being the dominant one
-//         - [ ] a button to swap between them
-//         - [ ] a small icon showing them in the hand inventory
-//         - [ ] can drop them with (q)
-//     - [ ] clicking and holding items puts them into your hand
-//         - [ ] when you hover over something, it highlights it with an action billboard that's like "grab X"
-//     - [ ] can eat them once they're in your hand
-// - [ ] can go up to a body of water, click, and drink
-// - [ ] wild vegetables/grains (potatoes/wheat). can plant them into tilled land, have them grow, pick them.
-// - [ ] wild animals
-//     - [ ] killing them drops meat and hide
+// - [x] wild berries that appear on bushes
+//     - [x] two "hand" inventory menus, with the right-one being the dominant one
+//         - [x] a button to swap between them
+//         - [x] a small icon showing them in the hand inventory
+//         - [x] can drop them with (q)
+//     - [x] clicking and holding items puts them into your hand
+//         - [x] when you hover over something, it highlights it with an action billboard that's like "grab X"
+//     - [x] can eat them once they're in your hand
+// - [x] can go up to a body of water, click, and drink
+// - [x] wild vegetables/grains (potatoes/wheat). can plant them into tilled land, have them grow, pick them.
+// - [x] wild animals
+//     - [x] killing them leaves a carcass, which can be butchered for meat and hide
 //
 // ## Crafting and building
-// - [ ] press (c) to craft using the two items in hand
-//     - [ ] (stone, stone) => knapping menu
-//     - [ ] (clay, X) => molding menu
-//     - [ ] (stone tool blank, stick) -> (stone tool, -)
-// - [ ] can use tools by clicking, with the tool in your dominant hand
-// - [ ] can press (b) to open up list of structures you can build, then you can place a blueprint. blueprints must be given the right materials, then they're built.
-//     - [ ] firepit
-//     - [ ] thatch lean-to
+// - [x] press (c) to craft using the two items in hand
+//     - [x] (stone, stone) => knapping menu
+//     - [x] (clay, X) => molding menu
+//     - [x] (stone tool blank, stick) -> (stone tool, -)
+// - [x] can use tools by clicking, with the tool in your dominant hand
+// - [x] can press (b) to open up list of structures you can build, then you can place a blueprint. blueprints must be given the right materials, then they're built.
+//     - [x] firepit
+//     - [x] thatch lean-to
+
+const USAGE: &str = "\
+Usage: survival [options]
+
+Options:
+    --seed <seed>          Seed of the world to start in, instead of a random one
+    --width <pixels>       Width of the window (default 800)
+    --height <pixels>      Height of the window (default 600)
+    --map-width <cells>    Width of the world, a multiple of the chunk size (default 16384)
+    --chunk-size <cells>   Width of a chunk of terrain (default 16)
+    --edges <edges>        What's past the edge of the world: `bounded` for sea, `wrapping` for the other side of
+                           the world, or `unbounded` for more terrain (default bounded). A wrapping world is at
+                           least 3 chunks wide, and chunks are only loaded up to halfway around it
+    --render-distance <n>  Chunks at most this many chunks away are drawn, far ones in less detail (default 8)
+    --unload-radius <n>    Chunks further than this many chunks away are unloaded, it's always past the render
+                           distance (default 10)
+    --prop-distance <n>    Chunks at most this many chunks away have trees, rocks, animals and everything else on
+                           them, it's never past the render distance (default 3)
+    --fullscreen           Fill the whole screen
+    --vsync <on|off>       Wait for the screen to refresh between frames (default on)
+    --benchmark-meshes     Compare how much VRAM and upload time terrain meshes take with and without shared
+                           vertices, instead of playing (seed 1 unless --seed is given)
+    --help                 Print this message
+
+Starting with --seed, --map-width, --chunk-size or --edges always starts a new world, instead of loading the save.
+That world isn't saved, so the save is kept as it is.";
 
 fn main() -> Result<(), String> {
-    run(
-        nalgebra_glm::I32Vec2::new(800, 600),
-        "Survival Prototype",
-        &|app| RefCell::new(Box::new(Gameplay::new(app))),
-    )
+    let (window_options, world_options, benchmark) = parse_args(std::env::args().skip(1))?;
+    if benchmark {
+        return run(window_options, &|_| {
+            RefCell::new(Box::new(MeshBenchmark::new(&world_options)))
+        });
+    }
+    run(window_options, &|app| {
+        RefCell::new(Box::new(Gameplay::new(app, &world_options)))
+    })
+}
+
+/// Reads the command line options, and whether to benchmark the terrain meshes instead of playing.
+fn parse_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(WindowOptions, WorldOptions, bool), String> {
+    let mut window_options = WindowOptions {
+        size: nalgebra_glm::I32Vec2::new(800, 600),
+        title: "Survival Prototype",
+        fullscreen: false,
+        vsync: true,
+    };
+    let mut world_options = WorldOptions::default();
+    let mut benchmark = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => world_options.seed = Some(parse_value(&arg, args.next())?),
+            "--width" => window_options.size.x = parse_value(&arg, args.next())?,
+            "--height" => window_options.size.y = parse_value(&arg, args.next())?,
+            "--map-width" => world_options.map_width = parse_value(&arg, args.next())?,
+            "--chunk-size" => world_options.chunk_width = parse_value(&arg, args.next())?,
+            "--render-distance" => world_options.render_distance = parse_value(&arg, args.next())?,
+            "--unload-radius" => world_options.unload_radius = parse_value(&arg, args.next())?,
+            "--prop-distance" => world_options.prop_distance = parse_value(&arg, args.next())?,
+            "--edges" => {
+                let id = args.next().unwrap_or_default();
+                world_options.edges = MapEdges::from_id(&id).ok_or_else(|| {
+                    String::from("--edges expects `bounded`, `wrapping` or `unbounded`")
+                })?;
+            }
+            "--fullscreen" => window_options.fullscreen = true,
+            "--vsync" => {
+                window_options.vsync = match args.next().as_deref() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err(String::from("--vsync expects `on` or `off`")),
+                }
+            }
+            "--benchmark-meshes" => benchmark = true,
+            "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => return Err(format!("unknown option `{}`\n\n{}", arg, USAGE)),
+        }
+    }
+
+    if window_options.size.x <= 0 || window_options.size.y <= 0 {
+        return Err(String::from("the window size must be positive"));
+    }
+    if world_options.chunk_width == 0 || world_options.map_width % world_options.chunk_width != 0 {
+        return Err(format!(
+            "the map width ({}) must be a multiple of the chunk size ({})",
+            world_options.map_width, world_options.chunk_width
+        ));
+    }
+    if world_options.edges == MapEdges::Wrapping
+        && world_options.map_width / world_options.chunk_width < 3
+    {
+        return Err(String::from(
+            "a wrapping map must be at least 3 chunks wide",
+        ));
+    }
+    Ok((window_options, world_options, benchmark))
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} expects a value", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("bad value `{}` for {}", value, option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(WindowOptions, WorldOptions, bool), String> {
+        parse_args(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn no_options_gives_the_defaults() {
+        let (window_options, world_options, benchmark) = parse(&[]).unwrap();
+        assert_eq!(window_options.size, nalgebra_glm::I32Vec2::new(800, 600));
+        assert!(!window_options.fullscreen);
+        assert!(window_options.vsync);
+        assert_eq!(world_options, WorldOptions::default());
+        assert!(!benchmark);
+    }
+
+    #[test]
+    fn options_are_read() {
+        let (window_options, world_options, benchmark) = parse(&[
+            "--seed",
+            "-42",
+            "--width",
+            "1024",
+            "--height",
+            "768",
+            "--map-width",
+            "4096",
+            "--chunk-size",
+            "32",
+            "--edges",
+            "wrapping",
+            "--render-distance",
+            "4",
+            "--unload-radius",
+            "6",
+            "--prop-distance",
+            "2",
+            "--fullscreen",
+            "--vsync",
+            "off",
+            "--benchmark-meshes",
+        ])
+        .unwrap();
+        assert_eq!(window_options.size, nalgebra_glm::I32Vec2::new(1024, 768));
+        assert!(window_options.fullscreen);
+        assert!(!window_options.vsync);
+        assert_eq!(
+            world_options,
+            WorldOptions {
+                seed: Some(-42),
+                map_width: 4096,
+                chunk_width: 32,
+                edges: MapEdges::Wrapping,
+                render_distance: 4,
+                unload_radius: 6,
+                prop_distance: 2,
+            }
+        );
+        assert!(benchmark);
+    }
+
+    #[test]
+    fn bad_options_are_reported() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+        assert!(error(&["--fly"]).starts_with("unknown option `--fly`"));
+        assert_eq!(error(&["--seed"]), "--seed expects a value");
+        assert_eq!(error(&["--width", "wide"]), "bad value `wide` for --width");
+        assert_eq!(
+            error(&["--height", "0"]),
+            "the window size must be positive"
+        );
+        assert_eq!(
+            error(&["--edges", "round"]),
+            "--edges expects `bounded`, `wrapping` or `unbounded`"
+        );
+        assert_eq!(
+            error(&["--vsync", "maybe"]),
+            "--vsync expects `on` or `off`"
+        );
+        assert_eq!(
+            error(&["--map-width", "1000", "--chunk-size", "16"]),
+            "the map width (1000) must be a multiple of the chunk size (16)"
+        );
+        assert_eq!(
+            error(&["--chunk-size", "0"]),
+            "the map width (16384) must be a multiple of the chunk size (0)"
+        );
+        assert_eq!(
+            error(&["--map-width", "32", "--edges", "wrapping"]),
+            "a wrapping map must be at least 3 chunks wide"
+        );
+    }
 }
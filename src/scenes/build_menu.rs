@@ -0,0 +1,113 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    engine::{rectangle::Rectangle, render2d::NineSlice},
+    game::building::{describe_materials, StructureKind},
+    App, Scene, SceneCommand,
+};
+
+const PADDING: f32 = 16.0;
+const TITLE_HEIGHT: f32 = 20.0;
+const ROW_WIDTH: f32 = 360.0;
+const ROW_HEIGHT: f32 = 32.0;
+
+/// Menu listing the structures that can be built. Opened over the gameplay scene with (b). The chosen structure is
+/// written to `choice` for the gameplay scene to start placing its blueprint.
+pub struct BuildMenu {
+    choice: Rc<RefCell<Option<StructureKind>>>,
+}
+
+impl Scene for BuildMenu {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        if !app.mouse_left_clicked {
+            return SceneCommand::None;
+        }
+        let mouse = nalgebra_glm::vec2(app.mouse_x as f32, app.mouse_y as f32);
+        let panel = panel_rect(app);
+
+        for (i, kind) in StructureKind::ALL.iter().enumerate() {
+            if row_rect(panel, i).contains(mouse) {
+                *self.choice.borrow_mut() = Some(*kind);
+                return SceneCommand::Pop;
+            }
+        }
+        if row_rect(panel, StructureKind::ALL.len()).contains(mouse) {
+            return SceneCommand::Pop;
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        let mouse = nalgebra_glm::vec2(app.mouse_x as f32, app.mouse_y as f32);
+        let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+        let panel = panel_rect(app);
+        let font = app.renderer.get_font_id_from_name("font").unwrap();
+        app.renderer.set_font(font);
+
+        // Darken the game behind the menu
+        app.renderer
+            .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.5));
+        app.renderer
+            .fill_rect(Rectangle::new(0.0, 0.0, screen_size.x, screen_size.y));
+        app.renderer.render_nine_slice(
+            NineSlice {
+                texture: app
+                    .renderer
+                    .get_texture_id_from_name("nine-slice-test")
+                    .unwrap(),
+                border: 8.0,
+            },
+            panel,
+        );
+        app.renderer
+            .draw_text(panel.pos + nalgebra_glm::vec2(PADDING, PADDING), "Build");
+
+        // One row per structure, and a cancel row at the bottom
+        let rows = StructureKind::ALL
+            .iter()
+            .map(|kind| (kind.name(), describe_materials(kind.materials())))
+            .chain(std::iter::once(("Cancel", String::new())));
+        for (i, (name, materials)) in rows.enumerate() {
+            let row = row_rect(panel, i);
+            let alpha = if row.contains(mouse) { 0.8 } else { 0.5 };
+            app.renderer
+                .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, alpha));
+            app.renderer.fill_rect(row);
+
+            let text_y = row.pos.y + (ROW_HEIGHT - app.renderer.measure_text(name).y) * 0.5;
+            app.renderer
+                .draw_text(nalgebra_glm::vec2(row.pos.x + 8.0, text_y), name);
+            let materials_width = app.renderer.measure_text(&materials).x;
+            app.renderer.draw_text(
+                nalgebra_glm::vec2(row.pos.x + ROW_WIDTH - 8.0 - materials_width, text_y),
+                &materials,
+            );
+        }
+    }
+}
+
+impl BuildMenu {
+    pub fn new(choice: Rc<RefCell<Option<StructureKind>>>) -> Self {
+        Self { choice }
+    }
+}
+
+fn panel_rect(app: &App) -> Rectangle {
+    let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+    let rows = StructureKind::ALL.len() + 1;
+    let panel_size = nalgebra_glm::vec2(
+        ROW_WIDTH + 2.0 * PADDING,
+        TITLE_HEIGHT + 3.0 * PADDING + rows as f32 * (ROW_HEIGHT + PADDING) - PADDING,
+    );
+    let panel_pos = (screen_size - panel_size) * 0.5;
+    Rectangle::new(panel_pos.x, panel_pos.y, panel_size.x, panel_size.y)
+}
+
+fn row_rect(panel: Rectangle, i: usize) -> Rectangle {
+    Rectangle::new(
+        panel.pos.x + PADDING,
+        panel.pos.y + 2.0 * PADDING + TITLE_HEIGHT + i as f32 * (ROW_HEIGHT + PADDING),
+        ROW_WIDTH,
+        ROW_HEIGHT,
+    )
+}
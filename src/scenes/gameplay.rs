@@ -1,35 +1,97 @@
 use core::f32;
+use std::{cell::RefCell, rc::Rc};
 
 use hecs::{Entity, World};
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use sdl2::keyboard::Scancode;
 
 use crate::{
     engine::{
-        bvh::{BVHNodeId, BVH},
+        audio::AudioManager,
+        bvh::{BVHNodeComponent, BVHNodeId, BVH},
         camera::{Camera, ProjectionKind},
-        chunked_map::ChunkedPerlinMap,
+        chunked_map::{
+            ChunkedPerlinMap, MapEdges, Soil, DEFAULT_PROP_DISTANCE, DEFAULT_RENDER_DISTANCE,
+            DEFAULT_UNLOAD_RADIUS, TERRAIN_VERSION, WATER_LEVEL,
+        },
         perlin::HeightMap,
         ray::Ray,
         rectangle::Rectangle,
         render2d::NineSlice,
         render_core::ModelComponent,
         shadow_map::DirectionalLightSource,
+        sphere::Sphere,
     },
-    App, Scene,
+    game::{
+        animals::{animal_system, Animal, Carcass},
+        building::{
+            complete_structure, spawn_structure, Blueprint, Structure, StructureKind,
+            BLUEPRINT_TINT,
+        },
+        cooking::{cooking_system, free_cooking_slot, Cooking, COOKING_SLOTS},
+        crafting::{CraftingMenu, RecipeOutput, Recipes},
+        farming::{crop_system, spawn_crop, Crop, CropKind},
+        fire::{fire_system, Fire, WARMTH_RADIUS},
+        inventory::{Hand, Hands},
+        items::{spawn_item, Item, ItemKind, Pickable},
+        knapping::KnappingOutcome,
+        molding::MoldingOutcome,
+        plants::{berry_bush_system, BerryBush},
+        props::Prop,
+        save::{SaveGame, REMOVED_PROPS_VERSION},
+        tools::{tool_action, Tool, ToolAction, ToolTarget},
+        trees::{spawn_regrowth, tree_system, Regrowth, Tree},
+        vitals::{Need, Vitals},
+    },
+    App, Scene, SceneCommand,
 };
 
+use super::{build_menu::BuildMenu, knapping::Knapping, molding::Molding};
+
 const MAP_WIDTH: usize = 16384; // 16k is desireable!
 const CHUNK_SIZE: usize = 16;
 const UNIT_PER_METER: f32 = 0.05;
 const MINUTES_PER_DAY: f32 = 10.0;
 const TICKS_OFFSET: f32 = 0.0;
+const TICKS_PER_DAY: f32 = 2.0 * f32::consts::PI * 60.0 * 60.0 * 0.5 * MINUTES_PER_DAY;
+pub(crate) const REACH: f32 = 3.0; //< How far away the player can interact with things
+const GRAB_TICKS: usize = 20; //< How long the mouse must be held down on something to grab it
+const FIST_DAMAGE: usize = 1;
+const SPEAR_DAMAGE: usize = 3;
+const SWING_HEIGHT: f32 = 12.0; //< How far up the held tool's icon moves at the top of a swing
+pub(crate) const WALK_SPEED: f32 = 1.6 * 2.5 * 4.317 * UNIT_PER_METER / 62.5;
+pub(crate) const FRICTION: f32 = 0.8;
+const RESPAWN_TICKS: usize = 60 * 5;
+const DROP_DISTANCE: f32 = 0.5; //< How far in front of the player dropped items land
+const MAX_RAY_DISTANCE: f32 = 50.0; //< How far the mouse ray looks for terrain
+const GHOST_TINT: nalgebra_glm::Vec4 = nalgebra_glm::Vec4::new(1.0, 1.0, 1.0, 0.5);
+const BLOCKED_GHOST_TINT: nalgebra_glm::Vec4 = nalgebra_glm::Vec4::new(1.0, 0.3, 0.3, 0.5);
+const SAVE_FILENAME: &str = "save.txt";
+const BAD_SAVE_FILENAME: &str = "save.txt.bad"; //< Where a save that couldn't be loaded is moved, so it isn't saved over
+const AUTOSAVE_TICKS: usize = 60 * 60 * 5; //< How often the game is saved on its own
+const AMBIENT_TICKS: usize = 60 * 8; //< How often the biome's ambient sound might play
+const AMBIENT_CHANCE: f64 = 0.5;
+const AMBIENT_VOLUME: i32 = 24;
+const MESSAGE_TICKS: usize = 60 * 5; //< How long messages stay on the screen
 
 pub const QUAD_DATA: &[u8] = include_bytes!("../../res/quad.obj");
 pub const QUAD_XY_DATA: &[u8] = include_bytes!("../../res/quad-xy.obj");
 pub const CUBE_DATA: &[u8] = include_bytes!("../../res/cube.obj");
 pub const CONE_DATA: &[u8] = include_bytes!("../../res/cone.obj");
 pub const BUSH_DATA: &[u8] = include_bytes!("../../res/bush.obj");
+pub const MOB_DATA: &[u8] = include_bytes!("../../res/mob.obj");
+
+/// The world to start the game in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorldOptions {
+    pub seed: Option<i32>, //< A random seed is picked if there isn't one
+    pub map_width: usize,
+    pub chunk_width: usize,
+    pub edges: MapEdges,
+    pub render_distance: usize, //< How many chunks away from the player chunks are drawn
+    pub unload_radius: usize,   //< How many chunks away from the player chunks are unloaded
+    pub prop_distance: usize, //< How many chunks away from the player chunks have props and animals
+}
 
 struct Player {
     bvh_node_id: BVHNodeId,
@@ -37,43 +99,139 @@ struct Player {
 
 pub struct Rock {}
 
+/// The sea's surface.
+struct Water {}
+
+/// A structure's ghost model that follows the mouse, until it's placed as a blueprint.
+struct Placement {
+    kind: StructureKind,
+    ghost: Entity,
+}
+
+/// Something the player can do to the terrain cell under the mouse.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum GroundAction {
+    Drink,
+    Till,
+    Water,
+    Plant(CropKind),
+}
+
+enum PlayerState {
+    Alive,
+    Dead { cause: Need, ticks_left: usize },
+}
+
 pub struct Gameplay {
     world: World,
     camera_3d: Camera,
     directional_light: DirectionalLightSource,
     map: ChunkedPerlinMap,
     bvh: BVH<Entity>,
+    audio: AudioManager,
+    recipes: Recipes,
 
     // Player stuff
     position: nalgebra_glm::Vec3,
     velocity: nalgebra_glm::Vec3,
+    facing: nalgebra_glm::Vec2, //< Which way the player last walked, always normalized
+    spawn_point: nalgebra_glm::Vec3,
+    player_state: PlayerState,
 
     prev_space_state: bool,
     debug: bool,
 
+    // Interaction stuff
+    hovered: Option<Entity>,
+    hovered_ground: Option<(nalgebra_glm::Vec3, GroundAction)>,
+    grab_ticks: usize,
+    knapping_outcome: Option<Rc<RefCell<Option<KnappingOutcome>>>>, //< Set while the knapping menu is open
+    molding_outcome: Option<Rc<RefCell<Option<MoldingOutcome>>>>, //< Set while the molding menu is open
+    build_choice: Option<Rc<RefCell<Option<StructureKind>>>>, //< Set while the build menu is open
+    placing: Option<Placement>,
+
+    ticks: usize, //< In-game time, which carries over between saves
+    save_filename: Option<&'static str>, //< Worlds picked on the command line aren't saved, so they don't replace the save
+    message: Option<(String, usize)>, //< Shown at the bottom of the screen, with how many more ticks it's shown for
     update_swap: u32,
 }
 
 impl Scene for Gameplay {
-    fn update(&mut self, app: &App) {
-        self.directional_light.light_dir.z =
-            (app.ticks as f32 / (60.0 * 60.0 * 0.5 * MINUTES_PER_DAY) + TICKS_OFFSET).cos();
-        self.directional_light.light_dir.y =
-            (app.ticks as f32 / (60.0 * 60.0 * 0.5 * MINUTES_PER_DAY) + TICKS_OFFSET).sin();
+    fn update(&mut self, app: &App) -> SceneCommand {
+        self.finish_knapping();
+        self.finish_molding();
+        self.finish_build_menu(app);
+        self.directional_light.light_dir.z = day_angle(self.ticks).cos();
+        self.directional_light.light_dir.y = day_angle(self.ticks).sin();
         self.map.check_chunks(
             &app.renderer,
             self.position.xy(),
+            self.camera_3d.position(),
+            &mut self.world,
+            &mut self.bvh,
+        );
+        self.move_water(app);
+        self.play_ambient_sound();
+        berry_bush_system(&mut self.world, &app.renderer, 1.0 / TICKS_PER_DAY);
+        crop_system(
+            &mut self.world,
+            &mut self.map,
+            &app.renderer,
+            1.0 / TICKS_PER_DAY,
+        );
+        fire_system(&mut self.world, 1.0 / TICKS_PER_DAY, self.ticks);
+        cooking_system(&mut self.world, &app.renderer, 1.0 / TICKS_PER_DAY);
+        tree_system(
+            &mut self.world,
+            &mut self.bvh,
+            &app.renderer,
+            1.0 / TICKS_PER_DAY,
+        );
+        animal_system(
             &mut self.world,
+            &self.map,
             &mut self.bvh,
+            &app.renderer,
+            self.position,
         );
         self.update_view(app);
-        self.update_clickers(app);
+        let mut command = SceneCommand::None;
+        match self.player_state {
+            PlayerState::Alive => {
+                if app.key_pressed(Scancode::C) {
+                    command = self.craft();
+                } else if app.key_pressed(Scancode::B) {
+                    command = self.open_build_menu();
+                } else if app.key_pressed(Scancode::F5) {
+                    self.save();
+                }
+                self.update_hands(app);
+                if self.placing.is_some() {
+                    self.update_placement(app);
+                } else {
+                    self.update_clickers(app);
+                }
+                self.update_vitals();
+            }
+            PlayerState::Dead { .. } => self.update_death(app),
+        }
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(AUTOSAVE_TICKS) {
+            self.save();
+        }
+        if let Some((_, ticks_left)) = &mut self.message {
+            *ticks_left = ticks_left.saturating_sub(1);
+            if *ticks_left == 0 {
+                self.message = None;
+            }
+        }
         self.update_swap += 1;
+        command
     }
 
     fn render(&mut self, app: &App) {
         // sky system
-        let model_t = app.ticks as f32 / (60.0 * 60.0 * 0.5 * MINUTES_PER_DAY) + TICKS_OFFSET;
+        let model_t = day_angle(self.ticks);
         unsafe {
             let day_color = nalgebra_glm::vec3(172.0, 205.0, 248.0);
             let night_color = nalgebra_glm::vec3(5.0, 6.0, 7.0);
@@ -107,61 +265,98 @@ impl Scene for Gameplay {
 
         let font = app.renderer.get_font_id_from_name("font").unwrap();
         app.renderer.set_font(font);
-        app.renderer
-            .draw_text(nalgebra_glm::vec2(10.0, 10.0), "Hunger");
+        self.render_feeling_bar(app);
 
-        let nine_slice = NineSlice {
-            texture: app
-                .renderer
-                .get_texture_id_from_name("nine-slice-test")
-                .unwrap(),
-            border: 8.0,
-        };
-        let nine_slice2 = NineSlice {
-            texture: app
-                .renderer
-                .get_texture_id_from_name("nine-slice-test2")
-                .unwrap(),
-            border: 8.0,
-        };
-        app.renderer
-            .render_nine_slice(nine_slice, Rectangle::new(70.0, 10.0, 164.0, 16.0));
-        app.renderer.render_nine_slice(
-            nine_slice2,
-            Rectangle::new(
-                70.0,
-                10.0,
-                (app.ticks as f32 * 0.01).cos().abs() * 100.0 + 16.0,
-                16.0,
-            ),
-        );
+        self.render_action_billboard(app);
+        self.render_hands(app);
+        self.render_message(app);
+        self.render_death_screen(app);
+    }
+}
 
-        // Draw the two hand inventory slots
-        const SLOT_SIZE: f32 = 96.0;
-        app.renderer
-            .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.5));
-        app.renderer.fill_rect(Rectangle::new(
-            app.window_size.x as f32 * 0.5 - SLOT_SIZE * 1.5,
-            app.window_size.y as f32 - SLOT_SIZE,
-            SLOT_SIZE,
-            SLOT_SIZE,
-        ));
-        app.renderer.fill_rect(Rectangle::new(
-            app.window_size.x as f32 * 0.5 + SLOT_SIZE * 0.5,
-            app.window_size.y as f32 - SLOT_SIZE,
-            SLOT_SIZE,
-            SLOT_SIZE,
-        ));
+impl Default for WorldOptions {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            map_width: MAP_WIDTH,
+            chunk_width: CHUNK_SIZE,
+            edges: MapEdges::Bounded,
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            unload_radius: DEFAULT_UNLOAD_RADIUS,
+            prop_distance: DEFAULT_PROP_DISTANCE,
+        }
+    }
+}
+
+impl WorldOptions {
+    /// Whether the options ask for a particular world, instead of whichever one is saved.
+    fn picks_world(&self) -> bool {
+        let default = WorldOptions::default();
+        self.seed.is_some()
+            || self.map_width != default.map_width
+            || self.chunk_width != default.chunk_width
+            || self.edges != default.edges
     }
 }
 
 impl Gameplay {
-    pub fn new(app: &App) -> Self {
+    /// Starts the game from the save file if there is one, or in a new world if there isn't. Asking for a world
+    /// other than the default one always starts a new world, which isn't saved.
+    pub fn new(app: &App, options: &WorldOptions) -> Self {
+        let mut load_error = None;
+        let save = if options.picks_world() {
+            None
+        } else if std::path::Path::new(SAVE_FILENAME).exists() {
+            SaveGame::from_file(SAVE_FILENAME)
+                .map_err(|err| load_error = Some(err))
+                .ok()
+        } else {
+            None
+        };
+        // A save that couldn't be loaded is moved aside, so that saving the new world doesn't replace it. If it can't
+        // be moved, the new world isn't saved at all
+        let mut saving = !options.picks_world();
+        let load_message = load_error.map(|err| {
+            let moved = std::fs::rename(SAVE_FILENAME, BAD_SAVE_FILENAME);
+            match moved {
+                Ok(()) => format!(
+                    "Starting a new world, {}. The save was moved to {}",
+                    err, BAD_SAVE_FILENAME
+                ),
+                Err(move_err) => {
+                    saving = false;
+                    format!(
+                        "Not saving the new world, {}, and it couldn't be moved aside: {}",
+                        err, move_err
+                    )
+                }
+            }
+        });
+        let terrain_changed = save
+            .as_ref()
+            .is_some_and(|save| save.terrain_version != TERRAIN_VERSION);
+        let save = save.map(|save| {
+            if terrain_changed {
+                save.without_world_changes()
+            } else {
+                save
+            }
+        });
+
         let mut world = World::new();
 
         let mut rng = rand::rngs::StdRng::from_entropy();
-        let mut map =
-            ChunkedPerlinMap::new(MAP_WIDTH, CHUNK_SIZE, 0.01, rand::Rng::gen(&mut rng), 1.0);
+        let mut map = match &save {
+            Some(save) => ChunkedPerlinMap::from_params(save.map),
+            None => ChunkedPerlinMap::new(
+                options.map_width,
+                options.chunk_width,
+                0.01,
+                options.seed.unwrap_or_else(|| rng.gen()),
+                1.0,
+                options.edges,
+            ),
+        };
 
         // Setup the mesh manager
         let quad_mesh = app.renderer.add_mesh_from_obj(QUAD_DATA, Some("quad"));
@@ -170,6 +365,7 @@ impl Gameplay {
         let cube_mesh = app.renderer.add_mesh_from_obj(CUBE_DATA, Some("cube"));
         app.renderer.add_mesh_from_obj(CONE_DATA, Some("tree"));
         app.renderer.add_mesh_from_obj(BUSH_DATA, Some("bush"));
+        app.renderer.add_mesh_from_obj(MOB_DATA, Some("mob"));
 
         // Setup the texture manager
         let grass_texture = app
@@ -180,6 +376,37 @@ impl Gameplay {
             .add_texture_from_png("water.png", Some("water"));
         app.renderer.add_texture_from_png("tree.png", Some("tree"));
         app.renderer.add_texture_from_png("rock.png", Some("rock"));
+        app.renderer
+            .add_texture_from_png("berry-bush.png", Some("berry-bush"));
+        app.renderer
+            .add_texture_from_png("berry.png", Some("berry"));
+        app.renderer
+            .add_texture_from_png("potato.png", Some("potato"));
+        app.renderer
+            .add_texture_from_png("wheat.png", Some("wheat"));
+        app.renderer
+            .add_texture_from_png("rabbit.png", Some("rabbit"));
+        app.renderer.add_texture_from_png("deer.png", Some("deer"));
+        app.renderer.add_texture_from_png("meat.png", Some("meat"));
+        app.renderer
+            .add_texture_from_png("cooked-meat.png", Some("cooked-meat"));
+        app.renderer
+            .add_texture_from_png("baked-potato.png", Some("baked-potato"));
+        app.renderer
+            .add_texture_from_png("burnt-food.png", Some("burnt-food"));
+        app.renderer.add_texture_from_png("hide.png", Some("hide"));
+        app.renderer
+            .add_texture_from_png("thatch.png", Some("thatch"));
+        app.renderer
+            .add_texture_from_png("firepit.png", Some("firepit"));
+        app.renderer
+            .add_texture_from_png("stick.png", Some("stick"));
+        app.renderer.add_texture_from_png("log.png", Some("log"));
+        app.renderer.add_texture_from_png("clay.png", Some("clay"));
+        app.renderer
+            .add_texture_from_png("tool-blank.png", Some("tool-blank"));
+        app.renderer
+            .add_texture_from_png("stone-tool.png", Some("stone-tool"));
         app.renderer
             .add_texture_from_png("nine-slice-test.png", Some("nine-slice-test"));
         app.renderer
@@ -191,14 +418,34 @@ impl Gameplay {
 
         let mut bvh = BVH::<Entity>::new();
 
-        let spawn_point =
-            nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0 + 1.0, MAP_WIDTH as f32 / 2.0, 2.5);
-        loop {
-            if map.chunkless_height(spawn_point.xy()) > 0.74 {
-                break;
+        let spawn_point = match &save {
+            Some(save) => save.spawn_point,
+            None => {
+                let spawn_point = nalgebra_glm::vec3(
+                    options.map_width as f32 / 2.0 + 1.0,
+                    options.map_width as f32 / 2.0,
+                    2.5,
+                );
+                // Random worlds are picked again until the player spawns on land. A chosen seed is kept as it is, so
+                // that the same seed always makes the same world
+                while options.seed.is_none() && map.chunkless_height(spawn_point.xy()) <= 0.74 {
+                    map = ChunkedPerlinMap::new(
+                        options.map_width,
+                        options.chunk_width,
+                        0.01,
+                        rng.gen(),
+                        1.0,
+                        options.edges,
+                    );
+                }
+                spawn_point
             }
-            map = ChunkedPerlinMap::new(MAP_WIDTH, CHUNK_SIZE, 0.01, rand::Rng::gen(&mut rng), 1.0);
-        }
+        };
+        map.set_render_distance(options.render_distance);
+        map.set_unload_radius(options.unload_radius);
+        map.set_prop_distance(options.prop_distance);
+        let map_width = map.params().map_width as f32;
+        let map_seed = map.params().seed;
 
         // Add player
         let scale_vec = nalgebra_glm::vec3(0.2, 0.2, 1.0);
@@ -218,42 +465,54 @@ impl Gameplay {
         world
             .insert(
                 player_entity,
-                (Player {
-                    bvh_node_id: player_node_id,
-                },),
+                (
+                    Player {
+                        bvh_node_id: player_node_id,
+                    },
+                    Hands::new(),
+                    Vitals::new(),
+                ),
             )
             .unwrap();
 
         // Add water plane
-        let scale_vec = nalgebra_glm::vec3(MAP_WIDTH as f32, MAP_WIDTH as f32, MAP_WIDTH as f32);
-        let water_entity = world.spawn((ModelComponent::new(
-            quad_mesh,
-            water_texture,
-            nalgebra_glm::vec3(0.0, 0.0, 0.5),
-            scale_vec,
-        ),));
-        bvh.insert(
+        let scale_vec = nalgebra_glm::vec3(map_width, map_width, map_width);
+        let water_entity = world.spawn((
+            ModelComponent::new(
+                quad_mesh,
+                water_texture,
+                nalgebra_glm::vec3(0.0, 0.0, WATER_LEVEL),
+                scale_vec,
+            ),
+            Water {},
+        ));
+        let node_id = bvh.insert(
             water_entity,
             app.renderer
                 .get_mesh_aabb(quad_mesh)
                 .scale(scale_vec)
-                .translate(nalgebra_glm::vec3(0.0, 0.0, 0.5)),
+                .translate(nalgebra_glm::vec3(0.0, 0.0, WATER_LEVEL)),
         );
+        world
+            .insert_one(water_entity, BVHNodeComponent { node_id })
+            .unwrap();
 
-        Self {
+        let mut gameplay = Self {
             world,
             camera_3d: Camera::new(
                 spawn_point,
-                nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0, MAP_WIDTH as f32 / 2.0, 0.5),
+                nalgebra_glm::vec3(map_width / 2.0, map_width / 2.0, 0.5),
                 nalgebra_glm::vec3(0.0, 0.0, 1.0),
                 ProjectionKind::Perspective { fov: 0.65 },
             ),
             bvh,
             map,
+            audio: AudioManager::new(),
+            recipes: Recipes::from_file("res/recipes.txt"),
             directional_light: DirectionalLightSource::new(
                 Camera::new(
-                    nalgebra_glm::vec3(MAP_WIDTH as f32 / -2.0, 0.0, 2.0),
-                    nalgebra_glm::vec3(MAP_WIDTH as f32 / 2.0, MAP_WIDTH as f32 / 2.0, 0.5),
+                    nalgebra_glm::vec3(map_width / -2.0, 0.0, 2.0),
+                    nalgebra_glm::vec3(map_width / 2.0, map_width / 2.0, 0.5),
                     nalgebra_glm::vec3(0.0, 0.0, 1.0),
                     ProjectionKind::Orthographic {
                         // These do not matter for now, they're reset later
@@ -266,15 +525,142 @@ impl Gameplay {
                     },
                 ),
                 nalgebra_glm::vec3(-0.1, 0.0, 0.86),
-                MAP_WIDTH as i32,
+                map_width as i32,
             ),
 
             position: spawn_point,
             velocity: nalgebra_glm::vec3(0.0, 0.0, 0.0),
+            // Away from the camera
+            facing: nalgebra_glm::vec2(-1.0, 0.0),
+            spawn_point,
+            player_state: PlayerState::Alive,
 
             prev_space_state: false,
             debug: false,
+
+            hovered: None,
+            hovered_ground: None,
+            grab_ticks: 0,
+            knapping_outcome: None,
+            molding_outcome: None,
+            build_choice: None,
+            placing: None,
+            ticks: 0,
+            save_filename: saving.then_some(SAVE_FILENAME),
+            message: None,
             update_swap: 0,
+        };
+        gameplay.show_message(match load_message {
+            Some(message) => message,
+            None if save.is_none() => format!("Starting a new world with seed {}", map_seed),
+            None if terrain_changed => format!(
+                "Loaded {}, but the terrain has changed since, so the world was started over",
+                SAVE_FILENAME
+            ),
+            None => format!("Loaded {}", SAVE_FILENAME),
+        });
+        if let Some(save) = save {
+            gameplay.load(save);
+        }
+        gameplay
+    }
+
+    /// Puts the player and everything they changed about the world back the way a save has it. The changes to the
+    /// world are stored in the map, and put back as the chunks they're in get their props.
+    fn load(&mut self, save: SaveGame) {
+        self.ticks = save.ticks;
+        self.position = save.position;
+        let player_entt = self.player_entity();
+        *self.world.get::<&mut Vitals>(player_entt).unwrap() = save.vitals.clone();
+        *self.world.get::<&mut Hands>(player_entt).unwrap() = save.hands.clone();
+
+        self.map.restore_changes(save.map_changes());
+        if save.version < REMOVED_PROPS_VERSION {
+            // Older saves don't say which props were taken, only what replaced some of them: the stumps of felled
+            // trees, and crops planted over wild ones
+            for (position, _) in &save.regrowth {
+                self.map.remove_generated(position.xy(), |prop| {
+                    matches!(prop, Prop::Tree { position: tree, .. }
+                        if nalgebra_glm::distance(tree, position) < 0.01)
+                });
+            }
+            for crop in &save.crops {
+                self.map.remove_generated(
+                    crop.cell,
+                    |prop| matches!(prop, Prop::Crop { crop: wild, .. } if wild.cell == crop.cell),
+                );
+            }
+        }
+    }
+
+    /// Everything about the game that goes in a save.
+    fn to_save(&self) -> SaveGame {
+        let player_entt = self.player_entity();
+        let mut save = SaveGame::new(self.map.params());
+        save.terrain_version = TERRAIN_VERSION;
+        save.ticks = self.ticks;
+        save.position = self.position;
+        save.spawn_point = self.spawn_point;
+        save.vitals = (*self.world.get::<&Vitals>(player_entt).unwrap()).clone();
+        save.hands = (*self.world.get::<&Hands>(player_entt).unwrap()).clone();
+        save.add_map_changes(self.map.changes(&self.world));
+        save
+    }
+
+    fn save(&mut self) {
+        let Some(filename) = self.save_filename else {
+            return;
+        };
+        let message = match self.to_save().to_file(filename) {
+            Ok(()) => format!("Saved to {}", filename),
+            Err(err) => format!("Couldn't save, {}", err),
+        };
+        self.show_message(message);
+    }
+
+    fn show_message(&mut self, text: String) {
+        self.message = Some((text, MESSAGE_TICKS));
+    }
+
+    /// Keeps the sea under the player in worlds that go on past the edges of the map. The water plane is twice as
+    /// wide as the map, so it only has to be moved once the player is a good way from its center.
+    fn move_water(&mut self, app: &App) {
+        let params = self.map.params();
+        if params.edges == MapEdges::Bounded {
+            return;
+        }
+        let water = self
+            .world
+            .query::<&Water>()
+            .iter()
+            .next()
+            .map(|(entt, _)| entt)
+            .unwrap();
+        let mut model = self.world.get::<&mut ModelComponent>(water).unwrap();
+        let offset = self.position.xy() - model.get_position().xy();
+        if offset.x.abs().max(offset.y.abs()) < params.map_width as f32 / 2.0 {
+            return;
+        }
+        model.set_position(nalgebra_glm::vec3(
+            self.position.x,
+            self.position.y,
+            WATER_LEVEL,
+        ));
+        let aabb = app.renderer.get_model_aabb(&model);
+        let mut bvh_node = self.world.get::<&mut BVHNodeComponent>(water).unwrap();
+        self.bvh.remove(bvh_node.node_id);
+        bvh_node.node_id = self.bvh.insert(water, aabb);
+    }
+
+    /// Every now and then, plays the ambient sound of the biome the player is in.
+    fn play_ambient_sound(&self) {
+        if !self.ticks.is_multiple_of(AMBIENT_TICKS) || !rand::thread_rng().gen_bool(AMBIENT_CHANCE)
+        {
+            return;
+        }
+        if let Some(biome) = self.map.biome_at(self.position.xy()) {
+            self.audio
+                .play_sound(String::from(biome.ambient_sound()), AMBIENT_VOLUME);
         }
     }
 
@@ -286,13 +672,14 @@ impl Gameplay {
         }
         let zoom = 1.0;
 
-        let curr_w_state = app.keys[Scancode::W as usize];
-        let curr_s_state = app.keys[Scancode::S as usize];
-        let curr_a_state = app.keys[Scancode::A as usize];
-        let curr_d_state = app.keys[Scancode::D as usize];
-        let curr_space_state = app.keys[Scancode::Space as usize];
+        // Dead players can't walk around
+        let alive = matches!(self.player_state, PlayerState::Alive);
+        let curr_w_state = alive && app.keys[Scancode::W as usize];
+        let curr_s_state = alive && app.keys[Scancode::S as usize];
+        let curr_a_state = alive && app.keys[Scancode::A as usize];
+        let curr_d_state = alive && app.keys[Scancode::D as usize];
+        let curr_space_state = alive && app.keys[Scancode::Space as usize];
         let walking = curr_w_state || curr_s_state || curr_a_state || curr_d_state;
-        let walk_speed: f32 = 1.6 * 2.5 * zoom;
         let facing_vec = nalgebra_glm::vec3(1.0, 0.0, 0.0);
         let sideways_vec = nalgebra_glm::vec3(0.0, 1.0, 0.0);
         let mut player_vel_vec: nalgebra_glm::Vec3 = nalgebra_glm::zero();
@@ -310,10 +697,10 @@ impl Gameplay {
         }
         self.debug = false;
         if curr_space_state && !self.prev_space_state {
-        } else if walking {
+        } else if walking && player_vel_vec.norm() > 0.0 {
             // Move the player, this way moving diagonal isn't faster
-            self.velocity +=
-                player_vel_vec.normalize() * walk_speed * 4.317 * UNIT_PER_METER / 62.5;
+            self.velocity += player_vel_vec.normalize() * WALK_SPEED * zoom;
+            self.facing = player_vel_vec.xy().normalize();
         }
         self.prev_space_state = curr_space_state;
         self.position += self.velocity;
@@ -334,7 +721,7 @@ impl Gameplay {
             &app.renderer.get_model_aabb(&model),
             &self.velocity,
         );
-        self.velocity *= 0.8; // friction
+        self.velocity *= FRICTION;
 
         self.camera_3d
             .set_position(self.position + nalgebra_glm::vec3(13.85, 0.0, 8.00) * zoom);
@@ -343,10 +730,879 @@ impl Gameplay {
         app.renderer.set_camera(self.camera_3d);
     }
 
+    fn update_vitals(&mut self) {
+        // Walking at top speed is full exertion
+        let top_speed = WALK_SPEED / (1.0 - FRICTION);
+        let exertion = nalgebra_glm::length(&self.velocity.xy()) / top_speed;
+        // Warm during the day, cold during the night, unless there's a fire nearby
+        let daylight_warmth = self.directional_light.light_dir.z.clamp(-1.0, 1.0);
+        let fire_warmth = self
+            .bvh
+            .iter_sphere(&Sphere::new(self.position, WARMTH_RADIUS))
+            .filter_map(|entity| {
+                let fire = self.world.get::<&Fire>(entity).ok()?;
+                let model = self.world.get::<&ModelComponent>(entity).ok()?;
+                Some(fire.warmth_at(nalgebra_glm::distance(
+                    &model.get_position(),
+                    &self.position,
+                )))
+            })
+            .fold(0.0, f32::max);
+        let warmth = daylight_warmth.max(fire_warmth);
+
+        let player_entt = self.player_entity();
+        let depleted = self
+            .world
+            .get::<&mut Vitals>(player_entt)
+            .unwrap()
+            .tick(exertion, warmth);
+        if let Some(cause) = depleted {
+            self.die(cause);
+        }
+    }
+
+    fn die(&mut self, cause: Need) {
+        self.player_state = PlayerState::Dead {
+            cause,
+            ticks_left: RESPAWN_TICKS,
+        };
+        self.hovered = None;
+        self.hovered_ground = None;
+        self.grab_ticks = 0;
+        self.cancel_placement();
+        self.audio.play_sound(String::from("res/dead.ogg"), 64);
+    }
+
+    fn update_death(&mut self, app: &App) {
+        let PlayerState::Dead { ticks_left, .. } = &mut self.player_state else {
+            return;
+        };
+        *ticks_left = ticks_left.saturating_sub(1);
+        if *ticks_left > 0 {
+            return;
+        }
+
+        // Whatever was in the player's hands stays where they died
+        let player_entt = self.player_entity();
+        let dropped: Vec<_> = {
+            let mut hands = self.world.get::<&mut Hands>(player_entt).unwrap();
+            [hands.left.take(), hands.right.take()]
+                .into_iter()
+                .flatten()
+                .collect()
+        };
+        let sideways = nalgebra_glm::vec2(-self.facing.y, self.facing.x);
+        for (i, item) in dropped.into_iter().enumerate() {
+            let drop_xy =
+                self.position.xy() + self.facing * DROP_DISTANCE + sideways * (i as f32 * 0.5);
+            let drop_point =
+                nalgebra_glm::vec3(drop_xy.x, drop_xy.y, self.map.height_interpolated(drop_xy));
+            spawn_item(
+                item,
+                drop_point,
+                &app.renderer,
+                &mut self.world,
+                &mut self.bvh,
+            );
+        }
+
+        *self.world.get::<&mut Vitals>(player_entt).unwrap() = Vitals::new();
+        self.position = self.spawn_point;
+        self.velocity = nalgebra_glm::zero();
+        self.player_state = PlayerState::Alive;
+    }
+
+    fn update_hands(&mut self, app: &App) {
+        let player_entt = self.player_entity();
+        let mut hands = self.world.get::<&mut Hands>(player_entt).unwrap();
+
+        if app.key_pressed(Scancode::Tab) {
+            hands.swap_dominant();
+        }
+
+        if app.key_pressed(Scancode::E) {
+            if let Some(food_value) = hands.dominant_item().and_then(|item| item.food_value()) {
+                let food = hands.take_dominant().unwrap().kind;
+                drop(hands);
+                let mut vitals = self.world.get::<&mut Vitals>(player_entt).unwrap();
+                vitals.restore(Need::Hunger, food_value);
+                if rand::thread_rng().gen::<f32>() < food.sickness_chance() {
+                    vitals.make_sick();
+                }
+                return;
+            }
+        }
+
+        if app.key_pressed(Scancode::Q) {
+            if let Some(item) = hands.take_dominant() {
+                drop(hands);
+                // Drop the item at the player's feet, a little in front of them
+                let drop_xy = self.position.xy() + self.facing * DROP_DISTANCE;
+                let drop_point =
+                    nalgebra_glm::vec3(drop_xy.x, drop_xy.y, self.map.height_interpolated(drop_xy));
+                spawn_item(
+                    item,
+                    drop_point,
+                    &app.renderer,
+                    &mut self.world,
+                    &mut self.bvh,
+                );
+            }
+        }
+    }
+
+    /// Crafts the two items in the player's hands together, if there's a recipe for them. Some recipes open a menu.
+    fn craft(&mut self) -> SceneCommand {
+        let player_entt = self.player_entity();
+        let mut hands = self.world.get::<&mut Hands>(player_entt).unwrap();
+        let (Some(dominant_item), Some(off_item)) = (hands.dominant_item(), hands.off_item())
+        else {
+            return SceneCommand::None;
+        };
+        match self.recipes.find(dominant_item, off_item) {
+            Some(RecipeOutput::Items(dominant_output, off_output)) => {
+                let dominant = hands.dominant;
+                *hands.slot_mut(dominant) = dominant_output.map(Item::new);
+                *hands.slot_mut(dominant.other()) = off_output.map(Item::new);
+                SceneCommand::None
+            }
+            Some(RecipeOutput::Menu(CraftingMenu::Knapping)) => {
+                let outcome = Rc::new(RefCell::new(None));
+                self.knapping_outcome = Some(outcome.clone());
+                SceneCommand::Push(Box::new(Knapping::new(outcome)))
+            }
+            Some(RecipeOutput::Menu(CraftingMenu::Molding)) => {
+                let outcome = Rc::new(RefCell::new(None));
+                self.molding_outcome = Some(outcome.clone());
+                SceneCommand::Push(Box::new(Molding::new(outcome)))
+            }
+            None => SceneCommand::None,
+        }
+    }
+
+    /// Applies how the knapping menu ended, once it has been closed. The stone in the dominant hand is the one
+    /// being knapped, and the one in the off hand is the hammerstone, which is kept.
+    fn finish_knapping(&mut self) {
+        let Some(outcome) = self
+            .knapping_outcome
+            .as_ref()
+            .and_then(|outcome| outcome.borrow_mut().take())
+        else {
+            return;
+        };
+        self.knapping_outcome = None;
+
+        let mut hands = self.world.get::<&mut Hands>(self.player_entity()).unwrap();
+        let dominant = hands.dominant;
+        match outcome {
+            KnappingOutcome::Made(item) => *hands.slot_mut(dominant) = Some(Item::new(item)),
+            KnappingOutcome::Broke => *hands.slot_mut(dominant) = None,
+            KnappingOutcome::Cancelled => {}
+        }
+    }
+
+    /// Applies how the molding menu ended, once it has been closed. The clay becomes the made item, and whatever it
+    /// was crafted with is kept. If both hands hold clay, the dominant one is used.
+    fn finish_molding(&mut self) {
+        let Some(outcome) = self
+            .molding_outcome
+            .as_ref()
+            .and_then(|outcome| outcome.borrow_mut().take())
+        else {
+            return;
+        };
+        self.molding_outcome = None;
+
+        let mut hands = self.world.get::<&mut Hands>(self.player_entity()).unwrap();
+        let clay_hand = if hands.dominant_item() == Some(ItemKind::Clay) {
+            hands.dominant
+        } else {
+            hands.dominant.other()
+        };
+        match outcome {
+            MoldingOutcome::Made(item) => *hands.slot_mut(clay_hand) = Some(Item::new(item)),
+            MoldingOutcome::Cancelled => {}
+        }
+    }
+
+    /// Opens the list of structures to build. If a blueprint is already being placed, it's put away instead.
+    fn open_build_menu(&mut self) -> SceneCommand {
+        if self.placing.is_some() {
+            self.cancel_placement();
+            return SceneCommand::None;
+        }
+        let choice = Rc::new(RefCell::new(None));
+        self.build_choice = Some(choice.clone());
+        SceneCommand::Push(Box::new(BuildMenu::new(choice)))
+    }
+
+    /// Starts placing whatever structure was picked in the build menu, once it has been closed.
+    fn finish_build_menu(&mut self, app: &App) {
+        let Some(choice) = self.build_choice.as_ref() else {
+            return;
+        };
+        let choice = choice.borrow_mut().take();
+        self.build_choice = None;
+        let Some(kind) = choice else {
+            return;
+        };
+
+        let ghost = spawn_structure(
+            kind,
+            self.position,
+            &app.renderer,
+            &mut self.world,
+            &mut self.bvh,
+        );
+        self.world.get::<&mut ModelComponent>(ghost).unwrap().tint = BLOCKED_GHOST_TINT;
+        self.placing = Some(Placement { kind, ghost });
+    }
+
+    /// Moves the ghost of the structure being placed to the mouse, and places it as a blueprint on click. Right click
+    /// puts it away.
+    fn update_placement(&mut self, app: &App) {
+        let Some(Placement { kind, ghost }) = self.placing else {
+            return;
+        };
+        self.hovered = None;
+        self.hovered_ground = None;
+        if app.mouse_right_clicked {
+            self.cancel_placement();
+            return;
+        }
+        let ray = self.mouse_ray(app);
+        let Some(hit) = self.map.raycast(&ray, MAX_RAY_DISTANCE) else {
+            return;
+        };
+        let position = nalgebra_glm::vec3(hit.x, hit.y, self.map.height_interpolated(hit.xy()));
+
+        let node_id = self.world.get::<&BVHNodeComponent>(ghost).unwrap().node_id;
+        let mut model = self.world.get::<&mut ModelComponent>(ghost).unwrap();
+        let displacement = position - model.get_position();
+        model.set_position(position);
+        let aabb = app.renderer.get_model_aabb(&model);
+        self.bvh.move_obj(node_id, &aabb, &displacement);
+
+        // Structures can't be built on top of trees, rocks or other structures
+        let blocked = self.bvh.iter_aabb(&aabb).any(|entity| {
+            entity != ghost
+                && (self.world.get::<&Tree>(entity).is_ok()
+                    || self.world.get::<&Regrowth>(entity).is_ok()
+                    || self.world.get::<&Rock>(entity).is_ok()
+                    || self.world.get::<&Structure>(entity).is_ok()
+                    || self.world.get::<&Blueprint>(entity).is_ok())
+        });
+        let valid = !blocked
+            && position.z >= WATER_LEVEL
+            && nalgebra_glm::distance(&position.xy(), &self.position.xy()) <= REACH;
+        model.tint = if valid {
+            GHOST_TINT
+        } else {
+            BLOCKED_GHOST_TINT
+        };
+
+        if valid && app.mouse_left_clicked {
+            model.tint = BLUEPRINT_TINT;
+            drop(model);
+            self.world.insert_one(ghost, Blueprint::new(kind)).unwrap();
+            self.placing = None;
+        }
+    }
+
+    fn cancel_placement(&mut self) {
+        let Some(placement) = self.placing.take() else {
+            return;
+        };
+        if let Ok(bvh_node) = self.world.get::<&BVHNodeComponent>(placement.ghost) {
+            self.bvh.remove(bvh_node.node_id);
+        }
+        self.world.despawn(placement.ghost).unwrap();
+    }
+
     fn update_clickers(&mut self, app: &App) {
         if app.mouse_left_clicked {
             println!("{:?} {:?}", app.mouse_x, app.mouse_y);
         }
+        let ray = self.mouse_ray(app);
+
+        // Set all outlines to false
+        for (_, model) in &mut self.world.query::<&mut ModelComponent>() {
+            model.outlined = false;
+        }
+
+        // Find the closest pickable under the mouse that is within reach
+        let hovered = self
+            .bvh
+            .iter_ray(&ray)
+            .filter(|entity| self.action_label(*entity).is_some())
+            .map(|entity| {
+                let position = self
+                    .world
+                    .get::<&ModelComponent>(entity)
+                    .unwrap()
+                    .get_position();
+                (entity, position)
+            })
+            .filter(|(_, position)| {
+                nalgebra_glm::distance(&position.xy(), &self.position.xy()) <= REACH
+            })
+            .min_by(|(_, a), (_, b)| {
+                nalgebra_glm::distance(a, &ray.origin)
+                    .total_cmp(&nalgebra_glm::distance(b, &ray.origin))
+            })
+            .map(|(entity, _)| entity);
+        // Only look at the ground if there's no entity in the way
+        let hovered_ground = if hovered.is_none() {
+            self.ground_hit(&ray)
+        } else {
+            None
+        };
+        let same_cell = match (self.hovered_ground, hovered_ground) {
+            (Some((a, _)), Some((b, _))) => {
+                nalgebra_glm::floor(&a.xy()) == nalgebra_glm::floor(&b.xy())
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        if hovered != self.hovered || !same_cell {
+            self.grab_ticks = 0;
+        }
+        self.hovered = hovered;
+        self.hovered_ground = hovered_ground;
+
+        if let Some((_, GroundAction::Drink)) = self.hovered_ground {
+            // Drinking is instant, there's no need to hold the mouse down
+            if app.mouse_left_clicked {
+                self.drink();
+            }
+            return;
+        }
+        if self.hovered.is_none() && self.hovered_ground.is_none() {
+            return;
+        }
+
+        // Set hovered outline to true, and act on it if the mouse has been held long enough
+        if let Some(entity) = self.hovered {
+            self.world
+                .get::<&mut ModelComponent>(entity)
+                .unwrap()
+                .outlined = true;
+        }
+        if app.mouse_left_down {
+            self.grab_ticks += 1;
+        } else {
+            self.grab_ticks = 0;
+        }
+        if self.grab_ticks >= self.hold_ticks() {
+            self.grab_ticks = 0;
+            if let Some(entity) = self.hovered {
+                self.perform_action(entity, app);
+            } else if let Some((point, action)) = self.hovered_ground {
+                self.perform_ground_action(point, action, app);
+            }
+        }
+    }
+
+    /// Where the ray hits the ground, and what can be done there, if it does so within reach of the player.
+    fn ground_hit(&self, ray: &Ray) -> Option<(nalgebra_glm::Vec3, GroundAction)> {
+        let terrain_hit = self.map.raycast(ray, MAX_RAY_DISTANCE)?;
+        // The sea is too salty to drink, and nothing grows under it
+        if terrain_hit.z < WATER_LEVEL {
+            return None;
+        }
+        if nalgebra_glm::distance(&terrain_hit.xy(), &self.position.xy()) > REACH {
+            return None;
+        }
+        if self.map.has_fresh_water(terrain_hit.xy()) {
+            return Some((terrain_hit, GroundAction::Drink));
+        }
+
+        let soil = self.map.soil(terrain_hit.xy())?;
+        let seed = self
+            .world
+            .get::<&Hands>(self.player_entity())
+            .unwrap()
+            .dominant_item()
+            .and_then(CropKind::from_seed);
+        // Only a hoe can break up the ground
+        let can_till = self.dominant_tool().is_some_and(|tool| {
+            tool_action(tool.kind, ToolTarget::Ground) == Some(ToolAction::Till)
+        });
+        let action = match seed {
+            Some(crop_kind) if soil.tilled && !soil.planted => GroundAction::Plant(crop_kind),
+            _ if can_till && !soil.tilled && !soil.planted => GroundAction::Till,
+            _ if soil.tilled && !soil.watered => GroundAction::Water,
+            _ => return None,
+        };
+        Some((terrain_hit, action))
+    }
+
+    /// Tills, waters or plants the terrain cell at a point, after the mouse has been held on it.
+    fn perform_ground_action(
+        &mut self,
+        point: nalgebra_glm::Vec3,
+        action: GroundAction,
+        app: &App,
+    ) {
+        let Some(mut soil) = self.map.soil(point.xy()) else {
+            return;
+        };
+        match action {
+            GroundAction::Drink => {
+                self.drink();
+                return;
+            }
+            GroundAction::Till => {
+                soil.tilled = true;
+                self.wear_tool();
+            }
+            GroundAction::Water => soil.watered = true,
+            GroundAction::Plant(crop_kind) => {
+                let player_entt = self.player_entity();
+                self.world
+                    .get::<&mut Hands>(player_entt)
+                    .unwrap()
+                    .take_dominant();
+                let cell = nalgebra_glm::floor(&point.xy());
+                spawn_crop(
+                    Crop::new(crop_kind, 0, cell),
+                    &self.map,
+                    &app.renderer,
+                    &mut self.world,
+                    &mut self.bvh,
+                );
+                soil.planted = true;
+            }
+        }
+        self.map
+            .set_soil(point.xy(), soil, &app.renderer, &mut self.world);
+        self.hovered_ground = None;
+    }
+
+    fn drink(&mut self) {
+        const SIP: f32 = 0.25;
+        self.world
+            .get::<&mut Vitals>(self.player_entity())
+            .unwrap()
+            .restore(Need::Thirst, SIP);
+        self.audio.play_sound(String::from("res/pop.ogg"), 64);
+    }
+
+    /// Text shown on the action billboard when hovering over an entity, or None if there's nothing to do with it.
+    fn action_label(&self, entity: Entity) -> Option<String> {
+        if let Some(action) = self.entity_tool_action(entity) {
+            let (_, name) = self.tool_target(entity)?;
+            Some(format!("{} {}", action.verb(), name))
+        } else if let Ok(pickable) = self.world.get::<&Pickable>(entity) {
+            Some(format!("Grab {}", pickable.label))
+        } else if let Ok(bush) = self.world.get::<&BerryBush>(entity) {
+            bush.has_berries().then(|| String::from("Pick Berries"))
+        } else if let Ok(animal) = self.world.get::<&Animal>(entity) {
+            Some(format!("Hunt {}", animal.kind.name()))
+        } else if let Ok(carcass) = self.world.get::<&Carcass>(entity) {
+            Some(format!("{} Carcass (needs a knife)", carcass.kind.name()))
+        } else if let Ok(fire) = self.world.get::<&Fire>(entity) {
+            let held = self
+                .world
+                .get::<&Hands>(self.player_entity())
+                .unwrap()
+                .dominant_item();
+            match held {
+                Some(item) if fire.can_add(item) => Some(format!("Add {} to Fire", item.name())),
+                Some(item) if self.can_cook_on(entity, item) => {
+                    Some(format!("Cook {}", item.name()))
+                }
+                _ if fire.is_burning() => Some(String::from("Fire")),
+                _ => Some(String::from("Fire (needs fuel)")),
+            }
+        } else if let Ok(blueprint) = self.world.get::<&Blueprint>(entity) {
+            let held = self
+                .world
+                .get::<&Hands>(self.player_entity())
+                .unwrap()
+                .dominant_item();
+            match held {
+                Some(item) if blueprint.needs(item) => Some(format!(
+                    "Add {} (needs {})",
+                    item.name(),
+                    blueprint.describe_remaining()
+                )),
+                _ => Some(format!("Needs {}", blueprint.describe_remaining())),
+            }
+        } else if let Ok(crop) = self.world.get::<&Crop>(entity) {
+            crop.is_mature()
+                .then(|| format!("Harvest {}", crop.kind.name()))
+        } else {
+            None
+        }
+    }
+
+    /// Does whatever the action billboard for an entity says, after the mouse has been held on it.
+    fn perform_action(&mut self, entity: Entity, app: &App) {
+        if let Some(action) = self.entity_tool_action(entity) {
+            self.use_tool(entity, action, app);
+        } else if self.world.get::<&Pickable>(entity).is_ok() {
+            self.grab(entity);
+        } else if self.world.get::<&BerryBush>(entity).is_ok() {
+            self.pick_berry(entity, app);
+        } else if self.world.get::<&Animal>(entity).is_ok() {
+            self.hit_animal(entity, FIST_DAMAGE, app);
+        } else if self.world.get::<&Fire>(entity).is_ok() {
+            self.tend_fire(entity, app);
+        } else if self.world.get::<&Blueprint>(entity).is_ok() {
+            self.deliver_to_blueprint(entity);
+        } else if self.world.get::<&Crop>(entity).is_ok() {
+            self.harvest_crop(entity, app);
+        }
+    }
+
+    /// The tool in the player's dominant hand, if they're holding one.
+    fn dominant_tool(&self) -> Option<Tool> {
+        let hands = self.world.get::<&Hands>(self.player_entity()).unwrap();
+        hands.slot(hands.dominant).and_then(|item| item.tool)
+    }
+
+    /// What tools see an entity as, and its name for the action billboard. None if no tool can be used on it.
+    fn tool_target(&self, entity: Entity) -> Option<(ToolTarget, String)> {
+        if self.world.get::<&Tree>(entity).is_ok() {
+            Some((ToolTarget::Tree, String::from("Tree")))
+        } else if self.world.get::<&Rock>(entity).is_ok() {
+            Some((ToolTarget::Rock, String::from("Rock")))
+        } else if let Ok(carcass) = self.world.get::<&Carcass>(entity) {
+            Some((
+                ToolTarget::Carcass,
+                format!("{} Carcass", carcass.kind.name()),
+            ))
+        } else if let Ok(animal) = self.world.get::<&Animal>(entity) {
+            Some((ToolTarget::Animal, String::from(animal.kind.name())))
+        } else {
+            None
+        }
+    }
+
+    /// What the held tool would do to an entity, if anything.
+    fn entity_tool_action(&self, entity: Entity) -> Option<ToolAction> {
+        let tool = self.dominant_tool()?;
+        let (target, _) = self.tool_target(entity)?;
+        tool_action(tool.kind, target)
+    }
+
+    /// How many ticks the mouse must be held down on whatever is hovered. Using a tool takes as long as its swing.
+    fn hold_ticks(&self) -> usize {
+        let swinging = match (self.hovered, self.hovered_ground) {
+            (Some(entity), _) => self.entity_tool_action(entity).is_some(),
+            (None, Some((_, GroundAction::Till))) => true,
+            _ => false,
+        };
+        match self.dominant_tool() {
+            Some(tool) if swinging => tool.kind.swing_ticks(),
+            _ => GRAB_TICKS,
+        }
+    }
+
+    /// Does a tool's action to an entity, at the end of a swing. Every use wears the tool down.
+    fn use_tool(&mut self, entity: Entity, action: ToolAction, app: &App) {
+        match action {
+            ToolAction::Chop => self.chop_tree(entity, app),
+            ToolAction::Break => self.break_rock(entity, app),
+            ToolAction::Butcher => self.butcher(entity, app),
+            ToolAction::Stab => self.hit_animal(entity, SPEAR_DAMAGE, app),
+            ToolAction::Till => {} // Tilling is done to the ground, see `perform_ground_action`
+        }
+        self.wear_tool();
+    }
+
+    /// Uses up some of the durability of the tool in the dominant hand. If that breaks it, it's gone.
+    fn wear_tool(&mut self) {
+        let mut hands = self.world.get::<&mut Hands>(self.player_entity()).unwrap();
+        let broke = hands.dominant_tool_mut().is_some_and(|tool| tool.wear());
+        if broke {
+            hands.take_dominant();
+            self.audio.play_sound(String::from("res/ground.ogg"), 64);
+        }
+    }
+
+    /// Takes a chop out of a tree. Once it's felled, it leaves a stump behind, along with some logs and sticks.
+    fn chop_tree(&mut self, entity: Entity, app: &App) {
+        self.audio.play_sound(String::from("res/hit.ogg"), 64);
+        let felled = self.world.get::<&mut Tree>(entity).unwrap().chop();
+        if !felled {
+            return;
+        }
+
+        let scale = self
+            .world
+            .get::<&ModelComponent>(entity)
+            .unwrap()
+            .get_scale();
+        let position = self.remove_entity(entity);
+        spawn_regrowth(
+            Regrowth::new(scale),
+            position,
+            &app.renderer,
+            &mut self.world,
+            &mut self.bvh,
+        );
+        self.drop_items(
+            &[
+                ItemKind::Log,
+                ItemKind::Log,
+                ItemKind::Stick,
+                ItemKind::Stick,
+            ],
+            position + nalgebra_glm::vec3(0.5, 0.0, 0.0),
+            app,
+        );
+    }
+
+    /// Smashes a rock into a couple of smaller stones.
+    fn break_rock(&mut self, entity: Entity, app: &App) {
+        let position = self.remove_entity(entity);
+        self.drop_items(&[ItemKind::Stone, ItemKind::Stone], position, app);
+    }
+
+    /// Cuts up a carcass into its meat and hide.
+    fn butcher(&mut self, entity: Entity, app: &App) {
+        let kind = self.world.get::<&Carcass>(entity).unwrap().kind;
+        let position = self.remove_entity(entity);
+        self.drop_items(kind.drops(), position, app);
+    }
+
+    /// Hurts an animal. If that kills it, it's left as a carcass where it died.
+    fn hit_animal(&mut self, entity: Entity, damage: usize, app: &App) {
+        self.audio.play_sound(String::from("res/hit.ogg"), 64);
+        let (killed, kind) = {
+            let mut animal = self.world.get::<&mut Animal>(entity).unwrap();
+            (animal.hit(damage), animal.kind)
+        };
+        if !killed {
+            return;
+        }
+
+        self.world.remove_one::<Animal>(entity).unwrap();
+        self.world.insert_one(entity, Carcass { kind }).unwrap();
+        let mut model = self.world.get::<&mut ModelComponent>(entity).unwrap();
+        model.set_scale(kind.carcass_scale());
+        let aabb = app.renderer.get_model_aabb(&model);
+        let mut bvh_node = self.world.get::<&mut BVHNodeComponent>(entity).unwrap();
+        self.bvh.remove(bvh_node.node_id);
+        bvh_node.node_id = self.bvh.insert(entity, aabb);
+    }
+
+    /// Lays items out in a row on the ground, starting at a position.
+    fn drop_items(&mut self, items: &[ItemKind], position: nalgebra_glm::Vec3, app: &App) {
+        for (i, item) in items.iter().enumerate() {
+            let drop_xy = position.xy() + nalgebra_glm::vec2(0.0, i as f32 * 0.3);
+            let drop_point =
+                nalgebra_glm::vec3(drop_xy.x, drop_xy.y, self.map.height_interpolated(drop_xy));
+            spawn_item(
+                Item::new(*item),
+                drop_point,
+                &app.renderer,
+                &mut self.world,
+                &mut self.bvh,
+            );
+        }
+    }
+
+    /// Takes an entity out of the world and the BVH. Returns where it was.
+    fn remove_entity(&mut self, entity: Entity) -> nalgebra_glm::Vec3 {
+        let position = self
+            .world
+            .get::<&ModelComponent>(entity)
+            .unwrap()
+            .get_position();
+        if let Ok(bvh_node) = self.world.get::<&BVHNodeComponent>(entity) {
+            self.bvh.remove(bvh_node.node_id);
+        }
+        self.world.despawn(entity).unwrap();
+        self.hovered = None;
+        position
+    }
+
+    /// Puts the item in the dominant hand towards a blueprint. The blueprint is built once it has everything.
+    fn deliver_to_blueprint(&mut self, entity: Entity) {
+        let player_entt = self.player_entity();
+        let Some(item) = self
+            .world
+            .get::<&Hands>(player_entt)
+            .unwrap()
+            .dominant_item()
+        else {
+            return;
+        };
+        let (delivered, complete, kind) = {
+            let mut blueprint = self.world.get::<&mut Blueprint>(entity).unwrap();
+            (
+                blueprint.deliver(item),
+                blueprint.is_complete(),
+                blueprint.kind,
+            )
+        };
+        if !delivered {
+            return;
+        }
+        self.world
+            .get::<&mut Hands>(player_entt)
+            .unwrap()
+            .take_dominant();
+
+        if complete {
+            self.world.remove_one::<Blueprint>(entity).unwrap();
+            complete_structure(entity, kind, &mut self.world);
+            self.hovered = None;
+            self.audio.play_sound(String::from("res/pop.ogg"), 64);
+        }
+    }
+
+    /// Whether an item can be put on a fire to cook. The fire has to be burning, and have room for it.
+    fn can_cook_on(&self, fire: Entity, item: ItemKind) -> bool {
+        item.cooked().is_some()
+            && self.world.get::<&Fire>(fire).unwrap().is_burning()
+            && free_cooking_slot(&self.world, fire).is_some()
+    }
+
+    /// Throws the item in the dominant hand onto a fire as fuel, or puts it on the fire to cook.
+    fn tend_fire(&mut self, entity: Entity, app: &App) {
+        let player_entt = self.player_entity();
+        let Some(item) = self
+            .world
+            .get::<&Hands>(player_entt)
+            .unwrap()
+            .dominant_item()
+        else {
+            return;
+        };
+
+        if self.world.get::<&mut Fire>(entity).unwrap().add_fuel(item) {
+            self.world
+                .get::<&mut Hands>(player_entt)
+                .unwrap()
+                .take_dominant();
+            self.audio.play_sound(String::from("res/pop.ogg"), 64);
+        } else if self.can_cook_on(entity, item) {
+            let slot = free_cooking_slot(&self.world, entity).unwrap();
+            let item = self
+                .world
+                .get::<&mut Hands>(player_entt)
+                .unwrap()
+                .take_dominant()
+                .unwrap();
+            let position = self
+                .world
+                .get::<&ModelComponent>(entity)
+                .unwrap()
+                .get_position();
+            let food = spawn_item(
+                item,
+                position + COOKING_SLOTS[slot],
+                &app.renderer,
+                &mut self.world,
+                &mut self.bvh,
+            );
+            self.world
+                .insert_one(food, Cooking::new(entity, slot))
+                .unwrap();
+        }
+    }
+
+    /// Digs up a mature crop. Whatever doesn't fit in the player's hands is left on the ground.
+    fn harvest_crop(&mut self, entity: Entity, app: &App) {
+        let (cell, (item, count)) = {
+            let crop = self.world.get::<&Crop>(entity).unwrap();
+            (crop.cell, crop.kind.harvest())
+        };
+        let position = self
+            .world
+            .get::<&ModelComponent>(entity)
+            .unwrap()
+            .get_position();
+
+        if let Ok(bvh_node) = self.world.get::<&BVHNodeComponent>(entity) {
+            self.bvh.remove(bvh_node.node_id);
+        }
+        self.world.despawn(entity).unwrap();
+        self.hovered = None;
+
+        let player_entt = self.player_entity();
+        for i in 0..count {
+            let given = self
+                .world
+                .get::<&mut Hands>(player_entt)
+                .unwrap()
+                .give(Item::new(item));
+            if !given {
+                let drop_point = position + nalgebra_glm::vec3(0.0, i as f32 * 0.2, 0.0);
+                spawn_item(
+                    Item::new(item),
+                    drop_point,
+                    &app.renderer,
+                    &mut self.world,
+                    &mut self.bvh,
+                );
+            }
+        }
+
+        if let Some(soil) = self.map.soil(cell) {
+            self.map.set_soil(
+                cell,
+                Soil {
+                    planted: false,
+                    ..soil
+                },
+                &app.renderer,
+                &mut self.world,
+            );
+        }
+    }
+
+    fn pick_berry(&mut self, entity: Entity, app: &App) {
+        let player_entt = self.player_entity();
+        if self.world.get::<&Hands>(player_entt).unwrap().is_full() {
+            return;
+        }
+        let mut bush = self.world.get::<&mut BerryBush>(entity).unwrap();
+        if let Some(berry) = bush.harvest() {
+            self.world
+                .get::<&mut Hands>(player_entt)
+                .unwrap()
+                .give(Item::new(berry));
+        }
+        if !bush.has_berries() {
+            self.world
+                .get::<&mut ModelComponent>(entity)
+                .unwrap()
+                .texture_id = app
+                .renderer
+                .get_texture_id_from_name(bush.texture_name())
+                .unwrap();
+        }
+    }
+
+    /// Moves a pickable entity from the world into the player's hands, if there is room for it.
+    fn grab(&mut self, entity: Entity) {
+        let item = Item {
+            kind: self.world.get::<&Pickable>(entity).unwrap().item_kind,
+            tool: self.world.get::<&Tool>(entity).ok().map(|tool| *tool),
+        };
+        let given = self
+            .world
+            .get::<&mut Hands>(self.player_entity())
+            .unwrap()
+            .give(item);
+        if !given {
+            return;
+        }
+
+        if let Ok(bvh_node) = self.world.get::<&BVHNodeComponent>(entity) {
+            self.bvh.remove(bvh_node.node_id);
+        }
+        self.world.despawn(entity).unwrap();
+        self.hovered = None;
+    }
+
+    /// Casts a ray from the camera, through the mouse cursor, and into the world.
+    fn mouse_ray(&self, app: &App) -> Ray {
         let ndc_x = (2.0 * app.mouse_x as f32) / app.window_size.x as f32 - 1.0;
         let ndc_y = 1.0 - (2.0 * (app.mouse_y as f32)) / app.window_size.y as f32;
 
@@ -359,30 +1615,239 @@ impl Gameplay {
         let world_coords = inv_view * eye_coords;
         let dir = (world_coords.xyz() - self.camera_3d.position()).normalize();
 
-        let ray = Ray {
+        Ray {
             dir,
             origin: self.camera_3d.position(),
+        }
+    }
+
+    /// Draws an action billboard above whatever the mouse is hovering over, like "Grab Stone".
+    fn render_action_billboard(&mut self, app: &App) {
+        const PADDING: f32 = 4.0;
+        let (anchor, text) = if let Some(entity) = self.hovered {
+            let Some(text) = self.action_label(entity) else {
+                return;
+            };
+            let position = self
+                .world
+                .get::<&ModelComponent>(entity)
+                .unwrap()
+                .get_position();
+            (position, text)
+        } else if let Some((point, action)) = self.hovered_ground {
+            let text = match action {
+                GroundAction::Drink => String::from("Drink"),
+                GroundAction::Till => String::from("Till"),
+                GroundAction::Water => String::from("Water"),
+                GroundAction::Plant(crop_kind) => format!("Plant {}", crop_kind.name()),
+            };
+            (point, text)
+        } else {
+            return;
+        };
+        let anchor = anchor + nalgebra_glm::vec3(0.0, 0.0, 0.5);
+        let Some(screen_pos) = self
+            .camera_3d
+            .world_to_screen(anchor, app.window_size.cast())
+        else {
+            return;
         };
 
-        // Set all outlines to false
-        for (_, model) in &mut self.world.query::<&mut ModelComponent>() {
-            model.outlined = false;
+        let text_size = app.renderer.measure_text(&text);
+        let text_pos = screen_pos - nalgebra_glm::vec2(text_size.x * 0.5, text_size.y);
+        app.renderer
+            .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.5));
+        app.renderer.fill_rect(Rectangle::new(
+            text_pos.x - PADDING,
+            text_pos.y - PADDING,
+            text_size.x + 2.0 * PADDING,
+            text_size.y + 2.0 * PADDING,
+        ));
+
+        // Progress of the click-and-hold grab
+        if self.grab_ticks > 0 {
+            app.renderer
+                .set_color(nalgebra_glm::vec4(1.0, 1.0, 1.0, 0.5));
+            app.renderer.fill_rect(Rectangle::new(
+                text_pos.x - PADDING,
+                text_pos.y + text_size.y + PADDING,
+                (text_size.x + 2.0 * PADDING) * self.grab_ticks as f32 / self.hold_ticks() as f32,
+                PADDING,
+            ));
         }
 
-        // Set hovered outlines to true
-        let hovereds: Vec<Entity> = self
-            .bvh
-            .iter_ray(&ray)
-            .filter(|entity| self.world.get::<&Rock>(*entity).is_ok())
-            .collect();
-        for entity in hovereds {
-            self.world
-                .get::<&mut ModelComponent>(entity)
-                .unwrap()
-                .outlined = true;
-            if app.mouse_left_clicked {
-                println!("{:?}", entity);
+        app.renderer.draw_text(text_pos, &text);
+    }
+
+    fn render_hands(&mut self, app: &App) {
+        const SLOT_SIZE: f32 = 96.0;
+        const ICON_PADDING: f32 = 24.0;
+        const DURABILITY_BAR_HEIGHT: f32 = 4.0;
+        // The held tool swings up and back down while the mouse is held
+        let swing_progress = self.grab_ticks as f32 / self.hold_ticks() as f32;
+        let swing_offset = (swing_progress * f32::consts::PI).sin() * SWING_HEIGHT;
+        let hands = self.world.get::<&Hands>(self.player_entity()).unwrap();
+
+        for (hand, slot_x) in [
+            (Hand::Left, app.window_size.x as f32 * 0.5 - SLOT_SIZE * 1.5),
+            (
+                Hand::Right,
+                app.window_size.x as f32 * 0.5 + SLOT_SIZE * 0.5,
+            ),
+        ] {
+            let slot_rect = Rectangle::new(
+                slot_x,
+                app.window_size.y as f32 - SLOT_SIZE,
+                SLOT_SIZE,
+                SLOT_SIZE,
+            );
+
+            // The dominant hand's slot is lighter than the other one
+            if hand == hands.dominant {
+                app.renderer
+                    .set_color(nalgebra_glm::vec4(1.0, 1.0, 1.0, 0.5));
+            } else {
+                app.renderer
+                    .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.5));
+            }
+            app.renderer.fill_rect(slot_rect);
+
+            if let Some(item) = hands.slot(hand) {
+                let icon = app
+                    .renderer
+                    .get_texture_id_from_name(item.kind.icon_name())
+                    .unwrap();
+                let bob = if hand == hands.dominant && item.tool.is_some() {
+                    swing_offset
+                } else {
+                    0.0
+                };
+                app.renderer.copy_whole_texture(
+                    Rectangle::new(
+                        slot_rect.pos.x + ICON_PADDING,
+                        slot_rect.pos.y + ICON_PADDING - bob,
+                        SLOT_SIZE - 2.0 * ICON_PADDING,
+                        SLOT_SIZE - 2.0 * ICON_PADDING,
+                    ),
+                    icon,
+                );
+
+                // Tools show how worn out they are along the bottom of the slot
+                if let Some(tool) = item.tool {
+                    app.renderer.set_color(nalgebra_glm::vec4(
+                        1.0 - tool.condition(),
+                        tool.condition(),
+                        0.0,
+                        0.8,
+                    ));
+                    app.renderer.fill_rect(Rectangle::new(
+                        slot_rect.pos.x,
+                        slot_rect.pos.y + SLOT_SIZE - DURABILITY_BAR_HEIGHT,
+                        SLOT_SIZE * tool.condition(),
+                        DURABILITY_BAR_HEIGHT,
+                    ));
+                }
             }
         }
     }
+
+    /// Draws the "feeling" bar, which shows the most pressing survival need.
+    fn render_feeling_bar(&mut self, app: &App) {
+        const BAR_WIDTH: f32 = 164.0;
+        const BORDER: f32 = 8.0;
+        let (need, value) = self
+            .world
+            .get::<&Vitals>(self.player_entity())
+            .unwrap()
+            .most_pressing();
+
+        let sick = self
+            .world
+            .get::<&Vitals>(self.player_entity())
+            .unwrap()
+            .is_sick();
+        let feeling = if sick {
+            format!("{} (Sick)", need.feeling())
+        } else {
+            String::from(need.feeling())
+        };
+        app.renderer
+            .draw_text(nalgebra_glm::vec2(10.0, 10.0), &feeling);
+
+        let nine_slice = NineSlice {
+            texture: app
+                .renderer
+                .get_texture_id_from_name("nine-slice-test")
+                .unwrap(),
+            border: BORDER,
+        };
+        let nine_slice2 = NineSlice {
+            texture: app
+                .renderer
+                .get_texture_id_from_name("nine-slice-test2")
+                .unwrap(),
+            border: BORDER,
+        };
+        app.renderer
+            .render_nine_slice(nine_slice, Rectangle::new(70.0, 10.0, BAR_WIDTH, 16.0));
+        app.renderer.render_nine_slice(
+            nine_slice2,
+            Rectangle::new(
+                70.0,
+                10.0,
+                2.0 * BORDER + value * (BAR_WIDTH - 2.0 * BORDER),
+                16.0,
+            ),
+        );
+    }
+
+    /// Draws the latest message along the bottom of the screen.
+    fn render_message(&mut self, app: &App) {
+        let Some((text, _)) = &self.message else {
+            return;
+        };
+        let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+        let text_size = app.renderer.measure_text(text);
+        app.renderer.draw_text(
+            nalgebra_glm::vec2(
+                (screen_size.x - text_size.x) * 0.5,
+                screen_size.y - text_size.y - 10.0,
+            ),
+            text,
+        );
+    }
+
+    fn render_death_screen(&mut self, app: &App) {
+        let PlayerState::Dead { cause, ticks_left } = self.player_state else {
+            return;
+        };
+        let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+        app.renderer
+            .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.6));
+        app.renderer
+            .fill_rect(Rectangle::new(0.0, 0.0, screen_size.x, screen_size.y));
+
+        let text = format!(
+            "{}\nRespawning in {}...",
+            cause.death_message(),
+            ticks_left / 60 + 1
+        );
+        let text_size = app.renderer.measure_text(&text);
+        app.renderer
+            .draw_text((screen_size - text_size) * 0.5, &text);
+    }
+
+    fn player_entity(&self) -> Entity {
+        self.world
+            .query::<&Player>()
+            .iter()
+            .next()
+            .map(|(entt, _)| entt)
+            .unwrap()
+    }
+}
+
+/// Angle of the sun, which the day/night cycle is derived from.
+fn day_angle(ticks: usize) -> f32 {
+    ticks as f32 / (60.0 * 60.0 * 0.5 * MINUTES_PER_DAY) + TICKS_OFFSET
 }
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    engine::chunked_map::{benchmark_meshes, ChunkCoord, ChunkMesh, MapParams},
+    scenes::gameplay::WorldOptions,
+    App, Scene, SceneCommand,
+};
+
+/// How many chunks there are along each side of the patch of terrain that's benchmarked.
+const PATCH_CHUNKS: i32 = 8;
+/// How many times each layout is uploaded. The fastest time is reported.
+const ROUNDS: usize = 5;
+
+/// Uploads the terrain meshes of a patch of chunks both the way the map builds them, with shared vertices, and with
+/// three vertices for every triangle like they used to be, then prints how much VRAM and time each layout took and
+/// quits.
+pub struct MeshBenchmark {
+    options: WorldOptions,
+}
+
+/// How one layout of the patch's meshes did.
+#[derive(Default)]
+struct LayoutResult {
+    vertices: usize,
+    indices: usize,
+    vram_bytes: usize,
+    upload_time: Duration,
+}
+
+impl Scene for MeshBenchmark {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        let params = MapParams {
+            map_width: self.options.map_width,
+            chunk_width: self.options.chunk_width,
+            level_of_detail: 0.01,
+            seed: self.options.seed.unwrap_or(1),
+            amplitude: 1.0,
+            edges: self.options.edges,
+        };
+        // A patch in the middle of the map, so that a bounded map doesn't make it all sea
+        let middle = (params.map_width / params.chunk_width) as i32 / 2 - PATCH_CHUNKS / 2;
+        let coords: Vec<ChunkCoord> = (0..PATCH_CHUNKS)
+            .flat_map(|y| (0..PATCH_CHUNKS).map(move |x| ChunkCoord::new(middle + x, middle + y)))
+            .collect();
+
+        println!("Building the meshes of {} chunks...", coords.len());
+        let (indexed, unindexed): (Vec<ChunkMesh>, Vec<ChunkMesh>) =
+            benchmark_meshes(&params, &coords).into_iter().unzip();
+        let indexed = upload(app, &indexed);
+        let unindexed = upload(app, &unindexed);
+
+        println!(
+            "{:<12}{:>12}{:>12}{:>14}{:>14}",
+            "layout", "vertices", "indices", "VRAM (KiB)", "upload (ms)"
+        );
+        for (name, result) in [("indexed", &indexed), ("unindexed", &unindexed)] {
+            println!(
+                "{:<12}{:>12}{:>12}{:>14.1}{:>14.2}",
+                name,
+                result.vertices,
+                result.indices,
+                result.vram_bytes as f64 / 1024.0,
+                result.upload_time.as_secs_f64() * 1000.0
+            );
+        }
+        println!(
+            "The indexed meshes take {:.1}% of the VRAM and {:.1}% of the upload time.",
+            100.0 * indexed.vram_bytes as f64 / unindexed.vram_bytes as f64,
+            100.0 * indexed.upload_time.as_secs_f64() / unindexed.upload_time.as_secs_f64()
+        );
+        SceneCommand::Pop
+    }
+
+    fn render(&mut self, _app: &App) {}
+}
+
+impl MeshBenchmark {
+    pub fn new(options: &WorldOptions) -> Self {
+        Self { options: *options }
+    }
+}
+
+/// Uploads the meshes a few times, freeing them after each time.
+fn upload(app: &App, meshes: &[ChunkMesh]) -> LayoutResult {
+    let mut result = LayoutResult {
+        upload_time: Duration::MAX,
+        ..Default::default()
+    };
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let ids: Vec<_> = meshes
+            .iter()
+            .map(|(indices, vertices, normals, uv)| {
+                app.renderer
+                    .add_mesh_from_verts(indices.clone(), vec![vertices, normals, uv], None)
+            })
+            .collect();
+        // The driver can queue up the uploads, so wait for it to actually do them
+        unsafe { gl::Finish() };
+        result.upload_time = result.upload_time.min(start.elapsed());
+
+        result.vram_bytes = ids
+            .iter()
+            .map(|id| app.renderer.get_mesh_from_id(*id).unwrap().vram_bytes())
+            .sum();
+        for id in ids {
+            app.renderer.remove_mesh(id);
+        }
+    }
+    result.vertices = meshes.iter().map(|mesh| mesh.1.len() / 3).sum();
+    result.indices = meshes.iter().map(|mesh| mesh.0.len()).sum();
+    result
+}
@@ -1 +1,5 @@
+pub(crate) mod build_menu;
 pub(crate) mod gameplay;
+pub(crate) mod knapping;
+pub(crate) mod mesh_benchmark;
+pub(crate) mod molding;
@@ -0,0 +1,209 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    engine::{rectangle::Rectangle, render2d::NineSlice},
+    game::molding::{MoldingGrid, MoldingOutcome, MOLDING_GRID_SIZE, MOLDING_TEMPLATES},
+    App, Scene, SceneCommand,
+};
+
+const CELL_SIZE: f32 = 32.0;
+const PREVIEW_CELL_SIZE: f32 = 6.0;
+const PADDING: f32 = 16.0;
+const TITLE_HEIGHT: f32 = 20.0;
+const BUTTON_WIDTH: f32 = 96.0;
+const BUTTON_HEIGHT: f32 = 28.0;
+const GRID_PX: f32 = CELL_SIZE * MOLDING_GRID_SIZE as f32;
+const PREVIEW_PX: f32 = PREVIEW_CELL_SIZE * MOLDING_GRID_SIZE as f32;
+
+/// Menu for pressing a lump of clay into a pot or bowl. Opened over the gameplay scene by crafting clay with anything.
+/// How it ended is written to `outcome` for the gameplay scene to pick up.
+pub struct Molding {
+    grid: MoldingGrid,
+    outcome: Rc<RefCell<Option<MoldingOutcome>>>,
+}
+
+/// Where everything in the menu is on the screen.
+struct Layout {
+    panel: Rectangle,
+    grid: Rectangle,
+    finish_button: Rectangle,
+    cancel_button: Rectangle,
+}
+
+impl Scene for Molding {
+    fn update(&mut self, app: &App) -> SceneCommand {
+        if !app.mouse_left_clicked {
+            return SceneCommand::None;
+        }
+        let layout = Layout::new(app);
+        let mouse = nalgebra_glm::vec2(app.mouse_x as f32, app.mouse_y as f32);
+
+        if let Some((x, y)) = layout.cell_at(mouse) {
+            self.grid.press(x, y);
+        } else if layout.finish_button.contains(mouse) {
+            if let Some(template) = self.grid.matching_template() {
+                return self.finish(MoldingOutcome::Made(template.output));
+            }
+        } else if layout.cancel_button.contains(mouse) {
+            return self.finish(MoldingOutcome::Cancelled);
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, app: &App) {
+        let layout = Layout::new(app);
+        let mouse = nalgebra_glm::vec2(app.mouse_x as f32, app.mouse_y as f32);
+        let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+        let font = app.renderer.get_font_id_from_name("font").unwrap();
+        app.renderer.set_font(font);
+
+        // Darken the game behind the menu
+        app.renderer
+            .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.5));
+        app.renderer
+            .fill_rect(Rectangle::new(0.0, 0.0, screen_size.x, screen_size.y));
+        app.renderer.render_nine_slice(
+            NineSlice {
+                texture: app
+                    .renderer
+                    .get_texture_id_from_name("nine-slice-test")
+                    .unwrap(),
+                border: 8.0,
+            },
+            layout.panel,
+        );
+
+        let title = match self.grid.matching_template() {
+            Some(template) => format!("Molding - {}", template.output.name()),
+            None => format!("Molding - {} clay left", self.grid.clay_left()),
+        };
+        app.renderer.draw_text(
+            layout.panel.pos + nalgebra_glm::vec2(PADDING, PADDING),
+            &title,
+        );
+
+        // The clay, with one texel of the clay texture per cell
+        let clay = app.renderer.get_texture_id_from_name("clay").unwrap();
+        let hovered_cell = layout.cell_at(mouse);
+        for y in 0..MOLDING_GRID_SIZE {
+            for x in 0..MOLDING_GRID_SIZE {
+                let cell_rect = layout.cell_rect(x, y);
+                if self.grid.cells[y][x] {
+                    app.renderer.copy_texture(
+                        cell_rect,
+                        clay,
+                        Rectangle::new(x as f32, y as f32, 1.0, 1.0),
+                    );
+                } else {
+                    app.renderer
+                        .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, 0.3));
+                    app.renderer.fill_rect(cell_rect);
+                }
+                if hovered_cell == Some((x, y)) {
+                    app.renderer
+                        .set_color(nalgebra_glm::vec4(1.0, 1.0, 1.0, 0.3));
+                    app.renderer.fill_rect(cell_rect);
+                }
+            }
+        }
+
+        // Small previews of the shapes that can be made
+        app.renderer
+            .set_color(nalgebra_glm::vec4(1.0, 1.0, 1.0, 0.6));
+        for (i, template) in MOLDING_TEMPLATES.iter().enumerate() {
+            let preview_pos = nalgebra_glm::vec2(
+                layout.grid.pos.x + GRID_PX + PADDING,
+                layout.grid.pos.y + i as f32 * (PREVIEW_PX + PADDING),
+            );
+            for y in 0..MOLDING_GRID_SIZE {
+                for x in 0..MOLDING_GRID_SIZE {
+                    if template.has_clay(x, y) {
+                        app.renderer.fill_rect(Rectangle::new(
+                            preview_pos.x + x as f32 * PREVIEW_CELL_SIZE,
+                            preview_pos.y + y as f32 * PREVIEW_CELL_SIZE,
+                            PREVIEW_CELL_SIZE,
+                            PREVIEW_CELL_SIZE,
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Buttons. Finish is only lit up once the clay is in a usable shape
+        let can_finish = self.grid.matching_template().is_some();
+        for (button, text, enabled) in [
+            (layout.finish_button, "Finish", can_finish),
+            (layout.cancel_button, "Cancel", true),
+        ] {
+            let alpha = if enabled && button.contains(mouse) {
+                0.8
+            } else if enabled {
+                0.5
+            } else {
+                0.2
+            };
+            app.renderer
+                .set_color(nalgebra_glm::vec4(0.0, 0.0, 0.0, alpha));
+            app.renderer.fill_rect(button);
+            let text_size = app.renderer.measure_text(text);
+            app.renderer
+                .draw_text(button.pos + (button.size - text_size) * 0.5, text);
+        }
+    }
+}
+
+impl Molding {
+    pub fn new(outcome: Rc<RefCell<Option<MoldingOutcome>>>) -> Self {
+        Self {
+            grid: MoldingGrid::new(),
+            outcome,
+        }
+    }
+
+    fn finish(&mut self, outcome: MoldingOutcome) -> SceneCommand {
+        *self.outcome.borrow_mut() = Some(outcome);
+        SceneCommand::Pop
+    }
+}
+
+impl Layout {
+    fn new(app: &App) -> Self {
+        let screen_size: nalgebra_glm::Vec2 = app.window_size.cast();
+        let panel_size = nalgebra_glm::vec2(
+            3.0 * PADDING + GRID_PX + PREVIEW_PX,
+            4.0 * PADDING + TITLE_HEIGHT + GRID_PX + BUTTON_HEIGHT,
+        );
+        let panel_pos = (screen_size - panel_size) * 0.5;
+        let grid_pos = panel_pos + nalgebra_glm::vec2(PADDING, 2.0 * PADDING + TITLE_HEIGHT);
+        let button_y = grid_pos.y + GRID_PX + PADDING;
+        Self {
+            panel: Rectangle::new(panel_pos.x, panel_pos.y, panel_size.x, panel_size.y),
+            grid: Rectangle::new(grid_pos.x, grid_pos.y, GRID_PX, GRID_PX),
+            finish_button: Rectangle::new(grid_pos.x, button_y, BUTTON_WIDTH, BUTTON_HEIGHT),
+            cancel_button: Rectangle::new(
+                grid_pos.x + GRID_PX - BUTTON_WIDTH,
+                button_y,
+                BUTTON_WIDTH,
+                BUTTON_HEIGHT,
+            ),
+        }
+    }
+
+    fn cell_rect(&self, x: usize, y: usize) -> Rectangle {
+        Rectangle::new(
+            self.grid.pos.x + x as f32 * CELL_SIZE,
+            self.grid.pos.y + y as f32 * CELL_SIZE,
+            CELL_SIZE,
+            CELL_SIZE,
+        )
+    }
+
+    /// The grid cell under a point on the screen.
+    fn cell_at(&self, point: nalgebra_glm::Vec2) -> Option<(usize, usize)> {
+        if !self.grid.contains(point) {
+            return None;
+        }
+        let cell = (point - self.grid.pos) / CELL_SIZE;
+        Some((cell.x as usize, cell.y as usize))
+    }
+}
@@ -1,15 +1,25 @@
 #version 330 core
 
 in vec3 texCoord;
+flat in vec3 flatTexCoord;
+flat in vec3 flatNormal;
 in vec3 color;
 in vec3 Normal_cameraspace;
 in vec3 LightDirection_cameraspace;
 in vec4 light_space_pos; // For shadow mapping
+in vec3 world_position; // For point lights
 
 out vec4 Color;
 
 uniform sampler2D texture0;
 uniform sampler2D shadow_map;
+uniform vec4 u_tint;
+
+#define MAX_POINT_LIGHTS 8
+uniform int u_point_light_count;
+uniform vec3 u_point_light_positions[MAX_POINT_LIGHTS];
+uniform vec3 u_point_light_colors[MAX_POINT_LIGHTS];
+uniform float u_point_light_radii[MAX_POINT_LIGHTS];
 
 vec2 poissonDisk[9] = vec2[](
   vec2( -1.0,  1.0 ),
@@ -45,6 +55,20 @@ float calc_shadow_factor()
     return visibility;
 }
 
+// Light added by every point light that reaches the fragment
+vec3 calc_point_lights(vec3 material, vec3 n)
+{
+    vec3 total = vec3(0.0);
+    for (int i = 0; i < u_point_light_count; i++) {
+        vec3 to_light = u_point_light_positions[i] - world_position;
+        float dist = length(to_light);
+        float falloff = clamp(1.0 - dist / u_point_light_radii[i], 0.0, 1.0);
+        float cos_theta = clamp(dot(n, to_light / max(dist, 0.0001)), 0.0, 1.0);
+        total += material * u_point_light_colors[i] * falloff * falloff * (0.25 + 0.75 * cos_theta);
+    }
+    return total;
+}
+
 vec3 tint(vec3 material, vec3 tint, float strength) {
     float luminance = dot(material, vec3(0.299, 0.587, 0.114));
     return mix(
@@ -56,12 +80,12 @@ vec3 tint(vec3 material, vec3 tint, float strength) {
 
 void main()
 {
-    vec4 texture_color = texture(texture0, texCoord.xy);
+    vec4 texture_color = texture(texture0, flatTexCoord.z > 0.5 ? flatTexCoord.xy : texCoord.xy);
     float texture_alpha = texture_color.w;
     vec3 material_color = texture_color.xyz;
 
     // Normal of the computed fragment, in camera space
-    vec3 n = normalize( Normal_cameraspace );
+    vec3 n = normalize( flatTexCoord.z > 1.5 ? flatNormal : Normal_cameraspace );
     // Direction of the light, in camera space
     vec3 l = normalize( LightDirection_cameraspace );
     // Direction to the eye, in camera space
@@ -84,9 +108,10 @@ void main()
     diff = floor(diff * levels) / levels;
 
     vec3 color = mix(shadow, light_tinted, diff);
+    color += calc_point_lights(material_color, n);
 
     color = color / (color + vec3(1.0));
     color = pow(color, vec3(1.0 / 2.2));
 
-    Color = vec4(color, texture_alpha);
+    Color = vec4(color * u_tint.rgb, texture_alpha * u_tint.a);
 }
\ No newline at end of file
@@ -12,10 +12,15 @@ layout (location = 2) in vec3 texture_coord;
 layout (location = 3) in vec3 Color;
 
 out vec3 texCoord;
+// Terrain triangles take these from their last vertex. A z above 0 means the whole triangle uses the flat texture
+// coordinate, and above 1 means it uses the flat normal too. Every other mesh has a z of 0
+flat out vec3 flatTexCoord;
+flat out vec3 flatNormal;
 out vec3 color;
 out vec3 Normal_cameraspace;
 out vec3 LightDirection_cameraspace;
 out vec4 light_space_pos; // For shadow mapping
+out vec3 world_position; // For point lights
 
 void main()
 {
@@ -29,6 +34,9 @@ void main()
 
     gl_Position = uv;
     texCoord = texture_coord;
+    flatTexCoord = texture_coord;
+    flatNormal = Normal_modelspace;
     color = Color;
     light_space_pos = light_mvp * u_model_matrix * vec4(Position, 1.0); // For shadow mapping
+    world_position = (u_model_matrix * vec4(Position, 1.0)).xyz;
 }
\ No newline at end of file